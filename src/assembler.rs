@@ -0,0 +1,870 @@
+/*
+* Assembler for CHIPPER 2.11 source, the syntax the bundled .SRC files are
+* written in, and the disassembler writes.
+*
+* A line holds an optional label, then an instruction or a directive, then
+* an optional `;` comment. Labels end with a colon, or start the line
+* without one (`SCORE DW 0`); a label on a line of its own names the next
+* thing assembled. Mnemonics and symbols are not case sensitive.
+*
+* Directives: `ALIGN ON|OFF` (instructions and `DW` on even addresses, on
+* by default), `DA 'text'`, `DB` and `DW` lists, `DS size`, `name = value`
+* and `name EQU value`, `DEFINE`/`UNDEF` with `IFDEF`/`IFUND`/`ELSE`/
+* `ENDIF`, `END`, `INCLUDE file`, `ORG address`, and `OPTION` to restrict
+* the instruction set (`CHIP8`, `CHIP48`, `SCHIP10`, `SCHIP11`). Only
+* binary output is produced, so `OPTION BINARY` is accepted and the HP48
+* formats are not; `USED` and `XREF` only affect CHIPPER's listing and are
+* ignored. A file may not include itself, directly or through others.
+*
+* Numbers are decimal, `#` hex, or `$` binary with `.` for a clear bit, as
+* in `$..1111..`; `?` (or `.`) is the current address. Expressions combine
+* them with, from the loosest binding: `|`, `^`, `&`, then `<` and `>`
+* (shifts) and `\` (division), then `+` and `-`, then `*`, `/` and `%`,
+* and the unary `-`, `+` and `~`.
+*
+* VBRIX.SRC was written for another assembler; its mnemonics (`MOV`,
+* `SKEQ`, `JSR`, `SPRITE`, `MVI`, ... with registers written `Rx` or `Vx`)
+* are accepted alongside CHIPPER's. That assembler evaluated operators
+* right to left, so the operands of its own mnemonics are too.
+*/
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use chip8::PROGRAM_BASE;
+use instruction::Instruction;
+
+// Programs have to fit in the 12-bit address space
+const ADDRESS_LIMIT: u32 = 0x1000;
+
+const DIRECTIVES: [&str; 19] = [
+      "=", "ALIGN", "DA", "DB", "DEFINE", "DS", "DW", "ELSE", "END", "ENDIF", "EQU",
+      "IFDEF", "IFUND", "INCLUDE", "OPTION", "ORG", "UNDEF", "USED", "XREF"
+];
+
+const MNEMONICS: [&str; 26] = [
+      "ADD", "AND", "CALL", "CLS", "DRW", "EXIT", "HIGH", "JP", "LD", "LOW", "OR", "RET", "RND",
+      "SCD", "SCL", "SCR", "SE", "SHL", "SHR", "SKNP", "SKP", "SNE", "SUB", "SUBN", "SYS", "XOR"
+];
+
+// The assembler VBRIX.SRC was written for also knows ADD, AND, CLS, OR, SHL, SHR, SUB and XOR
+const VBRIX_MNEMONICS: [&str; 22] = [
+      "ADI", "BCD", "FONT", "GDELAY", "HALT", "JMP", "JSR", "KEY", "LDR", "MOV", "MVI", "RANDOM",
+      "RSB", "RTS", "SDELAY", "SKEQ", "SKNE", "SKPR", "SKUP", "SPRITE", "SSOUND", "STR"
+];
+
+/**
+* An assembled program: its bytes, the address they load at, and the
+* address of every label, under the name it was defined with. The Octo
+* compiler (`octo::compile`) produces the same.
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct Program {
+      pub base: u16,
+      pub data: Vec<u8>,
+      pub labels: BTreeMap<String, u16>
+}
+
+impl Program {
+      // The name of a label at `addr`, to show next to the address; the first one by name if there are several.
+      pub fn label_at(&self, addr: u16) -> Option<&str> {
+            self.labels.iter().find(|&(_, &a)| a == addr).map(|(name, _)| name.as_str())
+      }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AsmError {
+      // The included file the error is in, if it is not in the main source
+      pub file: Option<String>,
+      pub line: usize,
+      pub message: String
+}
+
+impl fmt::Display for AsmError {
+      fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self.file {
+                  Some(ref file) => write!(f, "{} line {}: {}", file, self.line, self.message),
+                  None => write!(f, "line {}: {}", self.line, self.message)
+            }
+      }
+}
+
+impl Error for AsmError {}
+
+// The instruction sets `OPTION` chooses from, smallest first.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+enum Target {
+      Chip48,
+      Schip10,
+      Schip11
+}
+
+impl Target {
+      fn option(&self) -> &'static str {
+            match *self {
+                  Target::Chip48 => "CHIP48",
+                  Target::Schip10 => "SCHIP10",
+                  Target::Schip11 => "SCHIP11"
+            }
+      }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Operand {
+      V(u8),
+      // `V0-Vx`, as in `LDR V0-V2`
+      Range(u8, u8),
+      I,
+      IndirectI,
+      Dt,
+      St,
+      K,
+      F,
+      Hf,
+      B,
+      R,
+      Expr(String)
+}
+
+fn register(text: &str) -> Option<u8> {
+      let mut chars = text.chars();
+      match (chars.next(), chars.next(), chars.next()) {
+            (Some('V'), Some(digit), None) | (Some('v'), Some(digit), None)
+                  | (Some('R'), Some(digit), None) | (Some('r'), Some(digit), None) =>
+                  digit.to_digit(16).map(|x| x as u8),
+            _ => None
+      }
+}
+
+fn operand(text: &str) -> Operand {
+      if let Some(x) = register(text) {
+            return Operand::V(x);
+      }
+      let mut range = text.splitn(2, '-');
+      if let (Some(from), Some(to)) = (range.next(), range.next()) {
+            if let (Some(x), Some(y)) = (register(from.trim()), register(to.trim())) {
+                  return Operand::Range(x, y);
+            }
+      }
+      match text.to_uppercase().as_str() {
+            "I" => Operand::I,
+            "[I]" => Operand::IndirectI,
+            "DT" => Operand::Dt,
+            "ST" => Operand::St,
+            "K" => Operand::K,
+            "F" => Operand::F,
+            "HF" => Operand::Hf,
+            "B" => Operand::B,
+            "R" => Operand::R,
+            _ => Operand::Expr(String::from(text))
+      }
+}
+
+// Splits on commas outside of quotes; a trailing comma adds nothing.
+fn split_operands(text: &str) -> Vec<String> {
+      let mut operands = Vec::new();
+      let mut current = String::new();
+      let mut quote = None;
+      for c in text.chars() {
+            match (c, quote) {
+                  (',', None) => operands.push(current.trim().to_string()),
+                  ('\'', None) | ('"', None) => quote = Some(c),
+                  (_, Some(q)) if c == q => quote = None,
+                  _ => ()
+            }
+            if c == ',' && quote.is_none() {
+                  current.clear();
+            } else {
+                  current.push(c);
+            }
+      }
+      if !current.trim().is_empty() {
+            operands.push(current.trim().to_string());
+      }
+      operands
+}
+
+// The line without its comment.
+fn strip_comment(line: &str) -> &str {
+      let mut quote = None;
+      for (i, c) in line.char_indices() {
+            match (c, quote) {
+                  (';', None) => return &line[..i],
+                  ('\'', None) | ('"', None) => quote = Some(c),
+                  (_, Some(q)) if c == q => quote = None,
+                  _ => ()
+            }
+      }
+      line
+}
+
+// The first word of `text` and what follows it.
+fn split_word(text: &str) -> (&str, &str) {
+      let text = text.trim_start();
+      match text.find(char::is_whitespace) {
+            Some(end) => (&text[..end], text[end..].trim_start()),
+            None => (text, "")
+      }
+}
+
+fn is_keyword(word: &str) -> bool {
+      let word = word.to_uppercase();
+      DIRECTIVES.contains(&word.as_str()) || MNEMONICS.contains(&word.as_str())
+            || VBRIX_MNEMONICS.contains(&word.as_str())
+}
+
+// The characters of a `DA` string, which doubles its quote to include one.
+fn string(text: &str) -> Result<Vec<u8>, String> {
+      let quote = match text.chars().next() {
+            Some(q) if q == '\'' || q == '"' => q,
+            _ => return Err(format!("expected a quoted string, found `{}`", text))
+      };
+      let mut bytes = Vec::new();
+      let mut chars = text[1..].chars().peekable();
+      while let Some(c) = chars.next() {
+            if c == quote {
+                  if chars.peek() == Some(&quote) {
+                        chars.next();
+                  } else if chars.next().is_none() {
+                        return Ok(bytes);
+                  } else {
+                        return Err(format!("unexpected text after the string `{}`", text));
+                  }
+            }
+            if !c.is_ascii() {
+                  return Err(format!("`{}` is not ASCII", c));
+            }
+            bytes.push(c as u8);
+      }
+      Err(String::from("unterminated string"))
+}
+
+#[derive(Clone, Debug)]
+struct Symbol {
+      // As first written; the table is keyed by the upper case name
+      name: String,
+      // Unknown in the first pass for values that refer to later labels
+      value: Option<i64>,
+      label: bool
+}
+
+// Parses and evaluates one expression.
+struct Expression<'a> {
+      text: &'a [u8],
+      pos: usize,
+      symbols: &'a BTreeMap<String, Symbol>,
+      here: u32,
+      // `a-b-c` is `a-(b-c)` in VBRIX.SRC's assembler
+      right_to_left: bool,
+      // Set when a symbol has no value yet
+      unresolved: Option<String>
+}
+
+impl<'a> Expression<'a> {
+      fn peek(&mut self) -> Option<u8> {
+            while self.pos < self.text.len() && (self.text[self.pos] as char).is_whitespace() {
+                  self.pos += 1;
+            }
+            self.text.get(self.pos).cloned()
+      }
+
+      fn parse(&mut self) -> Result<i64, String> {
+            let value = self.binary(0)?;
+            match self.peek() {
+                  None => Ok(value),
+                  Some(c) => Err(format!("unexpected `{}` in expression", c as char))
+            }
+      }
+
+      // Binary operators bind by level, loosest first.
+      fn binary(&mut self, level: usize) -> Result<i64, String> {
+            const LEVELS: [&[u8]; 6] = [b"|", b"^", b"&", b"<>\\", b"+-", b"*/%"];
+            if level == LEVELS.len() {
+                  return self.unary();
+            }
+            let mut value = self.binary(level + 1)?;
+            while let Some(op) = self.peek() {
+                  if !LEVELS[level].contains(&op) {
+                        break;
+                  }
+                  self.pos += 1;
+                  let rhs = if self.right_to_left { self.binary(level)? } else { self.binary(level + 1)? };
+                  value = match op {
+                        b'|' => value | rhs,
+                        b'^' => value ^ rhs,
+                        b'&' => value & rhs,
+                        b'<' => value.checked_shl(rhs as u32).unwrap_or(0),
+                        b'>' => value.checked_shr(rhs as u32).unwrap_or(0),
+                        b'+' => value.wrapping_add(rhs),
+                        b'-' => value.wrapping_sub(rhs),
+                        b'*' => value.wrapping_mul(rhs),
+                        _ if rhs == 0 => return Err(String::from("division by zero")),
+                        b'/' | b'\\' => value / rhs,
+                        _ => value % rhs
+                  };
+            }
+            Ok(value)
+      }
+
+      fn unary(&mut self) -> Result<i64, String> {
+            match self.peek() {
+                  Some(b'-') => { self.pos += 1; Ok(self.unary()?.wrapping_neg()) },
+                  Some(b'+') => { self.pos += 1; self.unary() },
+                  Some(b'~') => { self.pos += 1; Ok(!self.unary()?) },
+                  Some(b'(') => {
+                        self.pos += 1;
+                        let value = self.binary(0)?;
+                        if self.peek() != Some(b')') {
+                              return Err(String::from("missing `)`"));
+                        }
+                        self.pos += 1;
+                        Ok(value)
+                  },
+                  Some(b'?') | Some(b'.') => { self.pos += 1; Ok(self.here as i64) },
+                  Some(b'#') => { self.pos += 1; self.number(16) },
+                  Some(b'$') => { self.pos += 1; self.number(2) },
+                  Some(c) if c.is_ascii_digit() => self.number(10),
+                  Some(c) if c.is_ascii_alphabetic() || c == b'_' => self.symbol(),
+                  Some(c) => Err(format!("unexpected `{}` in expression", c as char)),
+                  None => Err(String::from("expected a value"))
+            }
+      }
+
+      fn number(&mut self, radix: u32) -> Result<i64, String> {
+            let start = self.pos;
+            let mut value: i64 = 0;
+            while let Some(&c) = self.text.get(self.pos) {
+                  // Binary numbers draw clear bits as dots
+                  let digit = match c {
+                        b'.' if radix == 2 => 0,
+                        _ => match (c as char).to_digit(radix) {
+                              Some(d) => d,
+                              None => break
+                        }
+                  };
+                  value = value.wrapping_mul(radix as i64).wrapping_add(digit as i64);
+                  self.pos += 1;
+            }
+            if self.pos == start {
+                  return Err(String::from("expected digits"));
+            }
+            Ok(value)
+      }
+
+      fn symbol(&mut self) -> Result<i64, String> {
+            let start = self.pos;
+            while let Some(&c) = self.text.get(self.pos) {
+                  if !(c.is_ascii_alphanumeric() || c == b'_') {
+                        break;
+                  }
+                  self.pos += 1;
+            }
+            let name = String::from_utf8_lossy(&self.text[start..self.pos]).to_uppercase();
+            match self.symbols.get(&name) {
+                  Some(&Symbol { value: Some(value), .. }) => Ok(value),
+                  _ => {
+                        self.unresolved = Some(name);
+                        Ok(0)
+                  }
+            }
+      }
+}
+
+// IFDEF/IFUND state: whether lines are assembled, and whether ELSE was seen.
+struct Condition {
+      active: bool,
+      parent_active: bool,
+      in_else: bool,
+      line: usize
+}
+
+// One pass over the source. The first one only sizes things up and
+// defines the labels; the second one has every label, and emits.
+struct Pass<'a> {
+      final_pass: bool,
+      symbols: &'a mut BTreeMap<String, Symbol>,
+      defines: BTreeSet<String>,
+      include_dir: Option<PathBuf>,
+      // The files being assembled, outermost first, so none includes itself
+      including: Vec<PathBuf>,
+      address: u32,
+      align: bool,
+      // Set for the operands of VBRIX.SRC's mnemonics
+      right_to_left: bool,
+      target: Target,
+      conditions: Vec<Condition>,
+      // Labels on lines of their own, waiting for the next thing assembled
+      pending: Vec<String>,
+      base: u32,
+      data: Vec<u8>,
+      emitted: bool,
+      ended: bool,
+      // An error from an included file, reported with its own file and line
+      included_error: Option<AsmError>
+}
+
+impl<'a> Pass<'a> {
+      fn active(&self) -> bool {
+            match self.conditions.last() {
+                  Some(c) => c.active,
+                  None => true
+            }
+      }
+
+      // The value of an expression; a symbol with no value yet is only an
+      // error in the final pass.
+      fn eval(&self, text: &str) -> Result<Option<i64>, String> {
+            let mut expression = Expression {
+                  text: text.as_bytes(),
+                  pos: 0,
+                  symbols: &*self.symbols,
+                  here: self.address,
+                  right_to_left: self.right_to_left,
+                  unresolved: None
+            };
+            let value = expression.parse()?;
+            match expression.unresolved {
+                  Some(name) if self.final_pass => Err(format!("symbol `{}` is not defined", name)),
+                  Some(_) => Ok(None),
+                  None => Ok(Some(value))
+            }
+      }
+      fn value(&self, text: &str) -> Result<i64, String> {
+            Ok(self.eval(text)?.unwrap_or(0))
+      }
+      // A value that has to be known in the first pass, since it moves what follows.
+      fn known(&self, text: &str) -> Result<i64, String> {
+            match self.eval(text)? {
+                  Some(value) => Ok(value),
+                  None => Err(format!("`{}` must only use symbols defined before it", text))
+            }
+      }
+      fn ranged(&self, text: &str, min: i64, max: i64, what: &str) -> Result<i64, String> {
+            let value = self.value(text)?;
+            if value < min || value > max {
+                  return Err(format!("{} does not fit in {}", value, what));
+            }
+            Ok(value)
+      }
+      fn byte(&self, text: &str) -> Result<u8, String> {
+            Ok(self.ranged(text, -0x80, 0xFF, "a byte")? as u8)
+      }
+      fn nibble(&self, text: &str) -> Result<u8, String> {
+            Ok(self.ranged(text, 0, 0xF, "a nibble")? as u8)
+      }
+      fn addr(&self, text: &str) -> Result<u16, String> {
+            Ok(self.ranged(text, 0, ADDRESS_LIMIT as i64 - 1, "an address")? as u16)
+      }
+
+      fn define(&mut self, name: &str, value: Option<i64>, label: bool) -> Result<(), String> {
+            let key = name.to_uppercase();
+            if !self.final_pass {
+                  if is_keyword(name) || register(name).is_some() {
+                        return Err(format!("`{}` is reserved and cannot name a symbol", name));
+                  }
+                  if self.symbols.contains_key(&key) {
+                        return Err(format!("symbol `{}` is already defined", name));
+                  }
+            }
+            // Labels keep the address of the first pass; the two passes lay out the same
+            if !(self.final_pass && label) {
+                  self.symbols.insert(key, Symbol { name: String::from(name), value, label });
+            }
+            Ok(())
+      }
+
+      fn bind_labels(&mut self) -> Result<(), String> {
+            for name in std::mem::take(&mut self.pending) {
+                  let address = self.address as i64;
+                  self.define(&name, Some(address), true)?;
+            }
+            Ok(())
+      }
+
+      fn emit(&mut self, bytes: &[u8]) -> Result<(), String> {
+            self.bind_labels()?;
+            if !self.emitted {
+                  self.emitted = true;
+                  self.base = self.address;
+            }
+            if self.address < self.base {
+                  return Err(format!("#{:03X} lies before the start of the program, #{:03X}", self.address, self.base));
+            }
+            let end = self.address + bytes.len() as u32;
+            if end > ADDRESS_LIMIT {
+                  return Err(format!("the program runs past #{:03X}", ADDRESS_LIMIT - 1));
+            }
+            let start = (self.address - self.base) as usize;
+            if self.data.len() < start + bytes.len() {
+                  self.data.resize(start + bytes.len(), 0);
+            }
+            self.data[start..start + bytes.len()].copy_from_slice(bytes);
+            self.address = end;
+            Ok(())
+      }
+
+      // Pads to an even address, when aligning.
+      fn align(&mut self) -> Result<(), String> {
+            if self.align && self.address % 2 == 1 {
+                  // The padding belongs to no label
+                  let pending = std::mem::take(&mut self.pending);
+                  self.emit(&[0])?;
+                  self.pending = pending;
+            }
+            Ok(())
+      }
+
+      fn source(&mut self, text: &str, file: Option<&str>) -> Result<(), AsmError> {
+            for (number, line) in text.lines().enumerate() {
+                  if self.ended {
+                        break;
+                  }
+                  if let Err(message) = self.line(line, number + 1) {
+                        // Errors in included files come with their own file and line
+                        return Err(self.included_error.take().unwrap_or(AsmError {
+                              file: file.map(String::from),
+                              line: number + 1,
+                              message
+                        }));
+                  }
+            }
+            Ok(())
+      }
+
+      fn line(&mut self, line: &str, number: usize) -> Result<(), String> {
+            let line = strip_comment(line);
+            let (mut word, mut rest) = split_word(line);
+            if word.is_empty() {
+                  return Ok(());
+            }
+
+            // `name:`, glued to what follows or not, or a word that is no keyword before one
+            let mut label = None;
+            let next = split_word(rest).0;
+            if let Some(colon) = word.find(':') {
+                  label = Some(&word[..colon]);
+                  let after = line.trim_start()[colon + 1..].trim_start();
+                  let next = split_word(after);
+                  word = next.0;
+                  rest = next.1;
+            } else if !is_keyword(word) && (is_keyword(next) || next.starts_with('=')) {
+                  label = Some(word);
+                  let next = split_word(rest);
+                  word = next.0;
+                  rest = next.1;
+            }
+            // `name=value`
+            if word.starts_with('=') && word.len() > 1 {
+                  rest = line[line.find('=').unwrap() + 1..].trim();
+                  word = "=";
+            }
+            let directive = word.to_uppercase();
+
+            // Conditionals are followed even where nothing is assembled
+            match directive.as_str() {
+                  "IFDEF" | "IFUND" => {
+                        let parent_active = self.active();
+                        let defined = self.defines.contains(&rest.trim().to_uppercase());
+                        self.conditions.push(Condition {
+                              active: parent_active && defined == (directive == "IFDEF"),
+                              parent_active,
+                              in_else: false,
+                              line: number
+                        });
+                        return if parent_active { self.bind_label(label) } else { Ok(()) };
+                  },
+                  "ELSE" => {
+                        let c = match self.conditions.last_mut() {
+                              Some(c) if !c.in_else => c,
+                              _ => return Err(String::from("ELSE without IFDEF"))
+                        };
+                        c.in_else = true;
+                        c.active = c.parent_active && !c.active;
+                        return Ok(());
+                  },
+                  "ENDIF" => {
+                        if self.conditions.pop().is_none() {
+                              return Err(String::from("ENDIF without IFDEF"));
+                        }
+                        return Ok(());
+                  },
+                  _ => ()
+            }
+            if !self.active() {
+                  return Ok(());
+            }
+
+            match directive.as_str() {
+                  "=" | "EQU" => {
+                        let name = match label {
+                              Some(name) => name,
+                              None => return Err(format!("{} needs a symbol name", word))
+                        };
+                        let value = if self.final_pass { Some(self.value(rest)?) } else { self.eval(rest)? };
+                        return self.define(name, value, false);
+                  },
+                  "" => return self.bind_label(label),
+                  _ => self.bind_label(label)?
+            }
+
+            let operands = split_operands(rest);
+            let argument = || operands.first().map(|s| s.to_uppercase()).unwrap_or_default();
+            match directive.as_str() {
+                  "ALIGN" => match argument().as_str() {
+                        "ON" => self.align = true,
+                        "OFF" => self.align = false,
+                        _ => return Err(String::from("ALIGN is either ON or OFF"))
+                  },
+                  "DA" => {
+                        let mut bytes = Vec::new();
+                        for text in operands.iter() {
+                              bytes.extend(string(text)?);
+                        }
+                        self.emit(&bytes)?;
+                  },
+                  "DB" => {
+                        let mut bytes = Vec::new();
+                        for text in operands.iter() {
+                              bytes.push(self.byte(text)?);
+                        }
+                        self.emit(&bytes)?;
+                  },
+                  "DW" => {
+                        self.align()?;
+                        let mut bytes = Vec::new();
+                        for text in operands.iter() {
+                              let word = self.ranged(text, -0x8000, 0xFFFF, "a word")? as u16;
+                              bytes.push((word >> 8) as u8);
+                              bytes.push(word as u8);
+                        }
+                        self.emit(&bytes)?;
+                  },
+                  "DS" => {
+                        let size = self.known(rest)?;
+                        if size < 0 || self.address as i64 + size > ADDRESS_LIMIT as i64 {
+                              return Err(format!("cannot reserve {} bytes", size));
+                        }
+                        self.emit(&vec![0; size as usize])?;
+                  },
+                  "DEFINE" => { self.defines.insert(argument()); },
+                  "UNDEF" => { self.defines.remove(&argument()); },
+                  "END" => {
+                        self.ended = true;
+                  },
+                  "INCLUDE" => {
+                        let name = rest.trim().trim_matches(|c| c == '\'' || c == '"');
+                        let path = match self.include_dir {
+                              Some(ref dir) => dir.join(name),
+                              None => PathBuf::from(name)
+                        };
+                        let text = std::fs::read_to_string(&path).map_err(|e| format!("cannot include `{}`: {}", name, e))?;
+                        let file = std::fs::canonicalize(&path).unwrap_or(path.clone());
+                        if self.including.contains(&file) {
+                              return Err(format!("`{}` includes itself", name));
+                        }
+                        self.including.push(file);
+                        let outer = std::mem::replace(&mut self.include_dir, path.parent().map(Path::to_path_buf));
+                        let result = self.source(&text, Some(name));
+                        self.include_dir = outer;
+                        self.including.pop();
+                        if let Err(e) = result {
+                              let message = e.message.clone();
+                              self.included_error = Some(e);
+                              return Err(message);
+                        }
+                  },
+                  "OPTION" => match argument().as_str() {
+                        "BINARY" => (),
+                        "CHIP8" | "CHIP48" => self.target = Target::Chip48,
+                        "SCHIP10" => self.target = Target::Schip10,
+                        "SCHIP11" => self.target = Target::Schip11,
+                        "STRING" | "HPHEX" | "HPASC" => return Err(String::from("only OPTION BINARY output is supported")),
+                        other => return Err(format!("unknown option `{}`", other))
+                  },
+                  "ORG" => {
+                        let address = self.known(rest)?;
+                        if address < 0 || address >= ADDRESS_LIMIT as i64 {
+                              return Err(format!("ORG {} lies outside of memory", address));
+                        }
+                        self.address = address as u32;
+                  },
+                  "USED" | "XREF" => (),
+                  _ => {
+                        self.align()?;
+                        let operands: Vec<Operand> = operands.iter().map(|text| operand(text)).collect();
+                        self.right_to_left = VBRIX_MNEMONICS.contains(&directive.as_str());
+                        let instruction = self.instruction(&directive, &operands);
+                        self.right_to_left = false;
+                        let (instruction, target) = instruction?;
+                        // Operands are only final in the final pass
+                        if self.final_pass && target > self.target {
+                              return Err(format!("`{}` needs OPTION {}", word, target.option()));
+                        }
+                        let opcode = instruction.encode();
+                        self.emit(&[(opcode >> 8) as u8, opcode as u8])?;
+                  }
+            }
+            Ok(())
+      }
+
+      fn bind_label(&mut self, label: Option<&str>) -> Result<(), String> {
+            if let Some(name) = label {
+                  if name.is_empty() {
+                        return Err(String::from("empty label"));
+                  }
+                  self.pending.push(String::from(name));
+            }
+            Ok(())
+      }
+
+      // An instruction, and the smallest instruction set that has it.
+      fn instruction(&self, mnemonic: &str, operands: &[Operand]) -> Result<(Instruction, Target), String> {
+            use instruction::Instruction::*;
+            use self::Operand::*;
+
+            let chip48 = |i: Instruction| -> Result<(Instruction, Target), String> { Ok((i, Target::Chip48)) };
+            match (mnemonic, operands) {
+                  ("CLS", &[]) => chip48(Clear),
+                  ("RET", &[]) | ("RTS", &[]) => chip48(Return),
+                  ("SCR", &[]) => Ok((ScrollRight, Target::Schip11)),
+                  ("SCL", &[]) => Ok((ScrollLeft, Target::Schip11)),
+                  ("SCD", &[Expr(ref n)]) => Ok((ScrollDown(self.nibble(n)?), Target::Schip11)),
+                  ("EXIT", &[]) => Ok((Exit, Target::Schip10)),
+                  ("HALT", &[]) => chip48(Exit),
+                  ("LOW", &[]) => Ok((Lores, Target::Schip10)),
+                  ("HIGH", &[]) => Ok((Hires, Target::Schip10)),
+                  ("SYS", &[Expr(ref a)]) => chip48(Sys(self.addr(a)?)),
+                  ("JP", &[Expr(ref a)]) | ("JMP", &[Expr(ref a)]) => chip48(Jump(self.addr(a)?)),
+                  ("JP", &[V(0), Expr(ref a)]) => chip48(JumpOffset(self.addr(a)?)),
+                  ("CALL", &[Expr(ref a)]) | ("JSR", &[Expr(ref a)]) => chip48(Call(self.addr(a)?)),
+                  ("SE", &[V(x), Expr(ref b)]) | ("SKEQ", &[V(x), Expr(ref b)]) => chip48(SkipEqImm(x, self.byte(b)?)),
+                  ("SE", &[V(x), V(y)]) | ("SKEQ", &[V(x), V(y)]) => chip48(SkipEqReg(x, y)),
+                  ("SNE", &[V(x), Expr(ref b)]) | ("SKNE", &[V(x), Expr(ref b)]) => chip48(SkipNeImm(x, self.byte(b)?)),
+                  ("SNE", &[V(x), V(y)]) | ("SKNE", &[V(x), V(y)]) => chip48(SkipNeReg(x, y)),
+                  ("LD", &[V(x), Expr(ref b)]) | ("MOV", &[V(x), Expr(ref b)]) => chip48(LoadImm(x, self.byte(b)?)),
+                  ("LD", &[V(x), V(y)]) | ("MOV", &[V(x), V(y)]) => chip48(Move(x, y)),
+                  ("LD", &[I, Expr(ref a)]) | ("MVI", &[Expr(ref a)]) => chip48(LoadI(self.addr(a)?)),
+                  ("LD", &[V(x), Dt]) | ("GDELAY", &[V(x)]) => chip48(LoadDelay(x)),
+                  ("LD", &[V(x), K]) | ("KEY", &[V(x)]) => chip48(WaitKey(x)),
+                  ("LD", &[Dt, V(x)]) | ("SDELAY", &[V(x)]) => chip48(SetDelay(x)),
+                  ("LD", &[St, V(x)]) | ("SSOUND", &[V(x)]) => chip48(SetSound(x)),
+                  ("LD", &[F, V(x)]) | ("FONT", &[V(x)]) => chip48(Font(x)),
+                  ("LD", &[Hf, V(x)]) => Ok((BigFont(x), Target::Schip10)),
+                  ("LD", &[B, V(x)]) | ("BCD", &[V(x)]) => chip48(Bcd(x)),
+                  ("LD", &[IndirectI, V(x)]) | ("STR", &[Range(0, x)]) => chip48(StoreRegs(x)),
+                  ("LD", &[V(x), IndirectI]) | ("LDR", &[Range(0, x)]) => chip48(LoadRegs(x)),
+                  ("LD", &[R, V(x)]) => Ok((StoreFlags(x), Target::Schip10)),
+                  ("LD", &[V(x), R]) => Ok((LoadFlags(x), Target::Schip10)),
+                  ("ADD", &[V(x), Expr(ref b)]) => chip48(AddImm(x, self.byte(b)?)),
+                  ("ADD", &[V(x), V(y)]) => chip48(Add(x, y)),
+                  ("ADD", &[I, V(x)]) | ("ADI", &[V(x)]) => chip48(AddI(x)),
+                  ("OR", &[V(x), V(y)]) => chip48(Or(x, y)),
+                  ("AND", &[V(x), V(y)]) => chip48(And(x, y)),
+                  ("XOR", &[V(x), V(y)]) => chip48(Xor(x, y)),
+                  ("SUB", &[V(x), V(y)]) => chip48(Sub(x, y)),
+                  ("SUBN", &[V(x), V(y)]) | ("RSB", &[V(x), V(y)]) => chip48(SubN(x, y)),
+                  ("SHR", &[V(x)]) => chip48(ShiftRight(x, 0)),
+                  ("SHR", &[V(x), V(y)]) => chip48(ShiftRight(x, y)),
+                  ("SHL", &[V(x)]) => chip48(ShiftLeft(x, 0)),
+                  ("SHL", &[V(x), V(y)]) => chip48(ShiftLeft(x, y)),
+                  ("RND", &[V(x), Expr(ref b)]) | ("RANDOM", &[V(x), Expr(ref b)]) => chip48(Random(x, self.byte(b)?)),
+                  ("DRW", &[V(x), V(y), Expr(ref n)]) | ("SPRITE", &[V(x), V(y), Expr(ref n)]) => {
+                        let n = self.nibble(n)?;
+                        // 16x16 sprites came with SUPER-CHIP
+                        Ok((Draw(x, y, n), if n == 0 { Target::Schip10 } else { Target::Chip48 }))
+                  },
+                  ("SKP", &[V(x)]) | ("SKPR", &[V(x)]) => chip48(SkipKey(x)),
+                  ("SKNP", &[V(x)]) | ("SKUP", &[V(x)]) => chip48(SkipNotKey(x)),
+                  _ if is_keyword(mnemonic) => Err(format!("wrong operands for `{}`", mnemonic)),
+                  _ => Err(format!("unknown instruction `{}`", mnemonic))
+            }
+      }
+}
+
+/**
+* Assembles CHIPPER source. Symbols given to `define` start out defined
+* for `IFDEF`, as if the source began with `DEFINE` lines for them, e.g.
+* `Assembler::new().define("SUPER").assemble(&source)` for the SUPER-CHIP
+* build of BLINKY.SRC.
+*/
+#[derive(Clone, Debug, Default)]
+pub struct Assembler {
+      defines: BTreeSet<String>,
+      include_dir: Option<PathBuf>,
+      // The file being assembled, if any, which its includes cannot include again
+      file: Option<PathBuf>
+}
+
+impl Assembler {
+      pub fn new() -> Assembler {
+            Assembler::default()
+      }
+
+      pub fn define(mut self, name: &str) -> Assembler {
+            self.defines.insert(name.to_uppercase());
+            self
+      }
+
+      // Where `INCLUDE` looks for files; the working directory by default.
+      pub fn include_dir<P: AsRef<Path>>(mut self, dir: P) -> Assembler {
+            self.include_dir = Some(dir.as_ref().to_path_buf());
+            self
+      }
+
+      pub fn assemble(&self, source: &str) -> Result<Program, AsmError> {
+            let mut symbols = BTreeMap::new();
+            let mut program = None;
+            for &final_pass in [false, true].iter() {
+                  let mut pass = Pass {
+                        final_pass,
+                        symbols: &mut symbols,
+                        defines: self.defines.clone(),
+                        include_dir: self.include_dir.clone(),
+                        including: self.file.iter().cloned().collect(),
+                        address: PROGRAM_BASE as u32,
+                        align: true,
+                        right_to_left: false,
+                        target: Target::Chip48,
+                        conditions: Vec::new(),
+                        pending: Vec::new(),
+                        base: PROGRAM_BASE as u32,
+                        data: Vec::new(),
+                        emitted: false,
+                        ended: false,
+                        included_error: None
+                  };
+                  pass.source(source, None)?;
+                  let end = source.lines().count();
+                  if let Some(c) = pass.conditions.last() {
+                        return Err(AsmError { file: None, line: c.line, message: String::from("IFDEF without ENDIF") });
+                  }
+                  pass.bind_labels().map_err(|message| AsmError { file: None, line: end, message })?;
+                  program = Some(Program { base: pass.base as u16, data: pass.data, labels: BTreeMap::new() });
+            }
+
+            let mut program = program.unwrap();
+            for symbol in symbols.values().filter(|s| s.label) {
+                  program.labels.insert(symbol.name.clone(), symbol.value.unwrap_or(0) as u16);
+            }
+            Ok(program)
+      }
+
+      // Assembles a file, with `INCLUDE` relative to it.
+      pub fn assemble_file<P: AsRef<Path>>(&self, path: P) -> Result<Program, Box<dyn Error>> {
+            let path = path.as_ref();
+            let source = std::fs::read_to_string(path)?;
+            let mut assembler = self.clone();
+            if assembler.include_dir.is_none() {
+                  assembler.include_dir = path.parent().map(Path::to_path_buf);
+            }
+            assembler.file = Some(std::fs::canonicalize(path)?);
+            Ok(assembler.assemble(&source)?)
+      }
+}
+
+// Assembles CHIPPER source with no symbols defined beforehand.
+pub fn assemble(source: &str) -> Result<Program, AsmError> {
+      Assembler::new().assemble(source)
+}
@@ -0,0 +1,102 @@
+/*
+* XO-CHIP sound: a 128-bit, 1-bit sample pattern played back in a loop
+* for as long as the sound timer is non-zero.
+* --> https://github.com/JohnEarnest/Octo/blob/gh-pages/docs/XO-ChipSpecification.md
+*/
+
+use core::f64::consts::SQRT_2;
+
+pub const SAMPLE_RATE: u32 = 44100;
+pub const PATTERN_SIZE: usize = 16;
+const PATTERN_BITS: f64 = (PATTERN_SIZE * 8) as f64;
+const AMPLITUDE: i16 = 16384;
+// Pitch register value for which the pattern plays back at 4000 bits per second
+const BASE_PITCH: u8 = 64;
+const BASE_RATE: f64 = 4000.0;
+// 2^(n/48): the rate doubles every 48 pitch steps. Tabulated, since `no_std`
+// builds have no `powf`.
+const SEMITONE_STEPS: [f64; 48] = [
+      1.0, 1.0145453349375237, 1.029302236643492, 1.0442737824274138,
+      1.0594630943592953, 1.0748733399206964, 1.0905077326652577, 1.1063695328888334,
+      1.122462048309373, 1.1387886347566916, 1.155352696872273, 1.1721576888192515,
+      1.189207115002721, 1.2065045308005218, 1.2240535433046553, 1.241857812073484,
+      1.2599210498948732, 1.2782470235604306, 1.2968395546510096, 1.3157025203336377,
+      1.3348398541700344, 1.3542555469368927, 1.3739536474580891, 1.3939382634489994,
+      SQRT_2, 1.4347837723110002, 1.4556531828421873, 1.4768261459394993,
+      1.4983070768766815, 1.5201004551491148, 1.5422108254079407, 1.5646427984077742,
+      1.5874010519681994, 1.6104903319492543, 1.6339154532411, 1.6576813007680873,
+      1.681792830507429, 1.7062550705226855, 1.731073122012286, 1.7562521603732995,
+      1.7817974362806785, 1.8077142767822019, 1.8340080864093424, 1.8606843483042932,
+      1.8877486253633868, 1.9152065613971474, 1.9430638823072117, 1.9713263972803752
+];
+
+// 0b11001100 repeated is a 1000Hz square wave at the base rate, which
+// keeps plain CHIP-8 programs (which never load a pattern) beeping.
+const DEFAULT_PATTERN: [u8; PATTERN_SIZE] = [0xCC; PATTERN_SIZE];
+
+#[derive(Clone)]
+pub struct Audio {
+      pub pattern: [u8; PATTERN_SIZE],
+      pub pitch: u8,
+      // Playback position within the pattern, in bits
+      position: f64
+}
+
+impl Default for Audio {
+      fn default() -> Audio {
+            Audio::new()
+      }
+}
+
+impl Audio {
+      pub fn new() -> Audio {
+            Audio {
+                  pattern: DEFAULT_PATTERN,
+                  pitch: BASE_PITCH,
+                  position: 0.0
+            }
+      }
+
+      pub fn reset(&mut self) {
+            *self = Audio::new();
+      }
+
+      // Playback position within the pattern, in bits; kept by save states.
+      pub fn position(&self) -> f64 {
+            self.position
+      }
+      pub fn set_position(&mut self, position: f64) {
+            self.position = position % PATTERN_BITS;
+      }
+
+      // Pattern bits played per second for the current pitch.
+      pub fn playback_rate(&self) -> f64 {
+            let steps = self.pitch as i32 - BASE_PITCH as i32;
+            let octave = steps.div_euclid(48);
+            let scale = if octave >= 0 { (1 << octave) as f64 } else { 1.0 / (1 << -octave) as f64 };
+            BASE_RATE * scale * SEMITONE_STEPS[steps.rem_euclid(48) as usize]
+      }
+
+      /**
+      * Fills `out` with mono samples at `SAMPLE_RATE`. While `playing` is false
+      * the output is silent and the playback position is held, so consecutive
+      * calls produce one continuous waveform.
+      */
+      pub fn render(&mut self, playing: bool, out: &mut [i16]) {
+            if !playing {
+                  for sample in out.iter_mut() {
+                        *sample = 0;
+                  }
+                  return;
+            }
+
+            let step = self.playback_rate() / SAMPLE_RATE as f64;
+            for sample in out.iter_mut() {
+                  let bit = self.position as usize;
+                  let set = self.pattern[bit / 8] & (0b10000000 >> (bit % 8)) != 0;
+                  *sample = if set { AMPLITUDE } else { -AMPLITUDE };
+
+                  self.position = (self.position + step) % PATTERN_BITS;
+            }
+      }
+}
@@ -0,0 +1,60 @@
+/*
+* Headless benchmark of the decode cache against the plain interpreter.
+* Both run the same ROM for the same number of instructions, with no input,
+* and must end up in exactly the same state.
+*/
+
+use std::time::Instant;
+
+use chip8::{Chip, ChipError, Quirks};
+
+// Timers tick once per this many instructions, as at the default speed
+const INSTRUCTIONS_PER_FRAME: u64 = 8;
+
+// Runs `path` for up to `instructions` instructions; returns the machine and the seconds taken.
+fn run_engine(path: &str, quirks: Quirks, instructions: u64, cached: bool) -> Result<(Chip, f64), ChipError> {
+      let mut chip = Chip::new(quirks);
+      chip.set_decode_cache(cached);
+      chip.load_rom(path)?;
+
+      let start = Instant::now();
+      for n in 0..instructions {
+            if n % INSTRUCTIONS_PER_FRAME == 0 {
+                  chip.tick_timers();
+            }
+            // Faults are kept in `chip.state`
+            let _ = chip.cycle();
+            if !chip.running() {
+                  break;
+            }
+      }
+      Ok((chip, start.elapsed().as_secs_f64()))
+}
+
+pub fn run(path: &str, quirks: Quirks, instructions: u64) {
+      let mut results = Vec::new();
+      for &(name, cached) in [("interpreter", false), ("decode cache", true)].iter() {
+            match run_engine(path, quirks, instructions, cached) {
+                  Ok((chip, secs)) => {
+                        let count = chip.instruction_count();
+                        println!("{:>12}: {} instructions in {:.3} s ({:.1} MIPS)", name, count, secs, count as f64 / secs / 1e6);
+                        results.push((chip, secs));
+                  },
+                  Err(e) => {
+                        println!("{}", e);
+                        return;
+                  }
+            }
+      }
+
+      let (ref plain, plain_secs) = results[0];
+      let (ref cached, cached_secs) = results[1];
+      println!("Speedup: {:.2}x", plain_secs / cached_secs);
+      if plain.snapshot() == cached.snapshot() && plain.state == cached.state {
+            println!("Final states are identical");
+      } else {
+            println!("Final states differ!");
+            plain.dump();
+            cached.dump();
+      }
+}
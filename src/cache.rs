@@ -0,0 +1,50 @@
+/*
+* Decoded instructions, kept per address so that loops are only decoded on
+* their first pass. An entry depends on the two bytes at its address, so
+* writing a byte invalidates the entries for it and the address before it.
+*
+* Programs only change memory through Fx33, Fx55 and 5xy2, which the machine
+* reports here; anything else that writes to `Chip::memory` directly must
+* call `Chip::invalidate_code`.
+*/
+
+use alloc::vec::Vec;
+
+use instruction;
+use instruction::Instruction;
+
+pub struct DecodeCache {
+      entries: Vec<Option<Instruction>>
+}
+
+impl DecodeCache {
+      pub fn new(size: usize) -> DecodeCache {
+            DecodeCache { entries: vec![None; size] }
+      }
+
+      // The instruction at `addr`, decoding it on a miss; addresses wrap around memory.
+      pub fn get(&mut self, memory: &[u8], addr: u16) -> Instruction {
+            let addr = addr as usize % memory.len();
+            if let Some(instruction) = self.entries[addr] {
+                  return instruction;
+            }
+            let opcode = (memory[addr] as u16) << 8 | memory[(addr + 1) % memory.len()] as u16;
+            let instruction = instruction::decode(opcode);
+            self.entries[addr] = Some(instruction);
+            instruction
+      }
+
+      // Forgets the entries that read any of memory[start..start + len].
+      pub fn invalidate(&mut self, start: usize, len: usize) {
+            let size = self.entries.len();
+            for addr in (start + size - 1)..(start + size + len) {
+                  self.entries[addr % size] = None;
+            }
+      }
+
+      pub fn clear(&mut self) {
+            for entry in self.entries.iter_mut() {
+                  *entry = None;
+            }
+      }
+}
@@ -3,24 +3,52 @@
 * http://devernay.free.fr/hacks/chip8/C8TECH10.HTM -- chip8 technical reference
 * http://stevelosh.com/blog/2016/12/chip8-cpu/ -- a chip8 emulator in commmon lisp
 */
-extern crate rand;
-extern crate time;
-
-use std::time::Duration;
-use std::thread;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::error::Error;
+#[cfg(feature = "std")]
 use std::fs::File;
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "std")]
 use std::io::prelude::*;
 
-const MEMORY_SIZE: usize = 4096;
+use audio::{Audio, PATTERN_SIZE};
+use cache::DecodeCache;
+use framebuffer;
+use framebuffer::Framebuffer;
+use instruction;
+use instruction::Instruction;
+use rng::{Rng, XorShift};
+use snapshot;
+use snapshot::{Snapshot, SnapshotError};
+use timing;
+use timing::Timing;
+
+// Defaults for `ChipBuilder`; the largest machine any supported program expects
+pub const MEMORY_SIZE: usize = 0x10000;
 const NUM_REGISTERS: usize = 16;
-const STACK_SIZE: usize = 16;
-const PROGRAM_BASE: u16 = 0x200;
+pub const STACK_SIZE: usize = 16;
+pub const PROGRAM_BASE: u16 = 0x200;
 pub const DISPLAY_W: usize = 64;
 pub const DISPLAY_H: usize = 32;
-pub const DISPLAY_SIZE: usize = DISPLAY_W * DISPLAY_H;
+pub const HIRES_W: usize = 128;
+pub const HIRES_H: usize = 64;
+// The framebuffer is sized for SUPER-CHIP's high resolution mode, which is
+// also the largest display geometry a machine can have; smaller screens only
+// use the first width * height cells.
+pub const DISPLAY_SIZE: usize = HIRES_W * HIRES_H;
+pub const NUM_PLANES: usize = 2;
 const KEYPAD_SIZE: usize = 16;
+const NUM_RPL_FLAGS: usize = 16;
+const DEFAULT_SEED: u64 = 0x5EED;
+pub const FONT_BASE: u16 = 0x00;
+pub const BIG_FONT_BASE: u16 = 0x50;
 
-const FONT_SET: [u8; 80] = [
+pub const FONT_SET: [u8; 80] = [
       0xF0, 0x90, 0x90, 0x90, 0xF0,
       0x20, 0x60, 0x20, 0x20, 0x70,
       0xF0, 0x10, 0xF0, 0x80, 0xF0,
@@ -39,6 +67,26 @@ const FONT_SET: [u8; 80] = [
       0xF0, 0x80, 0xF0, 0x80, 0x80
 ];
 
+// SUPER-CHIP 10-byte hex digits, used by Fx30
+pub const BIG_FONT_SET: [u8; 160] = [
+      0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C,
+      0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C,
+      0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF,
+      0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C,
+      0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06,
+      0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C,
+      0x3E, 0x7C, 0xE0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C,
+      0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60,
+      0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C,
+      0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C,
+      0x3C, 0x7E, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3,
+      0xFC, 0xFE, 0xC3, 0xC3, 0xFE, 0xFE, 0xC3, 0xC3, 0xFE, 0xFC,
+      0x3C, 0x7E, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0x7E, 0x3C,
+      0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC,
+      0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFC, 0xC0, 0xC0, 0xFF, 0xFF,
+      0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFC, 0xC0, 0xC0, 0xC0, 0xC0
+];
+
 /**
 * Some helper functions.
 */
@@ -54,174 +102,755 @@ fn sub_borrow(a: u8, b: u8) -> (u8, u8) {
       (a.wrapping_sub(b), if not_borrow {0x1} else {0x0})
 }
 
+/**
+* Toggles for the opcodes whose behaviour differs between interpreters.
+* --> https://github.com/Timendus/chip8-test-suite#quirks-test
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct Quirks {
+      // 8xy6/8xyE shift Vy into Vx instead of shifting Vx in place
+      pub shift_uses_vy: bool,
+      // Fx55/Fx65 leave I pointing just past the last register transferred
+      pub load_store_increment: bool,
+      // Bnnn is read as Bxnn and jumps to xnn + Vx instead of nnn + V0
+      pub jump_uses_vx: bool,
+      // 8xy1/8xy2/8xy3 reset VF to 0
+      pub vf_reset: bool,
+      // sprites wrap around the screen edges instead of being clipped
+      pub wrap_sprites: bool,
+      // Dxyn waits for the next 60Hz tick before drawing
+      pub display_wait: bool
+}
+
+impl Quirks {
+      // The original COSMAC VIP interpreter.
+      pub fn vip() -> Quirks {
+            Quirks {
+                  shift_uses_vy: true,
+                  load_store_increment: true,
+                  jump_uses_vx: false,
+                  vf_reset: true,
+                  wrap_sprites: false,
+                  display_wait: true
+            }
+      }
+      // SUPER-CHIP 1.1 on the HP48.
+      pub fn schip() -> Quirks {
+            Quirks {
+                  shift_uses_vy: false,
+                  load_store_increment: false,
+                  jump_uses_vx: true,
+                  vf_reset: false,
+                  wrap_sprites: false,
+                  display_wait: false
+            }
+      }
+      // XO-CHIP, as implemented by Octo.
+      pub fn xochip() -> Quirks {
+            Quirks {
+                  shift_uses_vy: true,
+                  load_store_increment: true,
+                  jump_uses_vx: false,
+                  vf_reset: false,
+                  wrap_sprites: true,
+                  display_wait: false
+            }
+      }
+      pub fn from_name(name: &str) -> Option<Quirks> {
+            match name {
+                  "default" => Some(Quirks::default()),
+                  "vip" | "chip8" => Some(Quirks::vip()),
+                  "schip" => Some(Quirks::schip()),
+                  "xochip" => Some(Quirks::xochip()),
+                  _ => None
+            }
+      }
+}
+
+/**
+* Faults raised while loading or running a program. Runtime faults carry the
+* address and opcode of the offending instruction; the machine is left as it
+* was right before that instruction, so it can be inspected or reset.
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChipError {
+      // Opcode that no supported instruction set defines
+      UndefinedInstruction { pc: u16, opcode: u16 },
+      // 0nnn, a call into native COSMAC VIP code
+      MachineCode { pc: u16, opcode: u16 },
+      // 2nnn with the whole stack in use
+      StackOverflow { pc: u16, opcode: u16 },
+      // 00EE with an empty stack
+      StackUnderflow { pc: u16, opcode: u16 },
+      // Access to `addr`, which lies past the end of memory
+      MemoryOutOfBounds { pc: u16, opcode: u16, addr: usize },
+      // Execution ran into memory that holds no program (opcode 0000)
+      Runaway { pc: u16, opcode: u16 },
+      // The ROM file could not be read
+      RomIo { path: String, cause: String },
+      // The ROM holds no data at all
+      RomEmpty,
+      // The ROM does not fit between the load address and the end of memory
+      RomTooLarge { size: usize, max: usize },
+      // Programs cannot start at this address
+      InvalidLoadAddress(u16),
+      // A `ChipBuilder` setting no machine can have
+      InvalidConfig(String)
+}
+
+impl fmt::Display for ChipError {
+      fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match *self {
+                  ChipError::UndefinedInstruction { pc, opcode } =>
+                        write!(f, "undefined instruction {:04X} at {:03X}", opcode, pc),
+                  ChipError::MachineCode { pc, opcode } =>
+                        write!(f, "machine language subroutine {:04X} at {:03X} is unsupported", opcode, pc),
+                  ChipError::StackOverflow { pc, opcode } =>
+                        write!(f, "stack overflow in {:04X} at {:03X}", opcode, pc),
+                  ChipError::StackUnderflow { pc, opcode } =>
+                        write!(f, "stack underflow in {:04X} at {:03X}", opcode, pc),
+                  ChipError::MemoryOutOfBounds { pc, opcode, addr } =>
+                        write!(f, "out of bounds memory access to {:X} in {:04X} at {:03X}", addr, opcode, pc),
+                  ChipError::Runaway { pc, opcode } =>
+                        write!(f, "runaway execution: reached {:04X} at {:03X}, outside of any program", opcode, pc),
+                  ChipError::RomIo { ref path, ref cause } =>
+                        write!(f, "could not read ROM `{}`: {}", path, cause),
+                  ChipError::RomEmpty =>
+                        write!(f, "ROM is empty"),
+                  ChipError::RomTooLarge { size, max } =>
+                        write!(f, "ROM is {} bytes long, but at most {} fit in memory", size, max),
+                  ChipError::InvalidLoadAddress(addr) =>
+                        write!(f, "cannot load programs at {:X}", addr),
+                  ChipError::InvalidConfig(ref cause) =>
+                        write!(f, "invalid machine configuration: {}", cause)
+            }
+      }
+}
+
+#[cfg(feature = "std")]
+impl Error for ChipError {}
+
+/**
+* Whether the machine is still executing instructions, and if not, why it
+* stopped. Once stopped, `cycle` does nothing until the next reset.
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub enum MachineState {
+      Running,
+      // Jumped to itself (1nnn at nnn), the usual way programs end
+      Halted { pc: u16 },
+      // Left through 00FD
+      Exited,
+      // Executed as many instructions as the watchdog allows
+      WatchdogExpired { instructions: u64 },
+      // Stopped by a fault; see `ChipError`
+      Faulted(ChipError)
+}
+
+impl fmt::Display for MachineState {
+      fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match *self {
+                  MachineState::Running => write!(f, "running"),
+                  MachineState::Halted { pc } => write!(f, "halted at {:03X}", pc),
+                  MachineState::Exited => write!(f, "exited"),
+                  MachineState::WatchdogExpired { instructions } =>
+                        write!(f, "watchdog expired after {} instructions", instructions),
+                  MachineState::Faulted(ref e) => write!(f, "faulted: {}", e)
+            }
+      }
+}
+
+/**
+* Things an instruction can do that a debugger may want to stop on, besides
+* touching memory or changing a register.
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+      // Fx18 started the sound timer from zero
+      SoundStart,
+      // Fx0A found no key pressed, and started waiting for one
+      KeyWait,
+      // Dxyn turned off a pixel that was on
+      Collision
+}
+
+impl fmt::Display for Event {
+      fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match *self {
+                  Event::SoundStart => write!(f, "sound"),
+                  Event::KeyWait => write!(f, "keywait"),
+                  Event::Collision => write!(f, "collision")
+            }
+      }
+}
+
+/**
+* What the last instruction did to memory, and the events it raised; only
+* recorded while tracing is on (see `Chip::set_trace`). Fetching the
+* instruction itself, including the address word of F000, is not a read.
+*/
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Trace {
+      // Memory read and written as data, as (address, length)
+      pub reads: Vec<(usize, usize)>,
+      pub writes: Vec<(usize, usize)>,
+      pub events: Vec<Event>
+}
+
+impl Trace {
+      pub fn clear(&mut self) {
+            self.reads.clear();
+            self.writes.clear();
+            self.events.clear();
+      }
+      pub fn read(&self, addr: usize) -> bool {
+            self.reads.iter().any(|&(start, len)| addr >= start && addr < start + len)
+      }
+      pub fn wrote(&self, addr: usize) -> bool {
+            self.writes.iter().any(|&(start, len)| addr >= start && addr < start + len)
+      }
+      pub fn raised(&self, event: Event) -> bool {
+            self.events.contains(&event)
+      }
+}
+
 pub struct Chip {
-      pub memory: [u8; MEMORY_SIZE],
+      // Sized by `ChipBuilder::memory_size`; addresses past the end wrap around
+      pub memory: Vec<u8>,
       pub registers: [u8; NUM_REGISTERS],
-      pub running: bool,
-      pub stack: [u16; STACK_SIZE],
+      pub state: MachineState,
+      pub stack: Vec<u16>,
       pub stack_pointer: usize,
       pub program_counter: u16,
       pub rom: String,
       rom_size: usize,
+      // Where ROMs are copied to, and execution starts
+      load_address: u16,
+      rom_hash: u32,
+      // Hex digit sprites, copied into memory on every reset
+      font: [u8; 80],
+      font_base: u16,
+      big_font: [u8; 160],
+      big_font_base: u16,
       pub index: u16,
-      pub display: [bool; DISPLAY_SIZE],
-      pub clock: u64,
+      // One bitmap per XO-CHIP bitplane; see `pixel` for the composite picture
+      pub display: Framebuffer,
+      // Screen size in each resolution
+      lores_size: (usize, usize),
+      hires_size: (usize, usize),
+      pub plane: u8,
+      pub hires: bool,
+      pub rpl: [u8; NUM_RPL_FLAGS],
       pub delay_timer: u8,
       pub sound_timer: u8,
       pub key_pressed: u8,
       pub keypad: [bool; KEYPAD_SIZE],
-      wait: u8
+      pub audio: Audio,
+      pub quirks: Quirks,
+      // What `cycle` reports each instruction as costing
+      pub timing: Timing,
+      // Generator behind Cxkk, restarted from `seed` on every reset
+      rng: Box<dyn Rng>,
+      seed: u64,
+      // Opcode being executed, for error reports
+      opcode: u16,
+      // Instructions executed since the last reset, and the optional limit
+      instructions: u64,
+      watchdog: Option<u64>,
+      // Decoded instructions by address, when enabled
+      cache: Option<DecodeCache>,
+      // The last instruction's memory accesses and events, when enabled
+      trace: Option<Trace>,
+      wait: u8,
+      vblank: bool
 }
 
-impl Chip {
-      pub fn new() -> Chip {
+/**
+* Chooses the machine `build` makes: how much memory and stack it has, where
+* programs and the fonts live, the size of its screen, how its interpreter
+* behaves and where Cxkk gets its numbers. The presets describe historical
+* machines, and any setting can be changed on top of them, as in
+* `ChipBuilder::eti660().seed(42).build()`.
+*/
+pub struct ChipBuilder {
+      memory_size: usize,
+      stack_depth: usize,
+      program_start: u16,
+      font: [u8; 80],
+      font_base: u16,
+      big_font: [u8; 160],
+      big_font_base: u16,
+      lores_size: (usize, usize),
+      hires_size: (usize, usize),
+      quirks: Quirks,
+      rng: Option<Box<dyn Rng>>,
+      seed: u64
+}
+
+impl Default for ChipBuilder {
+      fn default() -> ChipBuilder {
+            ChipBuilder::new()
+      }
+}
+
+impl ChipBuilder {
+      // 64K of memory, programs at 0x200 and the usual fonts at the bottom of memory.
+      pub fn new() -> ChipBuilder {
+            ChipBuilder {
+                  memory_size: MEMORY_SIZE,
+                  stack_depth: STACK_SIZE,
+                  program_start: PROGRAM_BASE,
+                  font: FONT_SET,
+                  font_base: FONT_BASE,
+                  big_font: BIG_FONT_SET,
+                  big_font_base: BIG_FONT_BASE,
+                  lores_size: (DISPLAY_W, DISPLAY_H),
+                  hires_size: (HIRES_W, HIRES_H),
+                  quirks: Quirks::default(),
+                  rng: None,
+                  seed: DEFAULT_SEED
+            }
+      }
+      // The COSMAC VIP: 4K of RAM and room for 12 return addresses.
+      pub fn vip() -> ChipBuilder {
+            ChipBuilder::new().memory_size(0x1000).stack_depth(12).quirks(Quirks::vip())
+      }
+      // The ETI-660: a VIP-style interpreter with programs at 0x600 and a 64x48 screen.
+      pub fn eti660() -> ChipBuilder {
+            ChipBuilder::vip().program_start(0x600).display(64, 48)
+      }
+      // SUPER-CHIP 1.1 on the HP48, with 4K of memory.
+      pub fn schip() -> ChipBuilder {
+            ChipBuilder::new().memory_size(0x1000).quirks(Quirks::schip())
+      }
+      // XO-CHIP, with the full 64K address space.
+      pub fn xochip() -> ChipBuilder {
+            ChipBuilder::new().quirks(Quirks::xochip())
+      }
+      pub fn from_name(name: &str) -> Option<ChipBuilder> {
+            match name {
+                  "default" => Some(ChipBuilder::new()),
+                  "vip" | "chip8" => Some(ChipBuilder::vip()),
+                  "eti660" => Some(ChipBuilder::eti660()),
+                  "schip" => Some(ChipBuilder::schip()),
+                  "xochip" => Some(ChipBuilder::xochip()),
+                  _ => None
+            }
+      }
+
+      // In bytes, up to 64K.
+      pub fn memory_size(mut self, size: usize) -> ChipBuilder {
+            self.memory_size = size;
+            self
+      }
+      // How many calls may be nested.
+      pub fn stack_depth(mut self, depth: usize) -> ChipBuilder {
+            self.stack_depth = depth;
+            self
+      }
+      // Where ROMs are loaded and execution starts.
+      pub fn program_start(mut self, addr: u16) -> ChipBuilder {
+            self.program_start = addr;
+            self
+      }
+      // The 5-byte hex digits used by Fx29, and where they are stored.
+      pub fn font(mut self, set: &[u8; 80], base: u16) -> ChipBuilder {
+            self.font = *set;
+            self.font_base = base;
+            self
+      }
+      // The 10-byte hex digits used by Fx30, and where they are stored.
+      pub fn big_font(mut self, set: &[u8; 160], base: u16) -> ChipBuilder {
+            self.big_font = *set;
+            self.big_font_base = base;
+            self
+      }
+      // Screen size in low resolution, at most HIRES_W x HIRES_H.
+      pub fn display(mut self, width: usize, height: usize) -> ChipBuilder {
+            self.lores_size = (width, height);
+            self
+      }
+      // Screen size after 00FF, at most HIRES_W x HIRES_H.
+      pub fn hires_display(mut self, width: usize, height: usize) -> ChipBuilder {
+            self.hires_size = (width, height);
+            self
+      }
+      pub fn quirks(mut self, quirks: Quirks) -> ChipBuilder {
+            self.quirks = quirks;
+            self
+      }
+      /**
+      * Cxkk's generator, e.g. one backed by a board's hardware RNG; a
+      * `XorShift` by default. Either way it is seeded on every reset.
+      */
+      pub fn rng(mut self, rng: Box<dyn Rng>) -> ChipBuilder {
+            self.rng = Some(rng);
+            self
+      }
+      // See `Chip::seed_rng`.
+      pub fn seed(mut self, seed: u64) -> ChipBuilder {
+            self.seed = seed;
+            self
+      }
+
+      // Checks that the settings describe a workable machine, and makes a reset one.
+      pub fn build(self) -> Result<Chip, ChipError> {
+            if self.memory_size == 0 || self.memory_size > MEMORY_SIZE {
+                  return Err(ChipError::InvalidConfig(format!("{} bytes of memory, at most {} are addressable",
+                                                              self.memory_size, MEMORY_SIZE)));
+            }
+            if self.stack_depth == 0 {
+                  return Err(ChipError::InvalidConfig(String::from("the stack must hold at least one entry")));
+            }
+            if self.program_start as usize + 2 > self.memory_size {
+                  return Err(ChipError::InvalidLoadAddress(self.program_start));
+            }
+            let fonts = [(self.font_base, self.font.len()), (self.big_font_base, self.big_font.len())];
+            for &(base, len) in fonts.iter() {
+                  if base as usize + len > self.memory_size {
+                        return Err(ChipError::InvalidConfig(format!("a font at {:X} does not fit in {} bytes of memory",
+                                                                    base, self.memory_size)));
+                  }
+            }
+            for &(width, height) in [self.lores_size, self.hires_size].iter() {
+                  if width == 0 || width > HIRES_W || height == 0 || height > HIRES_H {
+                        return Err(ChipError::InvalidConfig(format!("a {}x{} display, at most {}x{} are supported",
+                                                                    width, height, HIRES_W, HIRES_H)));
+                  }
+            }
+
+            let rng: Box<dyn Rng> = match self.rng {
+                  Some(rng) => rng,
+                  None => Box::new(XorShift::new(self.seed))
+            };
             let mut c = Chip {
-                  memory: [0; MEMORY_SIZE],
+                  memory: vec![0; self.memory_size],
                   registers: [0; NUM_REGISTERS],
-                  running: false,
-                  stack: [0; STACK_SIZE],
+                  state: MachineState::Running,
+                  stack: vec![0; self.stack_depth],
                   stack_pointer: 0,
-                  program_counter: PROGRAM_BASE,
+                  program_counter: self.program_start,
                   rom: String::from(""),
                   rom_size: 0,
+                  load_address: self.program_start,
+                  rom_hash: 0,
+                  font: self.font,
+                  font_base: self.font_base,
+                  big_font: self.big_font,
+                  big_font_base: self.big_font_base,
                   index: 0,
-                  display: [false; DISPLAY_SIZE],
-                  clock: time::precise_time_ns(),
+                  display: Framebuffer::new(),
+                  lores_size: self.lores_size,
+                  hires_size: self.hires_size,
+                  plane: 0x1,
+                  hires: false,
+                  rpl: [0; NUM_RPL_FLAGS],
                   delay_timer: 0,
                   sound_timer: 0,
                   key_pressed: 0x10,
                   keypad: [false; KEYPAD_SIZE],
-                  wait: 0x10
+                  audio: Audio::new(),
+                  quirks: self.quirks,
+                  timing: Timing::Instructions,
+                  rng,
+                  seed: self.seed,
+                  opcode: 0,
+                  instructions: 0,
+                  watchdog: None,
+                  cache: None,
+                  trace: None,
+                  wait: 0x10,
+                  vblank: false
             };
             c.reset();
-            c
+            Ok(c)
       }
+}
 
-      fn display_write_byte(&mut self, x: usize, y: usize, byte: u8) -> bool {
-            // TODO:
-            // 1 - wrap around
-            // 2 - set VF if pixel erased -- DONE
-            let mut idx = y * DISPLAY_W + x;
-            let mut overlap = false;
-            for j in 0..8 {
-                  if idx < DISPLAY_SIZE {
-                        let mask = 0b00000001 << (7 - j);
-                        let old = self.display[idx].clone();
-                        self.display[idx] ^= (byte & mask) != 0;
-                        if old == true && self.display[idx] == false {
-                              overlap = true;
-                        }
-                        idx += 1;
+impl Chip {
+      // The default machine: 64K of memory and programs at 0x200; see `ChipBuilder` for others.
+      pub fn new(quirks: Quirks) -> Chip {
+            ChipBuilder::new().quirks(quirks).build().expect("default machine configuration is valid")
+      }
+
+      // Width and height of the active resolution.
+      pub fn display_width(&self) -> usize {
+            if self.hires { self.hires_size.0 } else { self.lores_size.0 }
+      }
+      pub fn display_height(&self) -> usize {
+            if self.hires { self.hires_size.1 } else { self.lores_size.1 }
+      }
+      // Colour index of a pixel, combining both bitplanes.
+      pub fn pixel(&self, x: usize, y: usize) -> u8 {
+            self.display.pixel(x, y)
+      }
+
+      fn display_write_byte(&mut self, plane: usize, x: usize, y: usize, byte: u8) -> bool {
+            let (width, height) = (self.display_width(), self.display_height());
+            // Rows and columns past the edge are either wrapped or clipped,
+            // depending on the quirk setting.
+            let (mut x, mut y) = (x, y);
+            if x >= width || y >= height {
+                  if !self.quirks.wrap_sprites {
+                        return false;
                   }
+                  x %= width;
+                  y %= height;
+            }
+            let mut bits = ((byte as u128) << 120 >> x) & framebuffer::row_mask(width);
+            if self.quirks.wrap_sprites && x + 8 > width {
+                  // The last pixels come back in on the left
+                  let spill = x + 8 - width;
+                  bits |= ((byte & ((0x1 << spill) - 1)) as u128) << (128 - spill);
+            }
+            self.display.xor_row(plane, y, bits)
+      }
+
+      // Moves the selected planes by (dx, dy) pixels, filling the gap with blank pixels.
+      fn display_scroll(&mut self, dx: isize, dy: isize) {
+            let (width, height) = (self.display_width(), self.display_height());
+            self.display.scroll(self.plane, dx, dy, width, height);
+      }
+      // Skips the instruction after the current one; F000 nnnn is four bytes long.
+      fn skip_next(&mut self) {
+            let next = self.program_counter.wrapping_add(2);
+            let long = self.read_byte(next as usize) == 0xF0 && self.read_byte(next as usize + 1) == 0x00;
+            self.program_counter = next.wrapping_add(if long {2} else {0});
+      }
+
+      // The byte at `addr`, wrapping around the end of memory.
+      fn read_byte(&self, addr: usize) -> u8 {
+            self.memory[addr % self.memory.len()]
+      }
+      // Fails unless memory[start..start + len] lies within memory.
+      fn check_memory(&self, start: usize, len: usize) -> Result<(), ChipError> {
+            let size = self.memory.len();
+            if start + len > size {
+                  return Err(ChipError::MemoryOutOfBounds {
+                        pc: self.program_counter,
+                        opcode: self.opcode,
+                        addr: start.max(size)
+                  });
+            }
+            Ok(())
+      }
+
+      fn trace_read(&mut self, start: usize, len: usize) {
+            if let Some(ref mut trace) = self.trace {
+                  trace.reads.push((start, len));
+            }
+      }
+      fn trace_write(&mut self, start: usize, len: usize) {
+            if let Some(ref mut trace) = self.trace {
+                  trace.writes.push((start, len));
+            }
+      }
+      fn trace_event(&mut self, event: Event) {
+            if let Some(ref mut trace) = self.trace {
+                  trace.events.push(event);
             }
-            overlap
       }
 
       fn set_flag(&mut self, val: u8) {
             self.registers[0xF] = val;
       }
+      fn logic_vf_reset(&mut self) {
+            if self.quirks.vf_reset {
+                  self.set_flag(0x0);
+            }
+      }
+      // The register 8xy6/8xyE shift from, depending on the quirk setting.
+      fn shift_source(&self, rx: usize, ry: usize) -> usize {
+            if self.quirks.shift_uses_vy { ry } else { rx }
+      }
+
+      /**
+      * Advances the machine by one 60Hz frame: the delay & sound timers are
+      * decreased by 1, and a Dxyn stalled on the display wait quirk may draw.
+      * The host decides when a frame elapses, so runs are reproducible.
+      */
+      pub fn tick_timers(&mut self) {
+            if self.delay_timer != 0 {
+                  self.delay_timer -= 1;
+            }
+            if self.sound_timer != 0 {
+                  self.sound_timer -= 1;
+            }
+            self.vblank = true;
+      }
 
-      pub fn cycle(&mut self) {
-            let high: u16 = self.memory[self.program_counter as usize] as u16;
-            let low: u16 = self.memory[(self.program_counter + 1) as usize] as u16;
+      // The opcode at the program counter.
+      pub fn fetch(&self) -> u16 {
+            let high: u16 = self.read_byte(self.program_counter as usize) as u16;
+            let low: u16 = self.read_byte(self.program_counter as usize + 1) as u16;
 
-            let mut instruction: u16 = 0;
-            instruction |= high << 8;
-            instruction |= low;
+            (high << 8) | low
+      }
 
-            // Delay & sound timers are decreased by 1 at a rate of 60Hz
-            let now = time::precise_time_ns();
-            if now - self.clock > 16666000 {
-                  self.clock = now;
-                  if self.delay_timer != 0 {
-                        self.delay_timer -= 1;
-                  }
-                  if self.sound_timer != 0 {
-                        self.sound_timer -= 1;
+      // A Dxyn stalled on the display wait quirk, which will draw after the next tick.
+      pub fn waiting_for_vblank(&self) -> bool {
+            if !self.quirks.display_wait || self.vblank {
+                  return false;
+            }
+            matches!(instruction::decode(self.fetch()), Instruction::Draw(..))
+      }
+
+      pub fn running(&self) -> bool {
+            self.state == MachineState::Running
+      }
+      pub fn instruction_count(&self) -> u64 {
+            self.instructions
+      }
+      // Stops the machine after `limit` instructions (counted from reset); `None` disables it.
+      pub fn set_watchdog(&mut self, limit: Option<u64>) {
+            self.watchdog = limit;
+      }
+      // Whether `count` more instructions can run before the watchdog stops the machine.
+      pub fn watchdog_allows(&self, count: u64) -> bool {
+            match self.watchdog {
+                  Some(limit) => self.instructions + count <= limit,
+                  None => true
+            }
+      }
+      // Adds instructions run outside of `cycle`, such as recompiled code, to `instruction_count`.
+      pub fn count_instructions(&mut self, count: u64) {
+            self.instructions += count;
+      }
+
+      /**
+      * Keeps decoded instructions around by address instead of decoding
+      * every fetch; faster for long headless runs, with identical results.
+      */
+      pub fn set_decode_cache(&mut self, enabled: bool) {
+            self.cache = if enabled { Some(DecodeCache::new(self.memory.len())) } else { None };
+      }
+      /**
+      * Records the memory accesses and events of each instruction `cycle`
+      * runs, for watchpoints; see `trace`.
+      */
+      pub fn set_trace(&mut self, enabled: bool) {
+            if enabled != self.trace.is_some() {
+                  self.trace = if enabled { Some(Trace::default()) } else { None };
+            }
+      }
+      // What the last instruction did, while tracing is on.
+      pub fn trace(&self) -> Option<&Trace> {
+            self.trace.as_ref()
+      }
+      // Must follow any direct write to `memory` while the decode cache is enabled.
+      pub fn invalidate_code(&mut self, start: usize, len: usize) {
+            if let Some(ref mut cache) = self.cache {
+                  cache.invalidate(start, len);
+            }
+      }
+
+      /**
+      * Runs the instruction at the program counter, and returns what it cost
+      * under the current timing mode: 1, or machine cycles on the VIP.
+      * A stopped machine costs nothing.
+      */
+      pub fn cycle(&mut self) -> Result<u32, ChipError> {
+            if !self.running() {
+                  return Ok(0);
+            }
+            if let Some(limit) = self.watchdog {
+                  if self.instructions >= limit {
+                        self.state = MachineState::WatchdogExpired { instructions: self.instructions };
+                        return Ok(0);
                   }
             }
 
-            //println!("Dispatching instruction {:x} at PC = {} (I = {})", instruction, self.program_counter, self.index);
-            match instruction & 0xF000 {
-                  0x0000 => {
-                        match instruction {
-                              0xE0 => self.op_clearsrc(),
-                              0xEE => self.op_ret(),
-                              _ => panic!("Execute machine language subroutine unsupported.")
-                        }
-                  },
-                  0x1000 => self.op_jump_imm(instruction),
-                  0x2000 => self.op_call(instruction),
-                  0x3000 => self.op_se_reg_imm(instruction),
-                  0x4000 => self.op_sne_reg_imm(instruction),
-                  0x5000 => {
-                        if instruction & 0x000F == 0 {
-                              self.op_se_reg_reg(instruction);
-                        } else {
-                              panic!("Undefined instrution {:x}", instruction);
-                        }
-                  },
-                  0x6000 => self.op_load_reg_imm(instruction),
-                  0x7000 => self.op_add_reg_imm(instruction),
-                  0x8000 => {
-                        match instruction & 0x000F {
-                              0x0 => self.op_load_reg_reg(instruction),
-                              0x1 => self.op_or(instruction),
-                              0x2 => self.op_and(instruction),
-                              0x3 => self.op_xor(instruction),
-                              0x4 => self.op_add_reg_reg(instruction),
-                              0x5 => self.op_sub_reg_reg(instruction),
-                              0x6 => self.op_shr(instruction),
-                              0x7 => self.op_subn_reg_reg(instruction),
-                              0xE => self.op_shl(instruction),
-                              _ => panic!("Instruction {:x} unimplemented", instruction)
-                        }
-                  },
-                  0x9000 => {
-                        if instruction & 0x000F == 0{
-                              self.op_sne_reg_reg(instruction);
-                        } else {
-                              panic!("Undefined instruction {:x}", instruction);
-                        }
-                  },
-                  0xA000 => self.op_load_i_imm(instruction),
-                  0xB000 => self.op_jump_imm_plus(instruction),
-                  0xC000 => self.op_rand(instruction),
-                  0xD000 => self.op_draw(instruction),
-                  0xE000 => {
-                        match instruction & 0x00FF {
-                              0x9E => self.op_skp(instruction),
-                              0xA1 => self.op_sknp(instruction),
-                              _ => panic!("Undefined instruction {:x}", instruction)
-                        }
-                  },
-                  0xF000 => {
-                        match instruction & 0x00FF {
-                              0x07 => self.op_load_reg_dt(instruction),
-                              0x0A => self.op_load_reg_key(instruction),
-                              0x15 => self.op_load_dt_reg(instruction),
-                              0x18 => self.op_load_st_reg(instruction),
-                              0x1E => self.op_add_i_reg(instruction),
-                              0x29 => self.op_load_font_reg(instruction),
-                              0x33 => self.op_load_bcd_reg(instruction),
-                              0x55 => self.op_store_regs_i(instruction),
-                              0x65 => self.op_load_regs_i(instruction),
-                              _ => panic!("Undefined instruction {:x}", instruction)
-                        }
-                  },
-                  _ => panic!("dunno")
+            let instruction = match self.cache {
+                  Some(ref mut cache) => cache.get(&self.memory, self.program_counter),
+                  None => instruction::decode(self.fetch())
+            };
+            let cost = match self.timing {
+                  Timing::Instructions => 1,
+                  Timing::Vip => timing::vip_cycles(self, &instruction)
+            };
+            if let Some(ref mut trace) = self.trace {
+                  trace.clear();
+            }
+
+            if let Err(e) = self.execute(instruction) {
+                  self.state = MachineState::Faulted(e.clone());
+                  return Err(e);
             }
+            self.instructions += 1;
+            Ok(cost)
+      }
 
-            if self.program_counter == 4094 {
-                  println!("Finished memory!");
-                  self.running = false;
-                  return
+      // Runs a single decoded instruction against the current machine state.
+      pub fn execute(&mut self, instruction: Instruction) -> Result<(), ChipError> {
+            use instruction::Instruction::*;
+
+            self.opcode = instruction.encode();
+            let reg = |r: u8| r as usize;
+            match instruction {
+                  Sys(0x000) => return Err(ChipError::Runaway {
+                        pc: self.program_counter,
+                        opcode: self.opcode
+                  }),
+                  Sys(_) => return Err(ChipError::MachineCode {
+                        pc: self.program_counter,
+                        opcode: self.opcode
+                  }),
+                  ScrollDown(n) => self.op_scroll_down(n),
+                  ScrollUp(n) => self.op_scroll_up(n),
+                  Clear => self.op_clearsrc(),
+                  Return => self.op_ret()?,
+                  ScrollRight => self.op_scroll_right(),
+                  ScrollLeft => self.op_scroll_left(),
+                  Exit => self.op_exit(),
+                  Lores => self.op_lores(),
+                  Hires => self.op_hires(),
+                  Jump(nnn) => self.op_jump_imm(nnn),
+                  Call(nnn) => self.op_call(nnn)?,
+                  SkipEqImm(x, kk) => self.op_se_reg_imm(reg(x), kk),
+                  SkipNeImm(x, kk) => self.op_sne_reg_imm(reg(x), kk),
+                  SkipEqReg(x, y) => self.op_se_reg_reg(reg(x), reg(y)),
+                  StoreRange(x, y) => self.op_store_range_i(reg(x), reg(y))?,
+                  LoadRange(x, y) => self.op_load_range_i(reg(x), reg(y))?,
+                  LoadImm(x, kk) => self.op_load_reg_imm(reg(x), kk),
+                  AddImm(x, kk) => self.op_add_reg_imm(reg(x), kk),
+                  Move(x, y) => self.op_load_reg_reg(reg(x), reg(y)),
+                  Or(x, y) => self.op_or(reg(x), reg(y)),
+                  And(x, y) => self.op_and(reg(x), reg(y)),
+                  Xor(x, y) => self.op_xor(reg(x), reg(y)),
+                  Add(x, y) => self.op_add_reg_reg(reg(x), reg(y)),
+                  Sub(x, y) => self.op_sub_reg_reg(reg(x), reg(y)),
+                  ShiftRight(x, y) => self.op_shr(reg(x), reg(y)),
+                  SubN(x, y) => self.op_subn_reg_reg(reg(x), reg(y)),
+                  ShiftLeft(x, y) => self.op_shl(reg(x), reg(y)),
+                  SkipNeReg(x, y) => self.op_sne_reg_reg(reg(x), reg(y)),
+                  LoadI(nnn) => self.op_load_i_imm(nnn),
+                  JumpOffset(nnn) => self.op_jump_imm_plus(nnn),
+                  Random(x, kk) => self.op_rand(reg(x), kk),
+                  Draw(x, y, n) => self.op_draw(reg(x), reg(y), n)?,
+                  SkipKey(x) => self.op_skp(reg(x)),
+                  SkipNotKey(x) => self.op_sknp(reg(x)),
+                  LoadILong => self.op_load_i_long(),
+                  Plane(n) => self.op_plane(n),
+                  Audio => self.op_load_audio_i()?,
+                  LoadDelay(x) => self.op_load_reg_dt(reg(x)),
+                  WaitKey(x) => self.op_load_reg_key(reg(x)),
+                  SetDelay(x) => self.op_load_dt_reg(reg(x)),
+                  SetSound(x) => self.op_load_st_reg(reg(x)),
+                  AddI(x) => self.op_add_i_reg(reg(x)),
+                  Font(x) => self.op_load_font_reg(reg(x)),
+                  BigFont(x) => self.op_load_bigfont_reg(reg(x)),
+                  Bcd(x) => self.op_load_bcd_reg(reg(x))?,
+                  Pitch(x) => self.op_load_pitch_reg(reg(x)),
+                  StoreRegs(x) => self.op_store_regs_i(reg(x))?,
+                  LoadRegs(x) => self.op_load_regs_i(reg(x))?,
+                  StoreFlags(x) => self.op_store_rpl(reg(x)),
+                  LoadFlags(x) => self.op_load_rpl(reg(x)),
+                  Undefined(_) => return Err(ChipError::UndefinedInstruction {
+                        pc: self.program_counter,
+                        opcode: self.opcode
+                  })
             }
-            if self.program_counter - PROGRAM_BASE >= self.rom_size as u16 {
-                  println!("Finished ROM!");
-                  self.running = false;
-                  return
-            } 
+            Ok(())
       }
 
       fn store(&mut self, reg: usize, val: u8) {
@@ -232,48 +861,214 @@ impl Chip {
       }
 
       pub fn reset(&mut self) {
-            self.memory = [0; MEMORY_SIZE];
-            self.memory[0..80].copy_from_slice(&FONT_SET);
+            for byte in self.memory.iter_mut() {
+                  *byte = 0;
+            }
+            let (font, big_font) = (self.font_base as usize, self.big_font_base as usize);
+            self.memory[font..font + self.font.len()].copy_from_slice(&self.font);
+            self.memory[big_font..big_font + self.big_font.len()].copy_from_slice(&self.big_font);
             self.registers = [0; NUM_REGISTERS];
-            self.running = true;
-            self.stack = [0; STACK_SIZE];
+            self.state = MachineState::Running;
+            self.instructions = 0;
+            for entry in self.stack.iter_mut() {
+                  *entry = 0;
+            }
             self.stack_pointer = 0;
-            self.program_counter = PROGRAM_BASE;
+            self.program_counter = self.load_address;
             self.index = 0;
-            self.display = [false; DISPLAY_SIZE];
-            self.clock = time::precise_time_ns();
+            self.display.clear(0xFF);
+            self.plane = 0x1;
+            self.hires = false;
             self.delay_timer = 0;
             self.sound_timer = 0;
             self.key_pressed = 0x10;
             self.keypad = [false; KEYPAD_SIZE];
+            self.audio.reset();
+            self.rng.seed(self.seed);
             self.wait = 0x10;
+            self.vblank = false;
+            if let Some(ref mut cache) = self.cache {
+                  cache.clear();
+            }
+      }
+
+      // Seeds Cxkk's generator; the sequence restarts from here on every reset.
+      pub fn seed_rng(&mut self, seed: u64) {
+            self.seed = seed;
+            self.rng.seed(seed);
+      }
+      pub fn rng_seed(&self) -> u64 {
+            self.seed
+      }
+      // Replaces Cxkk's generator, e.g. with one supplied by the host.
+      pub fn set_rng(&mut self, rng: Box<dyn Rng>) {
+            self.rng = rng;
+            self.rng.seed(self.seed);
+      }
+      pub fn rng_state(&self) -> u64 {
+            self.rng.state()
+      }
+      pub fn set_rng_state(&mut self, state: u64) {
+            self.rng.set_state(state);
       }
 
-      pub fn load_rom(&mut self, rom: &str) {
+      /**
+      * Sets where ROMs are loaded and execution starts, e.g. 0x600 for
+      * ETI-660 programs. Takes effect on the next reset or ROM load.
+      */
+      pub fn set_load_address(&mut self, addr: u16) -> Result<(), ChipError> {
+            if addr as usize + 2 > self.memory.len() {
+                  return Err(ChipError::InvalidLoadAddress(addr));
+            }
+            self.load_address = addr;
+            Ok(())
+      }
+      pub fn load_address(&self) -> u16 {
+            self.load_address
+      }
+
+      // Resets the machine and copies the program to the load address.
+      pub fn load_rom_bytes(&mut self, contents: &[u8]) -> Result<(), ChipError> {
+            let max = self.memory.len() - self.load_address as usize;
+            if contents.is_empty() {
+                  return Err(ChipError::RomEmpty);
+            }
+            if contents.len() > max {
+                  return Err(ChipError::RomTooLarge { size: contents.len(), max });
+            }
+
             self.reset();
-            self.rom = rom.to_string();
+            self.rom = String::from("");
+            self.rom_size = contents.len();
+            self.rom_hash = snapshot::rom_hash(contents);
+
+            let base = self.load_address as usize;
+            self.memory[base..base + contents.len()].copy_from_slice(contents);
+            self.invalidate_code(base, contents.len());
+            Ok(())
+      }
 
-            let mut file = File::open(rom).unwrap();
+      // Loads a program from any source, such as stdin; `name` identifies it in save states.
+      #[cfg(feature = "std")]
+      pub fn load_rom_reader<R: Read>(&mut self, name: &str, mut reader: R) -> Result<(), ChipError> {
             let mut contents = Vec::new();
-            file.read_to_end(&mut contents).unwrap();
-            self.rom_size = contents.len();
+            reader.read_to_end(&mut contents)
+                  .map_err(|e| ChipError::RomIo { path: name.to_string(), cause: e.to_string() })?;
+            self.load_rom_bytes(&contents)?;
+            self.rom = name.to_string();
+            Ok(())
+      }
+
+      #[cfg(feature = "std")]
+      pub fn load_rom(&mut self, rom: &str) -> Result<(), ChipError> {
+            let file = File::open(rom)
+                  .map_err(|e: io::Error| ChipError::RomIo { path: rom.to_string(), cause: e.to_string() })?;
+            self.load_rom_reader(rom, file)
+      }
+
+      // Captures the whole machine state; see snapshot.rs for the file format.
+      pub fn snapshot(&self) -> Snapshot {
+            Snapshot {
+                  rom: self.rom.clone(),
+                  rom_size: self.rom_size as u32,
+                  rom_hash: self.rom_hash,
+                  memory: self.memory.to_vec(),
+                  registers: self.registers,
+                  stack: self.stack.to_vec(),
+                  stack_pointer: self.stack_pointer as u16,
+                  program_counter: self.program_counter,
+                  index: self.index,
+                  state: self.state.clone(),
+                  instructions: self.instructions,
+                  display: self.display.to_cells(self.display_width(), self.display_height()),
+                  plane: self.plane,
+                  hires: self.hires,
+                  rpl: self.rpl,
+                  delay_timer: self.delay_timer,
+                  sound_timer: self.sound_timer,
+                  key_pressed: self.key_pressed,
+                  keypad: self.keypad,
+                  wait: self.wait,
+                  vblank: self.vblank,
+                  audio_pattern: self.audio.pattern,
+                  audio_pitch: self.audio.pitch,
+                  audio_position: self.audio.position(),
+                  quirks: self.quirks,
+                  rng_seed: self.seed,
+                  rng_state: self.rng.state()
+            }
+      }
+
+      // Puts the machine back into a state captured by `snapshot`.
+      pub fn restore(&mut self, s: &Snapshot) -> Result<(), SnapshotError> {
+            if s.memory.len() != self.memory.len() {
+                  return Err(SnapshotError::Incompatible(format!("{} bytes of memory, expected {}", s.memory.len(), self.memory.len())));
+            }
+            if s.display.len() != DISPLAY_SIZE {
+                  return Err(SnapshotError::Incompatible(format!("{} display cells, expected {}", s.display.len(), DISPLAY_SIZE)));
+            }
+            if s.stack.len() != self.stack.len() || s.stack_pointer as usize > s.stack.len() {
+                  return Err(SnapshotError::Incompatible(format!("stack of {} entries, expected {}", s.stack.len(), self.stack.len())));
+            }
 
-            for i in 0..contents.len() {
-                  self.memory[PROGRAM_BASE as usize + i] = contents[i];
+            self.rom = s.rom.clone();
+            self.rom_size = s.rom_size as usize;
+            self.rom_hash = s.rom_hash;
+            self.memory.copy_from_slice(&s.memory);
+            if let Some(ref mut cache) = self.cache {
+                  cache.clear();
             }
+            self.registers = s.registers;
+            self.stack.copy_from_slice(&s.stack);
+            self.stack_pointer = s.stack_pointer as usize;
+            self.program_counter = s.program_counter;
+            self.index = s.index;
+            self.state = s.state.clone();
+            self.instructions = s.instructions;
+            let (width, height) = if s.hires { self.hires_size } else { self.lores_size };
+            self.display.set_cells(&s.display, width, height);
+            self.plane = s.plane;
+            self.hires = s.hires;
+            self.rpl = s.rpl;
+            self.delay_timer = s.delay_timer;
+            self.sound_timer = s.sound_timer;
+            self.key_pressed = s.key_pressed;
+            self.keypad = s.keypad;
+            self.wait = s.wait;
+            self.vblank = s.vblank;
+            self.audio.pattern = s.audio_pattern;
+            self.audio.pitch = s.audio_pitch;
+            self.audio.set_position(s.audio_position);
+            self.quirks = s.quirks;
+            self.seed = s.rng_seed;
+            self.rng.set_state(s.rng_state);
+            Ok(())
+      }
+
+      // Synthesises the sound output for the current timer state; see `Audio::render`.
+      pub fn render_audio(&mut self, out: &mut [i16]) {
+            let playing = self.sound_timer > 0;
+            self.audio.render(playing, out);
       }
 
+      // Prints the registers to stdout; see `write_dump` for other outputs.
+      #[cfg(feature = "std")]
       pub fn dump(&self) {
-            println!("======================");
-            println!("  REGISTER DUMP");
-            println!("======================");
+            let mut out = String::new();
+            self.write_dump(&mut out).expect("writing to a String cannot fail");
+            print!("{}", out);
+      }
+      pub fn write_dump(&self, out: &mut dyn fmt::Write) -> fmt::Result {
+            writeln!(out, "======================")?;
+            writeln!(out, "  REGISTER DUMP")?;
+            writeln!(out, "======================")?;
             for i in 0..self.registers.len() {
-                  println!(" --> V{:} = {}", i, self.registers[i]);
+                  writeln!(out, " --> V{:} = {}", i, self.registers[i])?;
             }
-            println!(" --> DT  = {}", self.delay_timer);
-            println!(" --> ST  = {}", self.sound_timer);
-            println!(" --> PC  = {}", self.program_counter);
-            println!(" --> I   = {}", self.index);
+            writeln!(out, " --> DT  = {}", self.delay_timer)?;
+            writeln!(out, " --> ST  = {}", self.sound_timer)?;
+            writeln!(out, " --> PC  = {}", self.program_counter)?;
+            writeln!(out, " --> I   = {}", self.index)
       }
 
       /**
@@ -281,311 +1076,461 @@ impl Chip {
        */
       // 00E0 - CLS
       fn op_clearsrc(&mut self) {
-            self.display = [false; DISPLAY_SIZE];
+            self.display.clear(self.plane);
+
+            self.program_counter = self.program_counter.wrapping_add(2);
+      }
+      // 00Cn - SCD nibble
+      fn op_scroll_down(&mut self, rows: u8) {
+            self.display_scroll(0, rows as isize);
+
+            self.program_counter = self.program_counter.wrapping_add(2);
+      }
+      // 00Dn - SCU nibble
+      fn op_scroll_up(&mut self, rows: u8) {
+            self.display_scroll(0, -(rows as isize));
+
+            self.program_counter = self.program_counter.wrapping_add(2);
+      }
+      // 00FB - SCR
+      fn op_scroll_right(&mut self) {
+            self.display_scroll(4, 0);
+
+            self.program_counter = self.program_counter.wrapping_add(2);
+      }
+      // 00FC - SCL
+      fn op_scroll_left(&mut self) {
+            self.display_scroll(-4, 0);
+
+            self.program_counter = self.program_counter.wrapping_add(2);
+      }
+      // 00FD - EXIT
+      fn op_exit(&mut self) {
+            self.state = MachineState::Exited;
+      }
+      // 00FE - LOW
+      fn op_lores(&mut self) {
+            self.hires = false;
+            self.display.clear(0xFF);
 
-            self.program_counter += 2;
+            self.program_counter = self.program_counter.wrapping_add(2);
+      }
+      // 00FF - HIGH
+      fn op_hires(&mut self) {
+            self.hires = true;
+            self.display.clear(0xFF);
+
+            self.program_counter = self.program_counter.wrapping_add(2);
       }
       // 00EE - RET
-      fn op_ret(&mut self) {
+      fn op_ret(&mut self) -> Result<(), ChipError> {
+            if self.stack_pointer == 0 {
+                  return Err(ChipError::StackUnderflow { pc: self.program_counter, opcode: self.opcode });
+            }
             self.program_counter = self.stack[self.stack_pointer-1]/* + 2*/;
             self.stack_pointer -= 1;
+            Ok(())
       }
       // 1nnn - JP addr
-      fn op_jump_imm(&mut self, instruction: u16) {
-            let addr = instruction & 0x0FFF;
+      fn op_jump_imm(&mut self, addr: u16) {
+            if addr == self.program_counter {
+                  self.state = MachineState::Halted { pc: addr };
+            }
             self.program_counter = addr;
       }
       // 2nnn - CALL addr
-      fn op_call(&mut self, instruction: u16) {
-            if self.stack_pointer >= STACK_SIZE {
-                  panic!("Stack overflow!");
+      fn op_call(&mut self, addr: u16) -> Result<(), ChipError> {
+            if self.stack_pointer >= self.stack.len() {
+                  return Err(ChipError::StackOverflow { pc: self.program_counter, opcode: self.opcode });
             }
-            let addr = (instruction & 0x0FFF) as u16;
-            self.stack[self.stack_pointer] = self.program_counter + 2;
+            self.stack[self.stack_pointer] = self.program_counter.wrapping_add(2);
             self.program_counter = addr;
             self.stack_pointer += 1;
+            Ok(())
       }
       // 6xkk - LD Vx, byte
-      fn op_load_reg_imm(&mut self, instruction: u16) {
-            let rx = ((instruction & 0x0F00) >> 8) as usize;
-            let val = (instruction & 0x00FF) as u8;
-            
+      fn op_load_reg_imm(&mut self, rx: usize, val: u8) {
             self.store(rx, val);
-            self.program_counter += 2;
+            self.program_counter = self.program_counter.wrapping_add(2);
       }
       // 8xy0 - LD Vx, Vy
-      fn op_load_reg_reg(&mut self, instruction: u16) {
-            let rx = ((instruction & 0x0F00) >> 8) as usize;
-            let ry = ((instruction & 0x00F0) >> 4) as usize;
+      fn op_load_reg_reg(&mut self, rx: usize, ry: usize) {
             let val = self.load(ry);
 
             self.store(rx, val);
-            self.program_counter += 2;
+            self.program_counter = self.program_counter.wrapping_add(2);
       }
       // 8xy1 - OR Vx, Vy
-      fn op_or(&mut self, instruction: u16) {
-            let rx = ((instruction & 0x0F00) >> 8) as usize;
-            let ry = ((instruction & 0x00F0) >> 4) as usize;
+      fn op_or(&mut self, rx: usize, ry: usize) {
             let val = self.load(rx) | self.load(ry);
 
             self.store(rx, val);
-            self.program_counter += 2;
+            self.logic_vf_reset();
+            self.program_counter = self.program_counter.wrapping_add(2);
       }
       // 8xy2 - AND Vx, Vy
-      fn op_and(&mut self, instruction: u16) {
-            let rx = ((instruction & 0x0F00) >> 8) as usize;
-            let ry = ((instruction & 0x00F0) >> 4) as usize;
+      fn op_and(&mut self, rx: usize, ry: usize) {
             let val = self.load(rx) & self.load(ry);
 
             self.store(rx, val);
-            self.program_counter += 2;
+            self.logic_vf_reset();
+            self.program_counter = self.program_counter.wrapping_add(2);
       }
       // 8xy3 - XOR Vx, Vy
-      fn op_xor(&mut self, instruction: u16) {
-            let rx = ((instruction & 0x0F00) >> 8) as usize;
-            let ry = ((instruction & 0x00F0) >> 4) as usize;
+      fn op_xor(&mut self, rx: usize, ry: usize) {
             let val = self.load(rx) ^ self.load(ry);
 
             self.store(rx, val);
-            self.program_counter += 2;
+            self.logic_vf_reset();
+            self.program_counter = self.program_counter.wrapping_add(2);
       }
       // 8xy4 - ADD Vx, Vy
-      fn op_add_reg_reg(&mut self, instruction: u16) {
-            let rx = ((instruction & 0x0F00) >> 8) as usize;
-            let ry = ((instruction & 0x00F0) >> 4) as usize;
+      fn op_add_reg_reg(&mut self, rx: usize, ry: usize) {
             let (val, carry) = add_carry(self.load(rx), self.load(ry));
 
             self.store(rx, val);
             self.set_flag(carry);
-            self.program_counter += 2;
+            self.program_counter = self.program_counter.wrapping_add(2);
       }
       // 8xy5 - SUB Vx, Vy
-      fn op_sub_reg_reg(&mut self, instruction: u16) {
-            let rx = ((instruction & 0x0F00) >> 8) as usize;
-            let ry = ((instruction & 0x00F0) >> 4) as usize;
+      fn op_sub_reg_reg(&mut self, rx: usize, ry: usize) {
             let (val, borrow) = sub_borrow(self.load(rx), self.load(ry));
 
             self.store(rx, val);
             self.set_flag(borrow);
-            self.program_counter += 2;
+            self.program_counter = self.program_counter.wrapping_add(2);
       }
       // 8xy6 - SHR Vx {, Vy}
-      // SHOULD ADD TOGGLE BETWEEN REAL INSTRUCTION AND FAKE:
+      // Whether Vy is used is a quirk:
       // --> https://www.reddit.com/r/EmuDev/comments/72dunw/chip8_8xy6_help/
-      fn op_shr(&mut self, instruction: u16) {
-            let rx = ((instruction & 0x0F00) >> 8) as usize;
-            let val = self.load(rx);
+      fn op_shr(&mut self, rx: usize, ry: usize) {
+            let val = self.load(self.shift_source(rx, ry));
             // extract lsb
             self.set_flag(val & 0x1);
             self.store(rx, val >> 1);
-            self.program_counter += 2;
+            self.program_counter = self.program_counter.wrapping_add(2);
       }
       // 8xy7 - SUBN Vx, Vy
-      fn op_subn_reg_reg(&mut self, instruction: u16) {
-            let rx = ((instruction & 0x0F00) >> 8) as usize;
-            let ry = ((instruction & 0x00F0) >> 4) as usize;
+      fn op_subn_reg_reg(&mut self, rx: usize, ry: usize) {
             let (val, borrow) = sub_borrow(self.load(ry), self.load(rx));
 
             self.store(rx, val);
             self.set_flag(borrow);
-            self.program_counter += 2;
+            self.program_counter = self.program_counter.wrapping_add(2);
       }
       // 8xyE - SHL Vx {, Vy}
-      fn op_shl(&mut self, instruction: u16) {
-            let rx = ((instruction & 0x0F00) >> 8) as usize;
-            let val = self.load(rx);
+      fn op_shl(&mut self, rx: usize, ry: usize) {
+            let val = self.load(self.shift_source(rx, ry));
             // extract msb
             self.set_flag(val >> 7);
             self.store(rx, val << 1);
-            self.program_counter += 2;
+            self.program_counter = self.program_counter.wrapping_add(2);
+      }
+      // 5xy2 - LD [I], Vx-Vy
+      fn op_store_range_i(&mut self, rx: usize, ry: usize) -> Result<(), ChipError> {
+            let count = rx.abs_diff(ry) + 1;
+            self.check_memory(self.index as usize, count)?;
+            for j in 0..count {
+                  let reg = if rx <= ry { rx + j } else { rx - j };
+                  self.memory[self.index as usize + j] = self.load(reg);
+            }
+            let index = self.index as usize;
+            self.invalidate_code(index, count);
+            self.trace_write(index, count);
+
+            self.program_counter = self.program_counter.wrapping_add(2);
+            Ok(())
+      }
+      // 5xy3 - LD Vx-Vy, [I]
+      fn op_load_range_i(&mut self, rx: usize, ry: usize) -> Result<(), ChipError> {
+            let count = rx.abs_diff(ry) + 1;
+            self.check_memory(self.index as usize, count)?;
+            for j in 0..count {
+                  let reg = if rx <= ry { rx + j } else { rx - j };
+                  let val = self.memory[self.index as usize + j];
+                  self.store(reg, val);
+            }
+            let index = self.index as usize;
+            self.trace_read(index, count);
+
+            self.program_counter = self.program_counter.wrapping_add(2);
+            Ok(())
       }
       // Annn - LD I, addr
-      fn op_load_i_imm(&mut self, instruction: u16) {
-            let addr = instruction & 0x0FFF;
+      fn op_load_i_imm(&mut self, addr: u16) {
             self.index = addr;
-            self.program_counter += 2;
+            self.program_counter = self.program_counter.wrapping_add(2);
       }
-      // Bnnn - JP V0, addr
-      fn op_jump_imm_plus(&mut self, instruction: u16) {
-            self.program_counter = (instruction & 0x0FFF) + self.load(0x0) as u16;
+      // Bnnn - JP V0, addr (or Bxnn - JP Vx, addr)
+      fn op_jump_imm_plus(&mut self, addr: u16) {
+            let reg = if self.quirks.jump_uses_vx {
+                  (addr >> 8) as usize
+            } else {
+                  0x0
+            };
+            self.program_counter = addr + self.load(reg) as u16;
       }
       // Cxkk - RND Vx, byte
-      fn op_rand(&mut self, instruction: u16) {
-            let rx = ((instruction & 0x0F00) >> 8) as usize;
-            let mask = (instruction & 0x00FF) as u8;
-
-            self.store(rx, rand::random::<u8>() & mask);
-            self.program_counter += 2;
+      fn op_rand(&mut self, rx: usize, mask: u8) {
+            let val = self.rng.next_u8();
+            self.store(rx, val & mask);
+            self.program_counter = self.program_counter.wrapping_add(2);
       }
       // Dxyn - DRW Vx, Vy, nibble
-      fn op_draw(&mut self, instruction: u16) {
-            let rx = ((instruction & 0x0F00) >> 8) as usize;
-            let ry = ((instruction & 0x00F0) >> 4) as usize;
-            let bytes = (instruction & 0x000F) as usize;
-            let pos_x = self.load(rx) as usize;
-            let pos_y = self.load(ry) as usize;
-
-            let src = self.memory[(self.index as usize)..(self.index as usize + bytes)].to_vec();
+      // Dxy0 - DRW Vx, Vy, 0 (16x16 sprite, two bytes per row)
+      fn op_draw(&mut self, rx: usize, ry: usize, rows: u8) -> Result<(), ChipError> {
+            let pos_x = self.load(rx) as usize % self.display_width();
+            let pos_y = self.load(ry) as usize % self.display_height();
+
+            let (rows, row_bytes) = if rows == 0 { (16, 2) } else { (rows as usize, 1) };
+            let planes = (0..NUM_PLANES).filter(|p| self.plane & (0x1 << p) != 0).count();
+            self.check_memory(self.index as usize, rows * row_bytes * planes)?;
+
+            // Stall on this instruction until the next 60Hz tick
+            if self.quirks.display_wait {
+                  if !self.vblank {
+                        return Ok(());
+                  }
+                  self.vblank = false;
+            }
+
+            // With both planes selected, the second plane's sprite data
+            // follows the first plane's.
+            let mut addr = self.index as usize;
             let mut overlap = false;
-            for i in 0..src.len() {
-                  if self.display_write_byte(pos_x, pos_y + i, src[i]) {
-                        overlap = true;
+            for p in 0..NUM_PLANES {
+                  if self.plane & (0x1 << p) == 0 {
+                        continue;
+                  }
+                  for i in 0..rows {
+                        for b in 0..row_bytes {
+                              let byte = self.memory[addr];
+                              addr += 1;
+                              if self.display_write_byte(p, pos_x + b * 8, pos_y + i, byte) {
+                                    overlap = true;
+                              }
+                        }
                   }
             }
 
+            let index = self.index as usize;
+            self.trace_read(index, addr - index);
+            if overlap {
+                  self.trace_event(Event::Collision);
+            }
             self.store(0xF, if overlap {0x1} else {0x0});
-            self.program_counter += 2;
-      }
-      fn op_load_reg_key(&mut self, instruction: u16) {
-            self.wait = ((instruction & 0x0F00) >> 8) as u8;
-            if self.wait != 0x10 {
-                  if self.key_pressed != 0x10 {
-                        let wait = self.wait.clone();
-                        let key = self.key_pressed.clone();
-                        self.store(wait as usize, key as u8);
-
-                        self.wait = 0x10;
-                        self.key_pressed = 0x10;
-                        self.program_counter += 2;
-                  }
+            self.program_counter = self.program_counter.wrapping_add(2);
+            Ok(())
+      }
+      fn op_load_reg_key(&mut self, rx: usize) {
+            // Still set from the last time round while the wait goes on
+            if self.wait == 0x10 && self.key_pressed == 0x10 {
+                  self.trace_event(Event::KeyWait);
+            }
+            self.wait = rx as u8;
+            if self.wait != 0x10 && self.key_pressed != 0x10 {
+                  let wait = self.wait;
+                  let key = self.key_pressed;
+                  self.store(wait as usize, key);
+
+                  self.wait = 0x10;
+                  self.key_pressed = 0x10;
+                  self.program_counter = self.program_counter.wrapping_add(2);
             }
       }
-      fn op_load_st_reg(&mut self, instruction: u16) {
-            let rx = ((instruction & 0x0F00) >> 8) as usize;
+      // F000 nnnn - LD I, long addr
+      fn op_load_i_long(&mut self) {
+            let addr = self.program_counter.wrapping_add(2) as usize;
+            let high = self.read_byte(addr) as u16;
+            let low = self.read_byte(addr + 1) as u16;
+            self.index = (high << 8) | low;
+
+            self.program_counter = self.program_counter.wrapping_add(4);
+      }
+      // Fn01 - PLANE n
+      fn op_plane(&mut self, planes: u8) {
+            self.plane = planes & 0x3;
+
+            self.program_counter = self.program_counter.wrapping_add(2);
+      }
+      // F002 - AUDIO
+      fn op_load_audio_i(&mut self) -> Result<(), ChipError> {
+            let base = self.index as usize;
+            self.check_memory(base, PATTERN_SIZE)?;
+            self.audio.pattern.copy_from_slice(&self.memory[base..base + PATTERN_SIZE]);
+            self.trace_read(base, PATTERN_SIZE);
+
+            self.program_counter = self.program_counter.wrapping_add(2);
+            Ok(())
+      }
+      // Fx3A - PITCH Vx
+      fn op_load_pitch_reg(&mut self, rx: usize) {
+            self.audio.pitch = self.load(rx);
+
+            self.program_counter = self.program_counter.wrapping_add(2);
+      }
+      fn op_load_st_reg(&mut self, rx: usize) {
+            if self.sound_timer == 0 && self.load(rx) != 0 {
+                  self.trace_event(Event::SoundStart);
+            }
             self.sound_timer = self.load(rx);
 
-            self.program_counter += 2;
+            self.program_counter = self.program_counter.wrapping_add(2);
       }
-      fn op_add_i_reg(&mut self, instruction: u16) {
-            let rx = ((instruction & 0x0F00) >> 8) as usize;
-            self.index += self.load(rx) as u16;
+      fn op_add_i_reg(&mut self, rx: usize) {
+            self.index = self.index.wrapping_add(self.load(rx) as u16);
 
-            self.program_counter += 2;
+            self.program_counter = self.program_counter.wrapping_add(2);
       }
-      fn op_load_font_reg(&mut self, instruction: u16) {
-            let rx = ((instruction & 0x0F00) >> 8) as usize;
-            let digit = self.load(rx);
-            self.index = (digit as u16) * 5;
+      fn op_load_font_reg(&mut self, rx: usize) {
+            let digit = self.load(rx) & 0xF;
+            self.index = self.font_base + digit as u16 * 5;
 
-            self.program_counter += 2;
+            self.program_counter = self.program_counter.wrapping_add(2);
       }
-      fn op_se_reg_imm(&mut self, instruction: u16) {
-            let reg = ((instruction & 0x0F00) >> 8) as usize;
-            let val = (instruction & 0x00FF) as u8;
+      // Fx30 - LD HF, Vx
+      fn op_load_bigfont_reg(&mut self, rx: usize) {
+            let digit = self.load(rx) & 0xF;
+            self.index = self.big_font_base + digit as u16 * 10;
 
+            self.program_counter = self.program_counter.wrapping_add(2);
+      }
+      fn op_se_reg_imm(&mut self, reg: usize, val: u8) {
             if self.load(reg) == val {
-                  self.program_counter += 2;
+                  self.skip_next();
             }
 
-            self.program_counter += 2;
+            self.program_counter = self.program_counter.wrapping_add(2);
       }
-      fn op_sne_reg_imm(&mut self, instruction: u16) {
-            let reg = ((instruction & 0x0F00) >> 8) as usize;
-            let val = (instruction & 0x00FF) as u8;
-
+      fn op_sne_reg_imm(&mut self, reg: usize, val: u8) {
             if self.load(reg) != val {
-                  self.program_counter += 2;
+                  self.skip_next();
             }
 
-            self.program_counter += 2;
+            self.program_counter = self.program_counter.wrapping_add(2);
       }
-      fn op_se_reg_reg(&mut self, instruction: u16) {
-            let rx = ((instruction & 0x0F00) >> 8) as usize;
-            let ry = ((instruction & 0x00F0) >> 4) as usize;
+      fn op_se_reg_reg(&mut self, rx: usize, ry: usize) {
 
             if self.load(rx) == self.load(ry) {
-                  self.program_counter += 2;
+                  self.skip_next();
             }
 
-            self.program_counter += 2;
+            self.program_counter = self.program_counter.wrapping_add(2);
       }
-      fn op_add_reg_imm(&mut self, instruction: u16) {
-            use std::num::Wrapping;
+      fn op_add_reg_imm(&mut self, rx: usize, val: u8) {
+            use core::num::Wrapping;
 
-            let rx = ((instruction & 0x0F00) >> 8) as usize;
-            let val = Wrapping((instruction & 0x00FF) as u8);
+            let val = Wrapping(val);
             let result = val + Wrapping(self.load(rx));
 
             self.store(rx, result.0);
-            self.program_counter += 2;
+            self.program_counter = self.program_counter.wrapping_add(2);
       }
-      fn op_sne_reg_reg(&mut self, instruction: u16) {
-            let rx = ((instruction & 0x0F00) >> 8) as usize;
-            let ry = ((instruction & 0x00F0) >> 4) as usize;
+      fn op_sne_reg_reg(&mut self, rx: usize, ry: usize) {
 
             if self.load(rx) != self.load(ry) {
-                  self.program_counter += 2;
+                  self.skip_next();
             }
 
-            self.program_counter += 2;
+            self.program_counter = self.program_counter.wrapping_add(2);
       }
       // Ex9E - SKP Vx
-      fn op_skp(&mut self, instruction: u16) {
-            let rx = ((instruction & 0x0F00) >> 8) as usize;
+      fn op_skp(&mut self, rx: usize) {
             let val = (self.load(rx) & 0xF) as usize;
             
-            if self.keypad[val] == true {
-                  self.program_counter += 2;
+            if self.keypad[val] {
+                  self.skip_next();
             }
-            self.program_counter += 2;
+            self.program_counter = self.program_counter.wrapping_add(2);
       }
       // ExA1 - SKNP Vx
-      fn op_sknp(&mut self, instruction: u16) {
-            let rx = ((instruction & 0x0F00) >> 8) as usize;
+      fn op_sknp(&mut self, rx: usize) {
             let val = (self.load(rx) & 0xF) as usize;
             
-            if self.keypad[val] == false {
-                  self.program_counter += 2;
+            if !self.keypad[val] {
+                  self.skip_next();
             }
-            self.program_counter += 2;
+            self.program_counter = self.program_counter.wrapping_add(2);
       }
       // Fx15 - LD DT, Vx
-      fn op_load_dt_reg(&mut self, instruction: u16) {
-            let rx = ((instruction & 0x0F00) >> 8) as usize;
+      fn op_load_dt_reg(&mut self, rx: usize) {
             self.delay_timer = self.load(rx);
 
-            self.program_counter += 2;
+            self.program_counter = self.program_counter.wrapping_add(2);
       }
       // Fx07 - LD Vx, DT
-      fn op_load_reg_dt(&mut self, instruction: u16) {
-            let rx = ((instruction & 0x0F00) >> 8) as usize;
-            let val = self.delay_timer.clone();
+      fn op_load_reg_dt(&mut self, rx: usize) {
+            let val = self.delay_timer;
             self.store(rx, val);
 
-            self.program_counter += 2;
+            self.program_counter = self.program_counter.wrapping_add(2);
       }
       // Fx33 - LD B, Vx
-      fn op_load_bcd_reg(&mut self, instruction: u16) {
-            let rx = ((instruction & 0x0F00) >> 8) as usize;
-            let val = self.load(rx) as f32;
+      fn op_load_bcd_reg(&mut self, rx: usize) -> Result<(), ChipError> {
+            self.check_memory(self.index as usize, 3)?;
+            let val = self.load(rx);
 
-            let hundreds = (val / 100.0).floor() as u8 % 10;
-            let tens = (val / 10.0).floor() as u8 % 10;
-            let ones = val as u8 % 10;
+            let hundreds = val / 100;
+            let tens = val / 10 % 10;
+            let ones = val % 10;
 
             self.memory[self.index as usize]     = hundreds;
             self.memory[(self.index+1) as usize] = tens;
             self.memory[(self.index+2) as usize] = ones;
+            let index = self.index as usize;
+            self.invalidate_code(index, 3);
+            self.trace_write(index, 3);
 
-            self.program_counter += 2;
+            self.program_counter = self.program_counter.wrapping_add(2);
+            Ok(())
       }
       // Fx55 - LD [I], Vx
-      fn op_store_regs_i(&mut self, instruction: u16) {
-            let rx = ((instruction & 0x0F00) >> 8) as usize;
+      fn op_store_regs_i(&mut self, rx: usize) -> Result<(), ChipError> {
+            self.check_memory(self.index as usize, rx + 1)?;
             for j in 0..(rx+1) {
                   self.memory[self.index as usize + j] = self.load(j);
             }
+            let index = self.index as usize;
+            self.invalidate_code(index, rx + 1);
+            self.trace_write(index, rx + 1);
+            if self.quirks.load_store_increment {
+                  self.index = self.index.wrapping_add((rx + 1) as u16);
+            }
 
-            self.program_counter += 2;
+            self.program_counter = self.program_counter.wrapping_add(2);
+            Ok(())
       }
       // Fx65 - LD Vx, [I]
-      fn op_load_regs_i(&mut self, instruction: u16) {
-            let rx = ((instruction & 0x0F00) >> 8) as usize;
+      fn op_load_regs_i(&mut self, rx: usize) -> Result<(), ChipError> {
+            self.check_memory(self.index as usize, rx + 1)?;
             for j in 0..(rx+1) {
                   let val = self.memory[self.index as usize + j];
                   self.store(j, val);
             }
+            let index = self.index as usize;
+            self.trace_read(index, rx + 1);
+            if self.quirks.load_store_increment {
+                  self.index = self.index.wrapping_add((rx + 1) as u16);
+            }
+
+            self.program_counter = self.program_counter.wrapping_add(2);
+            Ok(())
+      }
+      // Fx75 - LD R, Vx
+      fn op_store_rpl(&mut self, rx: usize) {
+            for j in 0..(rx+1) {
+                  self.rpl[j] = self.load(j);
+            }
+
+            self.program_counter = self.program_counter.wrapping_add(2);
+      }
+      // Fx85 - LD Vx, R
+      fn op_load_rpl(&mut self, rx: usize) {
+            for j in 0..(rx+1) {
+                  let val = self.rpl[j];
+                  self.store(j, val);
+            }
 
-            self.program_counter += 2;
+            self.program_counter = self.program_counter.wrapping_add(2);
       }
 }
\ No newline at end of file
@@ -0,0 +1,730 @@
+/*
+* Run control for debugging: pausing, single-stepping, stepping over calls
+* and continuing, breakpoints, plus a text view of the machine for
+* frontends to show.
+*
+* The debugger sits between the scheduler and the machine: hand `cycle` to
+* `Scheduler::run_frame_until`, and it runs instructions while the machine
+* is meant to be running, and ends the frame as soon as it pauses.
+*
+* Breakpoints are written as text, one per string:
+* - `pc 0x2A4`: the PC reaches 2A4, before the instruction there runs
+* - `read 0x300`: an instruction reads the byte at 300 as data
+* - `write 0x300`: ... writes it
+* - `access 0x300`: ... does either
+* - `change V3`: an instruction changes V3 (or I, SP, DT, ST, PC)
+* - `sound`: Fx18 starts the sound timer
+* - `keywait`: Fx0A starts waiting for a key
+* - `collision`: Dxyn turns off a pixel
+* - `if V3 == 0x10`: the condition turns true
+*
+* Any of the first eight may end in `if` and a condition, checked when
+* the rest matches. Conditions are expressions over numbers (decimal, `0x`
+* hex or `0b` binary), the registers above and `[addr]`, the byte at an
+* address, with Rust's operators and precedence: unary `!` and `-`, then
+* `* / %`, `+ -`, `<< >>`, `&`, `^`, `|`, comparisons, `&&` and `||`.
+* Comparisons are 1 when true and 0 when false, and a condition holds when
+* it is not 0, as in `V3 == 0x10 && I > 0x300`.
+*/
+
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Write;
+use core::mem;
+#[cfg(feature = "std")]
+use std::error::Error;
+
+use chip8::{Chip, ChipError, Event, MEMORY_SIZE};
+use instruction;
+use instruction::Instruction;
+
+// Instructions listed before and after the program counter
+const LISTING_BEFORE: usize = 6;
+const LISTING_AFTER: usize = 8;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BreakpointError {
+      pub spec: String,
+      pub message: String
+}
+
+impl fmt::Display for BreakpointError {
+      fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "bad breakpoint `{}`: {}", self.spec, self.message)
+      }
+}
+
+#[cfg(feature = "std")]
+impl Error for BreakpointError {}
+
+// Parse results; the error is completed into a `BreakpointError` at the top
+type Parsed<T> = Result<T, String>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Register {
+      V(u8),
+      I,
+      Pc,
+      Sp,
+      Dt,
+      St
+}
+
+impl Register {
+      pub fn from_name(name: &str) -> Option<Register> {
+            let name = name.to_ascii_uppercase();
+            Some(match name.as_str() {
+                  "I" => Register::I,
+                  "PC" => Register::Pc,
+                  "SP" => Register::Sp,
+                  "DT" => Register::Dt,
+                  "ST" => Register::St,
+                  _ if name.len() == 2 && name.starts_with('V') =>
+                        Register::V(u8::from_str_radix(&name[1..], 16).ok()?),
+                  _ => return None
+            })
+      }
+
+      pub fn read(&self, chip: &Chip) -> u16 {
+            match *self {
+                  Register::V(x) => chip.registers[x as usize] as u16,
+                  Register::I => chip.index,
+                  Register::Pc => chip.program_counter,
+                  Register::Sp => chip.stack_pointer as u16,
+                  Register::Dt => chip.delay_timer as u16,
+                  Register::St => chip.sound_timer as u16
+            }
+      }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operator {
+      Mul, Div, Rem,
+      Add, Sub,
+      Shl, Shr,
+      And, Xor, Or,
+      Eq, Ne, Lt, Gt, Le, Ge,
+      LogicalAnd, LogicalOr
+}
+
+// Binary operators by precedence, loosest first
+const PRECEDENCE: [&[(&str, Operator)]; 9] = [
+      &[("||", Operator::LogicalOr)],
+      &[("&&", Operator::LogicalAnd)],
+      &[("==", Operator::Eq), ("!=", Operator::Ne), ("<=", Operator::Le), (">=", Operator::Ge),
+        ("<", Operator::Lt), (">", Operator::Gt)],
+      &[("|", Operator::Or)],
+      &[("^", Operator::Xor)],
+      &[("&", Operator::And)],
+      &[("<<", Operator::Shl), (">>", Operator::Shr)],
+      &[("+", Operator::Add), ("-", Operator::Sub)],
+      &[("*", Operator::Mul), ("/", Operator::Div), ("%", Operator::Rem)]
+];
+
+// Every symbol the tokenizer knows, longest first
+const SYMBOLS: [&str; 23] = [
+      "||", "&&", "==", "!=", "<=", ">=", "<<", ">>",
+      "<", ">", "|", "^", "&", "+", "-", "*", "/", "%", "!", "(", ")", "[", "]"
+];
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+      Number(i64),
+      Name(String),
+      Symbol(&'static str)
+}
+
+impl fmt::Display for Token {
+      fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match *self {
+                  Token::Number(n) => write!(f, "{}", n),
+                  Token::Name(ref name) => write!(f, "{}", name),
+                  Token::Symbol(symbol) => write!(f, "{}", symbol)
+            }
+      }
+}
+
+fn tokenize(text: &str) -> Parsed<Vec<Token>> {
+      let mut tokens = Vec::new();
+      let mut rest = text.trim_start();
+      while let Some(c) = rest.chars().next() {
+            let len = if c.is_ascii_alphanumeric() || c == '_' {
+                  let len = rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(rest.len());
+                  let word = &rest[..len];
+                  let number = if word.starts_with("0x") || word.starts_with("0X") {
+                        Some(i64::from_str_radix(&word[2..], 16))
+                  } else if word.starts_with("0b") || word.starts_with("0B") {
+                        Some(i64::from_str_radix(&word[2..], 2))
+                  } else if c.is_ascii_digit() {
+                        Some(word.parse())
+                  } else {
+                        None
+                  };
+                  tokens.push(match number {
+                        Some(Ok(n)) => Token::Number(n),
+                        Some(Err(_)) => return Err(format!("bad number `{}`", word)),
+                        None => Token::Name(word.to_string())
+                  });
+                  len
+            } else {
+                  match SYMBOLS.iter().find(|s| rest.starts_with(*s)) {
+                        Some(symbol) => {
+                              tokens.push(Token::Symbol(symbol));
+                              symbol.len()
+                        },
+                        None => return Err(format!("unexpected `{}`", c))
+                  }
+            };
+            rest = rest[len..].trim_start();
+      }
+      Ok(tokens)
+}
+
+/**
+* A condition, as parsed from text; see the top of this file for the
+* syntax.
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+      Number(i64),
+      Register(Register),
+      // The byte at an address
+      Memory(Box<Expr>),
+      Not(Box<Expr>),
+      Negate(Box<Expr>),
+      Binary(Operator, Box<Expr>, Box<Expr>)
+}
+
+impl Expr {
+      pub fn parse(text: &str) -> Result<Expr, BreakpointError> {
+            let error = |message| BreakpointError { spec: text.to_string(), message };
+            let tokens = tokenize(text).map_err(error)?;
+            let mut parser = Parser { tokens, pos: 0 };
+            parser.expression().and_then(|e| parser.end().map(|_| e)).map_err(error)
+      }
+
+      pub fn eval(&self, chip: &Chip) -> i64 {
+            match *self {
+                  Expr::Number(n) => n,
+                  Expr::Register(r) => r.read(chip) as i64,
+                  Expr::Memory(ref addr) => {
+                        let addr = addr.eval(chip) as usize;
+                        chip.memory[addr % chip.memory.len()] as i64
+                  },
+                  Expr::Not(ref e) => (e.eval(chip) == 0) as i64,
+                  Expr::Negate(ref e) => e.eval(chip).wrapping_neg(),
+                  Expr::Binary(op, ref lhs, ref rhs) => {
+                        let a = lhs.eval(chip);
+                        // Short-circuit, so the right side is only read when needed
+                        match op {
+                              Operator::LogicalAnd => return (a != 0 && rhs.eval(chip) != 0) as i64,
+                              Operator::LogicalOr => return (a != 0 || rhs.eval(chip) != 0) as i64,
+                              _ => ()
+                        }
+                        let b = rhs.eval(chip);
+                        match op {
+                              Operator::Mul => a.wrapping_mul(b),
+                              // Dividing by zero gives zero rather than stopping the machine
+                              Operator::Div => a.checked_div(b).unwrap_or(0),
+                              Operator::Rem => a.checked_rem(b).unwrap_or(0),
+                              Operator::Add => a.wrapping_add(b),
+                              Operator::Sub => a.wrapping_sub(b),
+                              Operator::Shl => a.wrapping_shl(b as u32),
+                              Operator::Shr => a.wrapping_shr(b as u32),
+                              Operator::And => a & b,
+                              Operator::Xor => a ^ b,
+                              Operator::Or => a | b,
+                              Operator::Eq => (a == b) as i64,
+                              Operator::Ne => (a != b) as i64,
+                              Operator::Lt => (a < b) as i64,
+                              Operator::Gt => (a > b) as i64,
+                              Operator::Le => (a <= b) as i64,
+                              Operator::Ge => (a >= b) as i64,
+                              Operator::LogicalAnd | Operator::LogicalOr => unreachable!()
+                        }
+                  }
+            }
+      }
+      pub fn holds(&self, chip: &Chip) -> bool {
+            self.eval(chip) != 0
+      }
+}
+
+struct Parser {
+      tokens: Vec<Token>,
+      pos: usize
+}
+
+impl Parser {
+      fn peek(&self) -> Option<&Token> {
+            self.tokens.get(self.pos)
+      }
+      fn next(&mut self) -> Option<Token> {
+            let token = self.tokens.get(self.pos).cloned();
+            self.pos += 1;
+            token
+      }
+      fn end(&self) -> Parsed<()> {
+            match self.peek() {
+                  Some(token) => Err(format!("unexpected `{}`", token)),
+                  None => Ok(())
+            }
+      }
+      fn expect(&mut self, symbol: &str) -> Parsed<()> {
+            match self.next() {
+                  Some(Token::Symbol(s)) if s == symbol => Ok(()),
+                  _ => Err(format!("missing `{}`", symbol))
+            }
+      }
+      // A bare number, as breakpoint addresses are
+      fn number(&mut self) -> Parsed<i64> {
+            match self.next() {
+                  Some(Token::Number(n)) => Ok(n),
+                  Some(token) => Err(format!("expected a number, found `{}`", token)),
+                  None => Err(String::from("expected a number"))
+            }
+      }
+      // A breakpoint address, which has to lie in the 64K address space
+      fn address(&mut self) -> Parsed<u16> {
+            let addr = self.number()?;
+            if addr < 0 || addr >= MEMORY_SIZE as i64 {
+                  return Err(format!("address {:#X} is outside memory", addr));
+            }
+            Ok(addr as u16)
+      }
+
+      fn expression(&mut self) -> Parsed<Expr> {
+            self.binary(0)
+      }
+      fn binary(&mut self, level: usize) -> Parsed<Expr> {
+            if level == PRECEDENCE.len() {
+                  return self.unary();
+            }
+            let mut lhs = self.binary(level + 1)?;
+            loop {
+                  let op = match self.peek() {
+                        Some(&Token::Symbol(s)) => PRECEDENCE[level].iter().find(|&&(name, _)| name == s).map(|&(_, op)| op),
+                        _ => None
+                  };
+                  let op = match op {
+                        Some(op) => op,
+                        None => return Ok(lhs)
+                  };
+                  self.pos += 1;
+                  let rhs = self.binary(level + 1)?;
+                  lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+            }
+      }
+      fn unary(&mut self) -> Parsed<Expr> {
+            match self.next() {
+                  Some(Token::Number(n)) => Ok(Expr::Number(n)),
+                  Some(Token::Name(name)) => match Register::from_name(&name) {
+                        Some(r) => Ok(Expr::Register(r)),
+                        None => Err(format!("unknown register `{}`", name))
+                  },
+                  Some(Token::Symbol("!")) => Ok(Expr::Not(Box::new(self.unary()?))),
+                  Some(Token::Symbol("-")) => Ok(Expr::Negate(Box::new(self.unary()?))),
+                  Some(Token::Symbol("(")) => {
+                        let e = self.expression()?;
+                        self.expect(")")?;
+                        Ok(e)
+                  },
+                  Some(Token::Symbol("[")) => {
+                        let addr = self.expression()?;
+                        self.expect("]")?;
+                        Ok(Expr::Memory(Box::new(addr)))
+                  },
+                  Some(token) => Err(format!("unexpected `{}`", token)),
+                  None => Err(String::from("unexpected end of condition"))
+            }
+      }
+}
+
+/**
+* What a breakpoint watches for; memory addresses are single bytes.
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Trigger {
+      // The PC reaching an address, before the instruction there runs
+      Address(u16),
+      Read(usize),
+      Write(usize),
+      // A read or a write
+      Access(usize),
+      Change(Register),
+      Event(Event),
+      // The condition turning true, checked before every instruction
+      Condition
+}
+
+impl Trigger {
+      // Whether the machine must trace memory accesses and events for this to fire.
+      fn traced(&self) -> bool {
+            matches!(*self, Trigger::Read(_) | Trigger::Write(_) | Trigger::Access(_) | Trigger::Event(_))
+      }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Breakpoint {
+      pub trigger: Trigger,
+      pub condition: Option<Expr>,
+      // As written, for display
+      spec: String,
+      // Whether a `Condition` breakpoint held before the last instruction
+      held: bool
+}
+
+impl Breakpoint {
+      // Parses a breakpoint written as described at the top of this file.
+      pub fn parse(spec: &str) -> Result<Breakpoint, BreakpointError> {
+            let error = |message| BreakpointError { spec: spec.to_string(), message };
+            let tokens = tokenize(spec).map_err(error)?;
+            let mut parser = Parser { tokens, pos: 0 };
+            Breakpoint::parse_tokens(&mut parser)
+                  .map(|(trigger, condition)| Breakpoint {
+                        trigger,
+                        condition,
+                        spec: spec.trim().to_string(),
+                        held: false
+                  })
+                  .map_err(error)
+      }
+
+      fn parse_tokens(parser: &mut Parser) -> Parsed<(Trigger, Option<Expr>)> {
+            let kind = match parser.next() {
+                  Some(Token::Name(name)) => name.to_ascii_lowercase(),
+                  Some(token) => return Err(format!("unknown breakpoint `{}`", token)),
+                  None => return Err(String::from("empty breakpoint"))
+            };
+            let trigger = match kind.as_str() {
+                  "pc" => Trigger::Address(parser.address()?),
+                  "read" => Trigger::Read(parser.address()? as usize),
+                  "write" => Trigger::Write(parser.address()? as usize),
+                  "access" => Trigger::Access(parser.address()? as usize),
+                  "change" => match parser.next().and_then(|t| match t {
+                        Token::Name(name) => Register::from_name(&name),
+                        _ => None
+                  }) {
+                        Some(r) => Trigger::Change(r),
+                        None => return Err(String::from("expected a register"))
+                  },
+                  "sound" => Trigger::Event(Event::SoundStart),
+                  "keywait" => Trigger::Event(Event::KeyWait),
+                  "collision" => Trigger::Event(Event::Collision),
+                  "if" => {
+                        let condition = parser.expression()?;
+                        parser.end()?;
+                        return Ok((Trigger::Condition, Some(condition)));
+                  },
+                  _ => return Err(format!("unknown breakpoint `{}`", kind))
+            };
+            let condition = match parser.next() {
+                  Some(Token::Name(ref word)) if word == "if" => Some(parser.expression()?),
+                  Some(token) => return Err(format!("unexpected `{}`", token)),
+                  None => None
+            };
+            parser.end()?;
+            Ok((trigger, condition))
+      }
+
+      /**
+      * Checks that the addresses the breakpoint watches are in the memory
+      * of `chip`; one past the end of a 4K machine would never fire.
+      */
+      pub fn check(&self, chip: &Chip) -> Result<(), BreakpointError> {
+            let addr = match self.trigger {
+                  Trigger::Address(addr) => addr as usize,
+                  Trigger::Read(addr) | Trigger::Write(addr) | Trigger::Access(addr) => addr,
+                  _ => return Ok(())
+            };
+            if addr >= chip.memory.len() {
+                  return Err(BreakpointError {
+                        spec: self.spec.clone(),
+                        message: format!("address {:#X} is outside the machine's {:#X} bytes of memory", addr, chip.memory.len())
+                  });
+            }
+            Ok(())
+      }
+
+      fn condition_holds(&self, chip: &Chip) -> bool {
+            match self.condition {
+                  Some(ref c) => c.holds(chip),
+                  None => true
+            }
+      }
+}
+
+impl fmt::Display for Breakpoint {
+      fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{}", self.spec)
+      }
+}
+
+/**
+* Why the debugger last stopped: the breakpoint, by its index in
+* `Debugger::breakpoints`, and the address of the instruction it stopped
+* at (which has not run yet for `pc` breakpoints and conditions, and has
+* for the rest).
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hit {
+      pub breakpoint: usize,
+      pub pc: u16
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Debugger {
+      paused: bool,
+      // Instructions left to run while paused
+      steps: u32,
+      // Where a step over a call ends: the return address, and the stack depth there
+      step_over: Option<(u16, usize)>,
+      breakpoints: Vec<Breakpoint>,
+      hit: Option<Hit>,
+      // The address a `pc` breakpoint stopped at, so continuing does not stop there again at once
+      stopped_at: Option<u16>
+}
+
+impl Debugger {
+      pub fn new() -> Debugger {
+            Debugger::default()
+      }
+
+      pub fn paused(&self) -> bool {
+            self.paused
+      }
+      pub fn pause(&mut self) {
+            self.paused = true;
+            self.steps = 0;
+            self.step_over = None;
+      }
+      pub fn resume(&mut self) {
+            self.paused = false;
+            self.steps = 0;
+            self.step_over = None;
+            self.hit = None;
+      }
+      pub fn toggle_pause(&mut self) {
+            if self.paused {
+                  self.resume();
+            } else {
+                  self.pause();
+            }
+      }
+
+      // Runs the next instruction, then pauses again; only while paused.
+      pub fn step(&mut self) {
+            if self.paused {
+                  self.steps += 1;
+                  self.hit = None;
+            }
+      }
+      /**
+      * Like `step`, except that a call runs until the subroutine returns to
+      * the instruction after it, however many frames that takes. Breakpoints
+      * inside the subroutine still stop it.
+      */
+      pub fn step_over(&mut self, chip: &Chip) {
+            if !self.paused {
+                  return;
+            }
+            match instruction::decode(chip.fetch()) {
+                  Instruction::Call(_) => {
+                        self.resume();
+                        self.step_over = Some((chip.program_counter.wrapping_add(2), chip.stack_pointer));
+                  },
+                  _ => self.step()
+            }
+      }
+
+      // Adds a breakpoint, and returns its index.
+      pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> usize {
+            self.breakpoints.push(breakpoint);
+            self.breakpoints.len() - 1
+      }
+      pub fn remove_breakpoint(&mut self, index: usize) -> Breakpoint {
+            self.hit = None;
+            self.breakpoints.remove(index)
+      }
+      pub fn breakpoints(&self) -> &[Breakpoint] {
+            &self.breakpoints
+      }
+      // The breakpoint the machine is paused at, if any.
+      pub fn hit(&self) -> Option<Hit> {
+            self.hit
+      }
+
+      /**
+      * Whether a 60Hz frame elapses for the machine: always while it runs,
+      * and while paused only for a step stalled on the display wait quirk,
+      * which cannot complete before the next tick.
+      */
+      pub fn frame_elapses(&self, chip: &Chip) -> bool {
+            !self.paused || (self.steps > 0 && chip.waiting_for_vblank())
+      }
+
+      /**
+      * Runs the instruction at the program counter, if the machine is meant
+      * to run at all, and returns its cost; `None` ends the frame, because
+      * the machine is paused. Steps taken while paused ignore breakpoints.
+      */
+      pub fn cycle(&mut self, chip: &mut Chip) -> Result<Option<u32>, ChipError> {
+            if self.paused {
+                  if self.steps == 0 {
+                        return Ok(None);
+                  }
+                  self.steps -= 1;
+                  self.stopped_at = None;
+                  return chip.cycle().map(Some);
+            }
+
+            let pc = chip.program_counter;
+            let stopped_at = self.stopped_at.take();
+            if let Some(n) = self.check_before(chip, stopped_at) {
+                  self.stop(n, pc);
+                  self.stopped_at = Some(pc);
+                  return Ok(None);
+            }
+
+            // Also turns tracing off once the last traced breakpoint is removed
+            chip.set_trace(self.breakpoints.iter().any(|b| b.trigger.traced()));
+            let cost = if self.breakpoints.is_empty() {
+                  chip.cycle()?
+            } else {
+                  let before: Vec<u16> = self.breakpoints.iter().map(|b| match b.trigger {
+                        Trigger::Change(r) => r.read(chip),
+                        _ => 0
+                  }).collect();
+                  let cost = chip.cycle()?;
+                  if let Some(n) = self.check_after(chip, &before) {
+                        self.stop(n, pc);
+                        return Ok(Some(cost));
+                  }
+                  cost
+            };
+            if let Some((pc, depth)) = self.step_over {
+                  if chip.program_counter == pc && chip.stack_pointer == depth {
+                        self.pause();
+                  }
+            }
+            Ok(Some(cost))
+      }
+
+      fn stop(&mut self, breakpoint: usize, pc: u16) {
+            self.pause();
+            self.hit = Some(Hit { breakpoint, pc });
+      }
+
+      // The first breakpoint that fires before the instruction at the PC runs.
+      fn check_before(&mut self, chip: &Chip, stopped_at: Option<u16>) -> Option<usize> {
+            let mut hit = None;
+            for (n, b) in self.breakpoints.iter_mut().enumerate() {
+                  let fired = match b.trigger {
+                        Trigger::Address(addr) =>
+                              addr == chip.program_counter && stopped_at != Some(addr) && b.condition_holds(chip),
+                        // Only a change from false to true fires, or continuing would stop again at once
+                        Trigger::Condition => {
+                              let holds = b.condition_holds(chip);
+                              let held = mem::replace(&mut b.held, holds);
+                              holds && !held
+                        },
+                        _ => false
+                  };
+                  if fired && hit.is_none() {
+                        hit = Some(n);
+                  }
+            }
+            hit
+      }
+
+      // The first watchpoint the last instruction fired; `before` holds the watched registers' old values.
+      fn check_after(&self, chip: &Chip, before: &[u16]) -> Option<usize> {
+            self.breakpoints.iter().enumerate().position(|(n, b)| {
+                  let fired = match (b.trigger, chip.trace()) {
+                        (Trigger::Read(addr), Some(trace)) => trace.read(addr),
+                        (Trigger::Write(addr), Some(trace)) => trace.wrote(addr),
+                        (Trigger::Access(addr), Some(trace)) => trace.read(addr) || trace.wrote(addr),
+                        (Trigger::Event(event), Some(trace)) => trace.raised(event),
+                        (Trigger::Change(r), _) => r.read(chip) != before[n],
+                        _ => false
+                  };
+                  fired && b.condition_holds(chip)
+            })
+      }
+
+      /**
+      * The machine as a debugger shows it: registers, stack, timers, keypad
+      * and a disassembly around the program counter, marked with `>`, then
+      * the breakpoints, with the one stopped at marked with `*`.
+      */
+      pub fn describe(&self, chip: &Chip) -> String {
+            let mut out = String::new();
+
+            let status = if self.paused { " (paused)" } else { "" };
+            writeln!(out, "Machine {}{}", chip.state, status).unwrap();
+            match self.hit {
+                  Some(hit) => writeln!(out, "Break: {} at {:04X}", self.breakpoints[hit.breakpoint], hit.pc).unwrap(),
+                  None => writeln!(out).unwrap()
+            }
+            writeln!(out, "PC {:04X}  I {:04X}  SP {:X}", chip.program_counter, chip.index, chip.stack_pointer).unwrap();
+            for (row, values) in chip.registers.chunks(4).enumerate() {
+                  let cells: Vec<String> = values.iter().enumerate()
+                        .map(|(col, v)| format!("V{:X} {:02X}", row * 4 + col, v))
+                        .collect();
+                  writeln!(out, "{}", cells.join("  ")).unwrap();
+            }
+            writeln!(out, "DT {:02X}  ST {:02X}", chip.delay_timer, chip.sound_timer).unwrap();
+
+            write!(out, "Stack").unwrap();
+            if chip.stack_pointer == 0 {
+                  write!(out, " empty").unwrap();
+            }
+            for addr in chip.stack[..chip.stack_pointer].iter() {
+                  write!(out, " {:04X}", addr).unwrap();
+            }
+            writeln!(out).unwrap();
+
+            // Pressed keys by hex digit, in keypad order
+            write!(out, "Keys ").unwrap();
+            for (key, &pressed) in chip.keypad.iter().enumerate() {
+                  if pressed {
+                        write!(out, "{:X}", key).unwrap();
+                  } else {
+                        write!(out, ".").unwrap();
+                  }
+            }
+            writeln!(out).unwrap();
+            writeln!(out).unwrap();
+
+            // Decoding backwards is ambiguous, so the listing starts a few
+            // words back and assumes the code there is aligned with the PC
+            let pc = chip.program_counter as usize;
+            let mut addr = pc.saturating_sub(LISTING_BEFORE * 2);
+            let mut lines = 0;
+            while lines <= LISTING_BEFORE + LISTING_AFTER && addr + 1 < chip.memory.len() {
+                  let opcode = (chip.memory[addr] as u16) << 8 | chip.memory[addr + 1] as u16;
+                  let instruction = instruction::decode(opcode);
+                  let marker = if addr == pc { ">" } else { " " };
+                  writeln!(out, "{} {:04X}  {:04X}  {}", marker, addr, opcode, instruction).unwrap();
+                  // Only an instruction at the PC is known to be one; elsewhere
+                  // F000 may just as well be data
+                  addr = match instruction {
+                        Instruction::LoadILong if addr == pc => addr + instruction.len() as usize,
+                        _ => addr + 2
+                  };
+                  lines += 1;
+            }
+
+            if !self.breakpoints.is_empty() {
+                  writeln!(out).unwrap();
+                  writeln!(out, "Breakpoints").unwrap();
+                  for (n, b) in self.breakpoints.iter().enumerate() {
+                        let marker = if self.hit.map(|h| h.breakpoint) == Some(n) { "*" } else { " " };
+                        writeln!(out, "{} {}", marker, b).unwrap();
+                  }
+            }
+            out
+      }
+}
@@ -0,0 +1,363 @@
+/*
+* Disassembler: turns a ROM back into source for Chipper or Octo.
+*
+* Code is told apart from data by following jumps, calls and skips from the
+* load address (see `recompiler::discover`); whatever is never reached is
+* emitted as bytes. Jump and call targets, and the addresses I is loaded
+* with, get generated labels. Instructions a syntax cannot express (XO-CHIP
+* opcodes in Chipper, 0nnn in Octo, ...) are emitted as bytes too, with the
+* mnemonic in a comment, so the output always re-assembles to the exact
+* bytes of the ROM.
+*/
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use chip8::Quirks;
+use instruction::Instruction;
+use recompiler;
+
+// Data bytes per line
+const BYTES_PER_LINE: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Syntax {
+      // CHIPPER 2.11, the assembler the bundled .SRC files are written for
+      Chipper,
+      // Octo, the usual language for new CHIP-8 and XO-CHIP programs
+      Octo
+}
+
+impl Syntax {
+      pub fn from_name(name: &str) -> Option<Syntax> {
+            match name {
+                  "chipper" => Some(Syntax::Chipper),
+                  "octo" => Some(Syntax::Octo),
+                  _ => None
+            }
+      }
+
+      fn comment(&self) -> &'static str {
+            match *self {
+                  Syntax::Chipper => ";",
+                  Syntax::Octo => "#"
+            }
+      }
+      fn byte(&self, b: u8) -> String {
+            match *self {
+                  Syntax::Chipper => format!("#{:02X}", b),
+                  Syntax::Octo => format!("0x{:02X}", b)
+            }
+      }
+      fn bytes(&self, bytes: &[u8]) -> String {
+            let bytes: Vec<String> = bytes.iter().map(|&b| self.byte(b)).collect();
+            match *self {
+                  Syntax::Chipper => format!("DB {}", bytes.join(", ")),
+                  Syntax::Octo => bytes.join(" ")
+            }
+      }
+}
+
+// The address an instruction refers to, if any; F000 takes it from the word that follows.
+fn target(data: &[u8], offset: usize, instruction: &Instruction) -> Option<u16> {
+      use instruction::Instruction::*;
+
+      match *instruction {
+            Jump(nnn) | Call(nnn) | LoadI(nnn) | JumpOffset(nnn) => Some(nnn),
+            LoadILong => Some((data[offset + 2] as u16) << 8 | data[offset + 3] as u16),
+            _ => None
+      }
+}
+
+struct Listing<'a> {
+      data: &'a [u8],
+      base: u16,
+      syntax: Syntax,
+      // Instructions by offset into the ROM, none of them overlapping
+      code: BTreeMap<usize, Instruction>,
+      labels: BTreeMap<usize, String>
+}
+
+impl<'a> Listing<'a> {
+      fn new(data: &'a [u8], base: u16, syntax: Syntax) -> Listing<'a> {
+            let mut starts = BTreeMap::new();
+            for block in recompiler::discover(data, base, &Quirks::default()).values() {
+                  for &(addr, instruction) in block.instructions.iter() {
+                        starts.insert((addr - base) as usize, instruction);
+                  }
+            }
+            // An instruction that runs into another one, or past the end of
+            // the ROM, can only be given as bytes
+            let mut code = BTreeMap::new();
+            for (&offset, instruction) in starts.iter() {
+                  let end = offset + instruction.len() as usize;
+                  if end <= data.len() && starts.range(offset + 1..end).next().is_none() {
+                        code.insert(offset, *instruction);
+                  }
+            }
+
+            let mut listing = Listing { data, base, syntax, code, labels: BTreeMap::new() };
+            let mut called = BTreeSet::new();
+            let mut targets = BTreeSet::new();
+            for (&offset, instruction) in listing.code.iter() {
+                  if let Some(addr) = target(data, offset, instruction) {
+                        targets.insert(addr);
+                        if let Instruction::Call(_) = *instruction {
+                              called.insert(addr);
+                        }
+                  }
+            }
+            for addr in targets {
+                  let offset = match addr.checked_sub(base) {
+                        Some(offset) if (offset as usize) < data.len() => offset as usize,
+                        _ => continue
+                  };
+                  let name = if called.contains(&addr) && listing.code.contains_key(&offset) {
+                        format!("sub_{:03X}", addr)
+                  } else if listing.code.contains_key(&offset) {
+                        format!("label_{:03X}", addr)
+                  } else if listing.inside_code(offset) {
+                        // Labels cannot split an instruction; refer to it by address
+                        continue;
+                  } else {
+                        format!("data_{:03X}", addr)
+                  };
+                  listing.labels.insert(offset, name);
+            }
+            // Octo starts programs at `main`
+            if syntax == Syntax::Octo {
+                  listing.labels.insert(0, String::from("main"));
+            }
+            listing
+      }
+
+      // Whether `offset` lies within an instruction, but not at its start.
+      fn inside_code(&self, offset: usize) -> bool {
+            match self.code.range(..offset).next_back() {
+                  Some((&start, instruction)) => offset < start + instruction.len() as usize,
+                  None => false
+            }
+      }
+
+      fn label(&self, addr: u16) -> Option<&String> {
+            addr.checked_sub(self.base).and_then(|offset| self.labels.get(&(offset as usize)))
+      }
+      // A label for `addr`, or the address itself.
+      fn addr(&self, addr: u16) -> String {
+            match (self.label(addr), self.syntax) {
+                  (Some(name), _) => name.clone(),
+                  (None, Syntax::Chipper) => format!("#{:03X}", addr),
+                  (None, Syntax::Octo) => format!("0x{:03X}", addr)
+            }
+      }
+
+      // Source for the instruction at `offset`, or `None` if the syntax has no way to write it.
+      fn instruction(&self, offset: usize, instruction: &Instruction) -> Option<String> {
+            let addr = target(self.data, offset, instruction);
+            let labelled = addr.and_then(|addr| self.label(addr)).is_some();
+            let target = addr.map(|addr| self.addr(addr));
+            match self.syntax {
+                  Syntax::Chipper => chipper(instruction, target),
+                  Syntax::Octo => octo(instruction, target, labelled)
+            }
+      }
+
+      fn write(&self, out: &mut String) {
+            let comment = self.syntax.comment();
+            let mut offset = 0;
+            while offset < self.data.len() {
+                  if let Some(name) = self.labels.get(&offset) {
+                        match self.syntax {
+                              Syntax::Chipper => writeln!(out, "{}:", name).unwrap(),
+                              Syntax::Octo => writeln!(out, ": {}", name).unwrap()
+                        }
+                  }
+                  if let Some(instruction) = self.code.get(&offset) {
+                        let len = instruction.len() as usize;
+                        match self.instruction(offset, instruction) {
+                              Some(text) => {
+                                    // Octo reads best with the skipped instruction on the same line
+                                    let next = offset + len;
+                                    let skipped = match self.code.get(&next) {
+                                          Some(i) if text.ends_with(" then") && !self.labels.contains_key(&next) =>
+                                                self.instruction(next, i).filter(|t| !t.ends_with(" then")).map(|t| (t, i.len())),
+                                          _ => None
+                                    };
+                                    match skipped {
+                                          Some((then, then_len)) => {
+                                                writeln!(out, "        {} {}", text, then).unwrap();
+                                                offset += then_len as usize;
+                                          },
+                                          None => writeln!(out, "        {}", text).unwrap()
+                                    }
+                              },
+                              None => writeln!(out, "        {}  {} {}", self.syntax.bytes(&self.data[offset..offset + len]),
+                                               comment, instruction).unwrap()
+                        }
+                        offset += len;
+                        continue;
+                  }
+                  // Data runs up to the next label or instruction
+                  let mut end = offset + 1;
+                  while end < self.data.len() && end - offset < BYTES_PER_LINE
+                        && !self.labels.contains_key(&end) && !self.code.contains_key(&end) {
+                        end += 1;
+                  }
+                  writeln!(out, "        {}", self.syntax.bytes(&self.data[offset..end])).unwrap();
+                  offset = end;
+            }
+      }
+}
+
+fn chipper(instruction: &Instruction, target: Option<String>) -> Option<String> {
+      use instruction::Instruction::*;
+
+      let target = target.unwrap_or_default();
+      Some(match *instruction {
+            ScrollDown(n) => format!("SCD #{:X}", n),
+            Clear => String::from("CLS"),
+            Return => String::from("RET"),
+            ScrollRight => String::from("SCR"),
+            ScrollLeft => String::from("SCL"),
+            Exit => String::from("EXIT"),
+            Lores => String::from("LOW"),
+            Hires => String::from("HIGH"),
+            Jump(_) => format!("JP {}", target),
+            Call(_) => format!("CALL {}", target),
+            SkipEqImm(x, kk) => format!("SE V{:X}, #{:02X}", x, kk),
+            SkipNeImm(x, kk) => format!("SNE V{:X}, #{:02X}", x, kk),
+            SkipEqReg(x, y) => format!("SE V{:X}, V{:X}", x, y),
+            LoadImm(x, kk) => format!("LD V{:X}, #{:02X}", x, kk),
+            AddImm(x, kk) => format!("ADD V{:X}, #{:02X}", x, kk),
+            Move(x, y) => format!("LD V{:X}, V{:X}", x, y),
+            Or(x, y) => format!("OR V{:X}, V{:X}", x, y),
+            And(x, y) => format!("AND V{:X}, V{:X}", x, y),
+            Xor(x, y) => format!("XOR V{:X}, V{:X}", x, y),
+            Add(x, y) => format!("ADD V{:X}, V{:X}", x, y),
+            Sub(x, y) => format!("SUB V{:X}, V{:X}", x, y),
+            ShiftRight(x, y) => format!("SHR V{:X}, V{:X}", x, y),
+            SubN(x, y) => format!("SUBN V{:X}, V{:X}", x, y),
+            ShiftLeft(x, y) => format!("SHL V{:X}, V{:X}", x, y),
+            SkipNeReg(x, y) => format!("SNE V{:X}, V{:X}", x, y),
+            LoadI(_) => format!("LD I, {}", target),
+            JumpOffset(_) => format!("JP V0, {}", target),
+            Random(x, kk) => format!("RND V{:X}, #{:02X}", x, kk),
+            Draw(x, y, n) => format!("DRW V{:X}, V{:X}, #{:X}", x, y, n),
+            SkipKey(x) => format!("SKP V{:X}", x),
+            SkipNotKey(x) => format!("SKNP V{:X}", x),
+            LoadDelay(x) => format!("LD V{:X}, DT", x),
+            WaitKey(x) => format!("LD V{:X}, K", x),
+            SetDelay(x) => format!("LD DT, V{:X}", x),
+            SetSound(x) => format!("LD ST, V{:X}", x),
+            AddI(x) => format!("ADD I, V{:X}", x),
+            Font(x) => format!("LD F, V{:X}", x),
+            BigFont(x) => format!("LD HF, V{:X}", x),
+            Bcd(x) => format!("LD B, V{:X}", x),
+            StoreRegs(x) => format!("LD [I], V{:X}", x),
+            LoadRegs(x) => format!("LD V{:X}, [I]", x),
+            StoreFlags(x) => format!("LD R, V{:X}", x),
+            LoadFlags(x) => format!("LD V{:X}, R", x),
+            // 0nnn, and the XO-CHIP extensions
+            _ => return None
+      })
+}
+
+// `labelled` tells whether `target` is a label rather than a number; Octo can only call labels.
+fn octo(instruction: &Instruction, target: Option<String>, labelled: bool) -> Option<String> {
+      use instruction::Instruction::*;
+
+      let target = target.unwrap_or_default();
+      Some(match *instruction {
+            ScrollDown(n) => format!("scroll-down {}", n),
+            ScrollUp(n) => format!("scroll-up {}", n),
+            Clear => String::from("clear"),
+            Return => String::from("return"),
+            ScrollRight => String::from("scroll-right"),
+            ScrollLeft => String::from("scroll-left"),
+            Exit => String::from("exit"),
+            Lores => String::from("lores"),
+            Hires => String::from("hires"),
+            Jump(_) => format!("jump {}", target),
+            Call(_) if labelled => target,
+            // Skips are written as the condition under which the next instruction runs
+            SkipEqImm(x, kk) => format!("if v{:x} != 0x{:02X} then", x, kk),
+            SkipNeImm(x, kk) => format!("if v{:x} == 0x{:02X} then", x, kk),
+            SkipEqReg(x, y) => format!("if v{:x} != v{:x} then", x, y),
+            StoreRange(x, y) => format!("save v{:x} - v{:x}", x, y),
+            LoadRange(x, y) => format!("load v{:x} - v{:x}", x, y),
+            LoadImm(x, kk) => format!("v{:x} := 0x{:02X}", x, kk),
+            AddImm(x, kk) => format!("v{:x} += 0x{:02X}", x, kk),
+            Move(x, y) => format!("v{:x} := v{:x}", x, y),
+            Or(x, y) => format!("v{:x} |= v{:x}", x, y),
+            And(x, y) => format!("v{:x} &= v{:x}", x, y),
+            Xor(x, y) => format!("v{:x} ^= v{:x}", x, y),
+            Add(x, y) => format!("v{:x} += v{:x}", x, y),
+            Sub(x, y) => format!("v{:x} -= v{:x}", x, y),
+            ShiftRight(x, y) => format!("v{:x} >>= v{:x}", x, y),
+            SubN(x, y) => format!("v{:x} =- v{:x}", x, y),
+            ShiftLeft(x, y) => format!("v{:x} <<= v{:x}", x, y),
+            SkipNeReg(x, y) => format!("if v{:x} == v{:x} then", x, y),
+            LoadI(_) => format!("i := {}", target),
+            JumpOffset(_) => format!("jump0 {}", target),
+            Random(x, kk) => format!("v{:x} := random 0x{:02X}", x, kk),
+            Draw(x, y, n) => format!("sprite v{:x} v{:x} {}", x, y, n),
+            SkipKey(x) => format!("if v{:x} -key then", x),
+            SkipNotKey(x) => format!("if v{:x} key then", x),
+            LoadILong => format!("i := long {}", target),
+            Plane(n) => format!("plane {}", n),
+            Audio => String::from("audio"),
+            LoadDelay(x) => format!("v{:x} := delay", x),
+            WaitKey(x) => format!("v{:x} := key", x),
+            SetDelay(x) => format!("delay := v{:x}", x),
+            SetSound(x) => format!("buzzer := v{:x}", x),
+            AddI(x) => format!("i += v{:x}", x),
+            Font(x) => format!("i := hex v{:x}", x),
+            BigFont(x) => format!("i := bighex v{:x}", x),
+            Bcd(x) => format!("bcd v{:x}", x),
+            Pitch(x) => format!("pitch := v{:x}", x),
+            StoreRegs(x) => format!("save v{:x}", x),
+            LoadRegs(x) => format!("load v{:x}", x),
+            StoreFlags(x) => format!("saveflags v{:x}", x),
+            LoadFlags(x) => format!("loadflags v{:x}", x),
+            // 0nnn, calls to unlabelled addresses and undefined opcodes
+            _ => return None
+      })
+}
+
+// Whether Chipper needs SUPER-CHIP mode to assemble `instruction`.
+fn is_schip(instruction: &Instruction) -> bool {
+      use instruction::Instruction::*;
+
+      matches!(*instruction, ScrollDown(_) | ScrollRight | ScrollLeft | Exit | Lores | Hires | Draw(_, _, 0)
+                             | BigFont(_) | StoreFlags(_) | LoadFlags(_))
+}
+
+/**
+* Source for `data`, a ROM loaded at `base`, that re-assembles to exactly
+* the same bytes; `name` goes in the header comment.
+*/
+pub fn disassemble(name: &str, data: &[u8], base: u16, syntax: Syntax) -> String {
+      let listing = Listing::new(data, base, syntax);
+      let mut out = String::new();
+
+      writeln!(out, "{} Disassembled from `{}` by `chip8 disasm`", syntax.comment(), name).unwrap();
+      match syntax {
+            Syntax::Chipper => {
+                  writeln!(out, "        OPTION BINARY").unwrap();
+                  if listing.code.values().any(is_schip) {
+                        writeln!(out, "        OPTION SCHIP11").unwrap();
+                  }
+                  // Code may follow an odd number of data bytes
+                  writeln!(out, "        ALIGN OFF").unwrap();
+                  if base != 0x200 {
+                        writeln!(out, "        ORG #{:03X}", base).unwrap();
+                  }
+            },
+            Syntax::Octo => if base != 0x200 {
+                  writeln!(out, ":org 0x{:03X}", base).unwrap();
+            }
+      }
+      writeln!(out).unwrap();
+      listing.write(&mut out);
+      out
+}
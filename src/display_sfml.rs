@@ -1,16 +1,33 @@
 extern crate sfml;
-extern crate time;
 
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use audio;
 use chip8;
+use debugger::Debugger;
 use display::Display;
+use snapshot::Snapshot;
 
 const WINDOW_W: usize = 1200;
 const WINDOW_H: usize = 600;
-const WINDOW_LEN: usize = WINDOW_W * WINDOW_H; 
+// Width of the game area; the rest of the window is the debugger panel
+const SCREEN_W: f32 = 800.0;
+const PANEL_X: f32 = SCREEN_W + 5.0;
+const PANEL_FONT_SIZE: u32 = 12;
+
+// Colours for each composite pixel value: background, plane 1, plane 2, both planes
+const PALETTE: [[u8; 4]; 4] = [
+      [22, 34, 56, 255],
+      [116, 163, 252, 255],
+      [252, 163, 116, 255],
+      [240, 240, 240, 255]
+];
 
 use self::sfml::window::Key;
 use self::sfml::system::Vector2f;
-use self::sfml::audio::{Sound, SoundBuffer};
+use self::sfml::audio::{SoundStream, SoundStreamPlayer};
+use self::sfml::system::Time;
 use self::sfml::graphics::Font;
 
 const KEY_BINDINGS: [(u8, Key); 16] = [
@@ -32,6 +49,19 @@ const KEY_BINDINGS: [(u8, Key); 16] = [
       (0xF, Key::V)
 ];
 
+// Save states are kept next to the ROM
+const SAVE_STATE_KEY: Key = Key::F5;
+const LOAD_STATE_KEY: Key = Key::F9;
+const REWIND_KEY: Key = Key::BackSpace;
+const PAUSE_KEY: Key = Key::F6;
+const STEP_KEY: Key = Key::F7;
+const STEP_OVER_KEY: Key = Key::F8;
+const DEBUGGER_HELP: &str = "F6 pause/continue  F7 step  F8 step over";
+
+fn save_state_path(chip: &chip8::Chip) -> String {
+      format!("{}.state", chip.rom)
+}
+
 fn key_local_to_chip(k: Key) -> Option<u8> {
       for pair in KEY_BINDINGS.iter() {
             if pair.1 == k {
@@ -41,18 +71,72 @@ fn key_local_to_chip(k: Key) -> Option<u8> {
       None
 }
 
+// Samples for one 60Hz frame
+const FRAME_SAMPLES: usize = audio::SAMPLE_RATE as usize / 60;
+// Samples handed to SFML per request
+const CHUNK_SAMPLES: usize = FRAME_SAMPLES;
+// Drop audio beyond this many queued frames, instead of letting latency grow
+const MAX_QUEUED_SAMPLES: usize = FRAME_SAMPLES * 4;
+
+/**
+* Feeds the samples synthesised by the emulator to SFML's audio thread,
+* padding with silence whenever the emulator falls behind.
+*/
+pub struct AudioStream {
+      queue: Arc<Mutex<VecDeque<i16>>>,
+      chunk: [i16; CHUNK_SAMPLES]
+}
+
+impl AudioStream {
+      pub fn new() -> AudioStream {
+            AudioStream {
+                  queue: Arc::new(Mutex::new(VecDeque::new())),
+                  chunk: [0; CHUNK_SAMPLES]
+            }
+      }
+}
+
+impl Default for AudioStream {
+      fn default() -> AudioStream {
+            AudioStream::new()
+      }
+}
+
+impl SoundStream for AudioStream {
+      fn get_data(&mut self) -> (&mut [i16], bool) {
+            let mut queue = self.queue.lock().unwrap();
+            for sample in self.chunk.iter_mut() {
+                  *sample = queue.pop_front().unwrap_or(0);
+            }
+            (&mut self.chunk, true)
+      }
+      fn seek(&mut self, _offset: Time) {
+            self.queue.lock().unwrap().clear();
+      }
+      fn channel_count(&self) -> u32 {
+            1
+      }
+      fn sample_rate(&self) -> u32 {
+            audio::SAMPLE_RATE
+      }
+}
+
 pub struct DisplaySFML<'t> {
       window: sfml::graphics::RenderWindow,
       texture_data: [u8; chip8::DISPLAY_SIZE * 4],
       texture: sfml::graphics::Texture,
+      texture_size: (usize, usize),
       font: Font,
-      beep: Sound<'t>
+      audio_queue: Arc<Mutex<VecDeque<i16>>>,
+      audio_frame: [i16; FRAME_SAMPLES],
+      player: SoundStreamPlayer<'t, AudioStream>,
+      debugger: Debugger
 }
 
 impl<'t> DisplaySFML<'t> {
 
-      pub fn new(beep_buffer: &'t SoundBuffer) -> DisplaySFML<'t> {
-            use self::sfml::window::{Event, Style};
+      pub fn new(audio_stream: &'t mut AudioStream) -> DisplaySFML<'t> {
+            use self::sfml::window::Style;
             use self::sfml::graphics::RenderWindow;
             DisplaySFML {
                   window: RenderWindow::new(
@@ -63,24 +147,55 @@ impl<'t> DisplaySFML<'t> {
                   ),
                   texture_data: [0; chip8::DISPLAY_SIZE * 4],
                   texture: sfml::graphics::Texture::new(chip8::DISPLAY_W as u32, chip8::DISPLAY_H as u32).unwrap(),
+                  texture_size: (chip8::DISPLAY_W, chip8::DISPLAY_H),
                   font: sfml::graphics::Font::from_file("res/Hack-Regular.ttf").unwrap(),
-                  beep: Sound::with_buffer(beep_buffer)
+                  audio_queue: audio_stream.queue.clone(),
+                  audio_frame: [0; FRAME_SAMPLES],
+                  player: SoundStreamPlayer::new(audio_stream),
+                  debugger: Debugger::new()
             }
       }
       
       pub fn init(&mut self) {
             self.texture.set_repeated(false);
-            self.beep.set_looping(true);
+            self.player.play();
+      }
+
+      // Pausing and stepping follow the debugger hotkeys; run the machine through it.
+      pub fn debugger(&mut self) -> &mut Debugger {
+            &mut self.debugger
+      }
+
+      // Whether the user is holding the rewind key.
+      pub fn rewind_held(&self) -> bool {
+            REWIND_KEY.is_pressed()
       }
 }
 
 impl<'t> Display for DisplaySFML<'t> {
       fn update(&mut self, chip: &mut chip8::Chip) {
-            use self::sfml::window::{Event, Key};
+            use self::sfml::window::Event;
 
             while let Some(ev) = self.window.poll_event() {
                   match ev {
                         Event::Closed => self.window.close(),
+                        Event::KeyPressed { code, .. } if code == SAVE_STATE_KEY => {
+                              let path = save_state_path(chip);
+                              match chip.snapshot().save(&path) {
+                                    Ok(()) => println!("Saved state to `{}`", path),
+                                    Err(e) => println!("{}", e)
+                              }
+                        },
+                        Event::KeyPressed { code, .. } if code == LOAD_STATE_KEY => {
+                              let path = save_state_path(chip);
+                              match Snapshot::load(&path).and_then(|s| chip.restore(&s)) {
+                                    Ok(()) => println!("Loaded state from `{}`", path),
+                                    Err(e) => println!("{}", e)
+                              }
+                        },
+                        Event::KeyPressed { code, .. } if code == PAUSE_KEY => self.debugger.toggle_pause(),
+                        Event::KeyPressed { code, .. } if code == STEP_KEY => self.debugger.step(),
+                        Event::KeyPressed { code, .. } if code == STEP_OVER_KEY => self.debugger.step_over(chip),
                         Event::KeyPressed { code, .. } => {
                               if let Some(key) = key_local_to_chip(code) {
                                     chip.key_pressed = key;
@@ -96,40 +211,55 @@ impl<'t> Display for DisplaySFML<'t> {
             }
 
 
-            // Play / stop beep
-            if chip.sound_timer > 0 {
-                  if self.beep.status() != sfml::audio::SoundStatus::Playing {
-                        self.beep.play();
-                  }
-            } else {
-                  self.beep.stop();
+            // Queue this frame's worth of sound
+            chip.render_audio(&mut self.audio_frame);
+            let mut queue = self.audio_queue.lock().unwrap();
+            queue.extend(self.audio_frame.iter());
+            while queue.len() > MAX_QUEUED_SAMPLES {
+                  queue.pop_front();
             }
       }
       fn draw(&mut self, chip: &chip8::Chip) {
-            use self::sfml::graphics::{RenderTarget, Transformable};
+            use self::sfml::graphics::{Color, RenderTarget, Text, Transformable};
+
+            // Recreate the texture whenever the resolution changes, and
+            // upload only the rows drawn to since the last frame
+            let (width, height) = (chip.display_width(), chip.display_height());
+            let mut dirty = chip.display.dirty_rows();
+            if self.texture_size != (width, height) {
+                  self.texture = sfml::graphics::Texture::new(width as u32, height as u32).unwrap();
+                  self.texture.set_repeated(false);
+                  self.texture_size = (width, height);
+                  dirty = !0;
+            }
 
-            for i in 0..chip8::DISPLAY_SIZE {
-                  let mut color = [22, 34, 56, 255];
-                  if chip.display[i]  {
-                        color = [116, 163, 252, 255];
+            for y in 0..height {
+                  if dirty & (1 << y) == 0 {
+                        continue;
+                  }
+                  let row = &mut self.texture_data[y * width * 4..(y + 1) * width * 4];
+                  for x in 0..width {
+                        let color = PALETTE[chip.pixel(x, y) as usize];
+                        row[x*4..x*4+4].copy_from_slice(&color);
                   }
-                  self.texture_data[i*4] = color[0];
-                  self.texture_data[i*4+1] = color[1];
-                  self.texture_data[i*4+2] = color[2];
-                  self.texture_data[i*4+3] = color[3];
+                  self.texture.update_from_pixels(row, width as u32, 1, 0, y as u32);
             }
-            self.texture.update_from_pixels(&self.texture_data, chip8::DISPLAY_W as u32, chip8::DISPLAY_H as u32, 0, 0);
             let mut sprite = sfml::graphics::Sprite::with_texture(&self.texture);
-            //sprite.set_scale(sfml::system::Vector2f::new(WINDOW_W as f32 / chip8::DISPLAY_W as f32, WINDOW_H as f32 / chip8::DISPLAY_H as f32));
-            sprite.set_scale(Vector2f::new(12.5, 12.5));
+            let scale = SCREEN_W / width as f32;
+            sprite.set_scale(Vector2f::new(scale, scale));
 
-            // debugging
-            let string = String::from("Hello world");
-            let mut text = sfml::graphics::Text::new(&string, &self.font, 12);
-            text.set_fill_color(&sfml::graphics::Color::WHITE);
-            text.set_position(Vector2f::new(805.0, 0.0));
+            // Debugger panel, redrawn from scratch every frame
+            let panel = self.debugger.describe(chip);
+            let mut text = Text::new(&panel, &self.font, PANEL_FONT_SIZE);
+            text.set_fill_color(&Color::WHITE);
+            text.set_position(Vector2f::new(PANEL_X, 0.0));
+            let mut help = Text::new(DEBUGGER_HELP, &self.font, PANEL_FONT_SIZE);
+            help.set_fill_color(&Color::WHITE);
+            help.set_position(Vector2f::new(PANEL_X, WINDOW_H as f32 - 20.0));
 
+            self.window.clear(&Color::BLACK);
             self.window.draw(&text);
+            self.window.draw(&help);
             self.window.draw(&sprite);
             self.window.display();
       }
@@ -0,0 +1,128 @@
+/*
+* Video memory, packed one bit per pixel: each bitplane is a column of
+* u128 rows, wide enough for SUPER-CHIP's 128 pixel lines. Column x of a
+* row is bit 127 - x, so sprites are placed with a shift, drawn with an XOR
+* and tested for collisions with an AND.
+*
+* Every write marks the rows it touches as dirty, so frontends can skip
+* re-uploading rows (or whole frames) that did not change since they last
+* called `clear_dirty`.
+*/
+
+use alloc::vec::Vec;
+
+use chip8::{DISPLAY_SIZE, HIRES_H, NUM_PLANES};
+
+#[derive(Clone)]
+pub struct Framebuffer {
+      planes: [[u128; HIRES_H]; NUM_PLANES],
+      // Bit y is set when row y changed
+      dirty: u64
+}
+
+// The first `width` columns of a row.
+pub fn row_mask(width: usize) -> u128 {
+      !0 << (128 - width)
+}
+
+impl Default for Framebuffer {
+      fn default() -> Framebuffer {
+            Framebuffer::new()
+      }
+}
+
+impl Framebuffer {
+      pub fn new() -> Framebuffer {
+            Framebuffer {
+                  planes: [[0; HIRES_H]; NUM_PLANES],
+                  dirty: !0
+            }
+      }
+
+      pub fn row(&self, plane: usize, y: usize) -> u128 {
+            self.planes[plane][y]
+      }
+      // Colour index of a pixel, combining both bitplanes.
+      pub fn pixel(&self, x: usize, y: usize) -> u8 {
+            (0..NUM_PLANES).fold(0, |acc, p| acc | (((self.planes[p][y] >> (127 - x)) & 0x1) as u8) << p)
+      }
+
+      // XORs `bits` into a row of `plane`, and returns whether any lit pixel was hit.
+      pub fn xor_row(&mut self, plane: usize, y: usize, bits: u128) -> bool {
+            let row = &mut self.planes[plane][y];
+            let collision = *row & bits != 0;
+            *row ^= bits;
+            if bits != 0 {
+                  self.dirty |= 1 << y;
+            }
+            collision
+      }
+
+      // Blanks the planes selected in the `planes` bit mask.
+      pub fn clear(&mut self, planes: u8) {
+            for p in 0..NUM_PLANES {
+                  if planes & (0x1 << p) != 0 {
+                        self.planes[p] = [0; HIRES_H];
+                  }
+            }
+            self.dirty = !0;
+      }
+
+      /**
+      * Moves the selected planes by (dx, dy) pixels within a width x height
+      * screen, filling the gap with blank pixels.
+      */
+      pub fn scroll(&mut self, planes: u8, dx: isize, dy: isize, width: usize, height: usize) {
+            let mask = row_mask(width);
+            for p in 0..NUM_PLANES {
+                  if planes & (0x1 << p) == 0 {
+                        continue;
+                  }
+                  let old = self.planes[p];
+                  for y in 0..height {
+                        let sy = y as isize - dy;
+                        let row = if sy >= 0 && (sy as usize) < height { old[sy as usize] } else { 0 };
+                        let moved = if dx >= 0 { row >> dx } else { row << -dx };
+                        self.planes[p][y] = moved & mask;
+                  }
+            }
+            self.dirty = !0;
+      }
+
+      /**
+      * One byte per pixel, as colour indices in row-major order for a
+      * screen `width` pixels wide; the layout used by save states.
+      */
+      pub fn to_cells(&self, width: usize, height: usize) -> Vec<u8> {
+            let mut cells = vec![0; DISPLAY_SIZE];
+            for y in 0..height {
+                  for x in 0..width {
+                        cells[y * width + x] = self.pixel(x, y);
+                  }
+            }
+            cells
+      }
+      pub fn set_cells(&mut self, cells: &[u8], width: usize, height: usize) {
+            self.planes = [[0; HIRES_H]; NUM_PLANES];
+            for y in 0..height {
+                  for x in 0..width {
+                        for p in 0..NUM_PLANES {
+                              if cells[y * width + x] & (0x1 << p) != 0 {
+                                    self.planes[p][y] |= 1 << (127 - x);
+                              }
+                        }
+                  }
+            }
+            self.dirty = !0;
+      }
+
+      pub fn is_dirty(&self) -> bool {
+            self.dirty != 0
+      }
+      pub fn dirty_rows(&self) -> u64 {
+            self.dirty
+      }
+      pub fn clear_dirty(&mut self) {
+            self.dirty = 0;
+      }
+}
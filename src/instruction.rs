@@ -0,0 +1,355 @@
+/*
+* Instruction decoding, shared by the CPU and anything else that needs to
+* know what an opcode means (disassembler, debugger, tracer).
+* --> http://devernay.free.fr/hacks/chip8/C8TECH10.HTM#3.1 -- mnemonics
+* --> http://johnearnest.github.io/Octo/docs/SuperChip.html
+* --> http://johnearnest.github.io/Octo/docs/XO-ChipSpecification.html
+*
+* Register operands are register numbers (0x0 - 0xF); `Display` prints them
+* as V0 - VF.
+*/
+
+use core::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+      // 0nnn - SYS addr
+      Sys(u16),
+      // 00Cn - SCD nibble
+      ScrollDown(u8),
+      // 00Dn - SCU nibble
+      ScrollUp(u8),
+      // 00E0 - CLS
+      Clear,
+      // 00EE - RET
+      Return,
+      // 00FB - SCR
+      ScrollRight,
+      // 00FC - SCL
+      ScrollLeft,
+      // 00FD - EXIT
+      Exit,
+      // 00FE - LOW
+      Lores,
+      // 00FF - HIGH
+      Hires,
+      // 1nnn - JP addr
+      Jump(u16),
+      // 2nnn - CALL addr
+      Call(u16),
+      // 3xkk - SE Vx, byte
+      SkipEqImm(u8, u8),
+      // 4xkk - SNE Vx, byte
+      SkipNeImm(u8, u8),
+      // 5xy0 - SE Vx, Vy
+      SkipEqReg(u8, u8),
+      // 5xy2 - LD [I], Vx-Vy
+      StoreRange(u8, u8),
+      // 5xy3 - LD Vx-Vy, [I]
+      LoadRange(u8, u8),
+      // 6xkk - LD Vx, byte
+      LoadImm(u8, u8),
+      // 7xkk - ADD Vx, byte
+      AddImm(u8, u8),
+      // 8xy0 - LD Vx, Vy
+      Move(u8, u8),
+      // 8xy1 - OR Vx, Vy
+      Or(u8, u8),
+      // 8xy2 - AND Vx, Vy
+      And(u8, u8),
+      // 8xy3 - XOR Vx, Vy
+      Xor(u8, u8),
+      // 8xy4 - ADD Vx, Vy
+      Add(u8, u8),
+      // 8xy5 - SUB Vx, Vy
+      Sub(u8, u8),
+      // 8xy6 - SHR Vx {, Vy}
+      ShiftRight(u8, u8),
+      // 8xy7 - SUBN Vx, Vy
+      SubN(u8, u8),
+      // 8xyE - SHL Vx {, Vy}
+      ShiftLeft(u8, u8),
+      // 9xy0 - SNE Vx, Vy
+      SkipNeReg(u8, u8),
+      // Annn - LD I, addr
+      LoadI(u16),
+      // Bnnn - JP V0, addr
+      JumpOffset(u16),
+      // Cxkk - RND Vx, byte
+      Random(u8, u8),
+      // Dxyn - DRW Vx, Vy, nibble
+      Draw(u8, u8, u8),
+      // Ex9E - SKP Vx
+      SkipKey(u8),
+      // ExA1 - SKNP Vx
+      SkipNotKey(u8),
+      // F000 nnnn - LD I, long addr; the address is the word that follows
+      LoadILong,
+      // Fn01 - PLANE n
+      Plane(u8),
+      // F002 - AUDIO
+      Audio,
+      // Fx07 - LD Vx, DT
+      LoadDelay(u8),
+      // Fx0A - LD Vx, K
+      WaitKey(u8),
+      // Fx15 - LD DT, Vx
+      SetDelay(u8),
+      // Fx18 - LD ST, Vx
+      SetSound(u8),
+      // Fx1E - ADD I, Vx
+      AddI(u8),
+      // Fx29 - LD F, Vx
+      Font(u8),
+      // Fx30 - LD HF, Vx
+      BigFont(u8),
+      // Fx33 - LD B, Vx
+      Bcd(u8),
+      // Fx3A - PITCH Vx
+      Pitch(u8),
+      // Fx55 - LD [I], Vx
+      StoreRegs(u8),
+      // Fx65 - LD Vx, [I]
+      LoadRegs(u8),
+      // Fx75 - LD R, Vx
+      StoreFlags(u8),
+      // Fx85 - LD Vx, R
+      LoadFlags(u8),
+      // Anything else
+      Undefined(u16)
+}
+
+pub fn decode(opcode: u16) -> Instruction {
+      use self::Instruction::*;
+
+      let x = ((opcode & 0x0F00) >> 8) as u8;
+      let y = ((opcode & 0x00F0) >> 4) as u8;
+      let n = (opcode & 0x000F) as u8;
+      let kk = (opcode & 0x00FF) as u8;
+      let nnn = opcode & 0x0FFF;
+
+      match opcode & 0xF000 {
+            0x0000 => {
+                  match opcode {
+                        0x00E0 => Clear,
+                        0x00EE => Return,
+                        0x00FB => ScrollRight,
+                        0x00FC => ScrollLeft,
+                        0x00FD => Exit,
+                        0x00FE => Lores,
+                        0x00FF => Hires,
+                        _ if opcode & 0xFFF0 == 0x00C0 => ScrollDown(n),
+                        _ if opcode & 0xFFF0 == 0x00D0 => ScrollUp(n),
+                        _ => Sys(nnn)
+                  }
+            },
+            0x1000 => Jump(nnn),
+            0x2000 => Call(nnn),
+            0x3000 => SkipEqImm(x, kk),
+            0x4000 => SkipNeImm(x, kk),
+            0x5000 => {
+                  match n {
+                        0x0 => SkipEqReg(x, y),
+                        0x2 => StoreRange(x, y),
+                        0x3 => LoadRange(x, y),
+                        _ => Undefined(opcode)
+                  }
+            },
+            0x6000 => LoadImm(x, kk),
+            0x7000 => AddImm(x, kk),
+            0x8000 => {
+                  match n {
+                        0x0 => Move(x, y),
+                        0x1 => Or(x, y),
+                        0x2 => And(x, y),
+                        0x3 => Xor(x, y),
+                        0x4 => Add(x, y),
+                        0x5 => Sub(x, y),
+                        0x6 => ShiftRight(x, y),
+                        0x7 => SubN(x, y),
+                        0xE => ShiftLeft(x, y),
+                        _ => Undefined(opcode)
+                  }
+            },
+            0x9000 => {
+                  match n {
+                        0x0 => SkipNeReg(x, y),
+                        _ => Undefined(opcode)
+                  }
+            },
+            0xA000 => LoadI(nnn),
+            0xB000 => JumpOffset(nnn),
+            0xC000 => Random(x, kk),
+            0xD000 => Draw(x, y, n),
+            0xE000 => {
+                  match kk {
+                        0x9E => SkipKey(x),
+                        0xA1 => SkipNotKey(x),
+                        _ => Undefined(opcode)
+                  }
+            },
+            _ => {
+                  match kk {
+                        0x00 if opcode == 0xF000 => LoadILong,
+                        0x01 => Plane(x),
+                        0x02 if opcode == 0xF002 => Audio,
+                        0x07 => LoadDelay(x),
+                        0x0A => WaitKey(x),
+                        0x15 => SetDelay(x),
+                        0x18 => SetSound(x),
+                        0x1E => AddI(x),
+                        0x29 => Font(x),
+                        0x30 => BigFont(x),
+                        0x33 => Bcd(x),
+                        0x3A => Pitch(x),
+                        0x55 => StoreRegs(x),
+                        0x65 => LoadRegs(x),
+                        0x75 => StoreFlags(x),
+                        0x85 => LoadFlags(x),
+                        _ => Undefined(opcode)
+                  }
+            }
+      }
+}
+
+// `len` is a size in memory, never zero, so there is no `is_empty` to go with it
+#[allow(clippy::len_without_is_empty)]
+impl Instruction {
+      // Length in bytes, including the address word that follows F000.
+      pub fn len(&self) -> u16 {
+            match *self {
+                  Instruction::LoadILong => 4,
+                  _ => 2
+            }
+      }
+
+      /**
+      * The opcode this instruction decodes from; `encode(decode(op)) == op`
+      * for every opcode. The reverse only holds for decoded instructions: a
+      * `Sys` operand such as 0x0E0 aliases another opcode (CLS), which is
+      * what it decodes back to.
+      */
+      pub fn encode(&self) -> u16 {
+            use self::Instruction::*;
+
+            let xy = |base: u16, x: u8, y: u8| base | (x as u16 & 0xF) << 8 | (y as u16 & 0xF) << 4;
+            let xkk = |base: u16, x: u8, kk: u8| base | (x as u16 & 0xF) << 8 | kk as u16;
+            let x = |base: u16, x: u8| base | (x as u16 & 0xF) << 8;
+
+            match *self {
+                  Sys(nnn) => nnn & 0x0FFF,
+                  ScrollDown(n) => 0x00C0 | (n as u16 & 0xF),
+                  ScrollUp(n) => 0x00D0 | (n as u16 & 0xF),
+                  Clear => 0x00E0,
+                  Return => 0x00EE,
+                  ScrollRight => 0x00FB,
+                  ScrollLeft => 0x00FC,
+                  Exit => 0x00FD,
+                  Lores => 0x00FE,
+                  Hires => 0x00FF,
+                  Jump(nnn) => 0x1000 | (nnn & 0x0FFF),
+                  Call(nnn) => 0x2000 | (nnn & 0x0FFF),
+                  SkipEqImm(rx, kk) => xkk(0x3000, rx, kk),
+                  SkipNeImm(rx, kk) => xkk(0x4000, rx, kk),
+                  SkipEqReg(rx, ry) => xy(0x5000, rx, ry),
+                  StoreRange(rx, ry) => xy(0x5002, rx, ry),
+                  LoadRange(rx, ry) => xy(0x5003, rx, ry),
+                  LoadImm(rx, kk) => xkk(0x6000, rx, kk),
+                  AddImm(rx, kk) => xkk(0x7000, rx, kk),
+                  Move(rx, ry) => xy(0x8000, rx, ry),
+                  Or(rx, ry) => xy(0x8001, rx, ry),
+                  And(rx, ry) => xy(0x8002, rx, ry),
+                  Xor(rx, ry) => xy(0x8003, rx, ry),
+                  Add(rx, ry) => xy(0x8004, rx, ry),
+                  Sub(rx, ry) => xy(0x8005, rx, ry),
+                  ShiftRight(rx, ry) => xy(0x8006, rx, ry),
+                  SubN(rx, ry) => xy(0x8007, rx, ry),
+                  ShiftLeft(rx, ry) => xy(0x800E, rx, ry),
+                  SkipNeReg(rx, ry) => xy(0x9000, rx, ry),
+                  LoadI(nnn) => 0xA000 | (nnn & 0x0FFF),
+                  JumpOffset(nnn) => 0xB000 | (nnn & 0x0FFF),
+                  Random(rx, kk) => xkk(0xC000, rx, kk),
+                  Draw(rx, ry, n) => xy(0xD000, rx, ry) | (n as u16 & 0xF),
+                  SkipKey(rx) => x(0xE09E, rx),
+                  SkipNotKey(rx) => x(0xE0A1, rx),
+                  LoadILong => 0xF000,
+                  Plane(n) => x(0xF001, n),
+                  Audio => 0xF002,
+                  LoadDelay(rx) => x(0xF007, rx),
+                  WaitKey(rx) => x(0xF00A, rx),
+                  SetDelay(rx) => x(0xF015, rx),
+                  SetSound(rx) => x(0xF018, rx),
+                  AddI(rx) => x(0xF01E, rx),
+                  Font(rx) => x(0xF029, rx),
+                  BigFont(rx) => x(0xF030, rx),
+                  Bcd(rx) => x(0xF033, rx),
+                  Pitch(rx) => x(0xF03A, rx),
+                  StoreRegs(rx) => x(0xF055, rx),
+                  LoadRegs(rx) => x(0xF065, rx),
+                  StoreFlags(rx) => x(0xF075, rx),
+                  LoadFlags(rx) => x(0xF085, rx),
+                  Undefined(opcode) => opcode
+            }
+      }
+}
+
+impl fmt::Display for Instruction {
+      fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            use self::Instruction::*;
+
+            match *self {
+                  Sys(nnn) => write!(f, "SYS {:03X}", nnn),
+                  ScrollDown(n) => write!(f, "SCD {:X}", n),
+                  ScrollUp(n) => write!(f, "SCU {:X}", n),
+                  Clear => write!(f, "CLS"),
+                  Return => write!(f, "RET"),
+                  ScrollRight => write!(f, "SCR"),
+                  ScrollLeft => write!(f, "SCL"),
+                  Exit => write!(f, "EXIT"),
+                  Lores => write!(f, "LOW"),
+                  Hires => write!(f, "HIGH"),
+                  Jump(nnn) => write!(f, "JP {:03X}", nnn),
+                  Call(nnn) => write!(f, "CALL {:03X}", nnn),
+                  SkipEqImm(x, kk) => write!(f, "SE V{:X}, {:02X}", x, kk),
+                  SkipNeImm(x, kk) => write!(f, "SNE V{:X}, {:02X}", x, kk),
+                  SkipEqReg(x, y) => write!(f, "SE V{:X}, V{:X}", x, y),
+                  StoreRange(x, y) => write!(f, "LD [I], V{:X}-V{:X}", x, y),
+                  LoadRange(x, y) => write!(f, "LD V{:X}-V{:X}, [I]", x, y),
+                  LoadImm(x, kk) => write!(f, "LD V{:X}, {:02X}", x, kk),
+                  AddImm(x, kk) => write!(f, "ADD V{:X}, {:02X}", x, kk),
+                  Move(x, y) => write!(f, "LD V{:X}, V{:X}", x, y),
+                  Or(x, y) => write!(f, "OR V{:X}, V{:X}", x, y),
+                  And(x, y) => write!(f, "AND V{:X}, V{:X}", x, y),
+                  Xor(x, y) => write!(f, "XOR V{:X}, V{:X}", x, y),
+                  Add(x, y) => write!(f, "ADD V{:X}, V{:X}", x, y),
+                  Sub(x, y) => write!(f, "SUB V{:X}, V{:X}", x, y),
+                  ShiftRight(x, y) => write!(f, "SHR V{:X}, V{:X}", x, y),
+                  SubN(x, y) => write!(f, "SUBN V{:X}, V{:X}", x, y),
+                  ShiftLeft(x, y) => write!(f, "SHL V{:X}, V{:X}", x, y),
+                  SkipNeReg(x, y) => write!(f, "SNE V{:X}, V{:X}", x, y),
+                  LoadI(nnn) => write!(f, "LD I, {:03X}", nnn),
+                  JumpOffset(nnn) => write!(f, "JP V0, {:03X}", nnn),
+                  Random(x, kk) => write!(f, "RND V{:X}, {:02X}", x, kk),
+                  Draw(x, y, n) => write!(f, "DRW V{:X}, V{:X}, {:X}", x, y, n),
+                  SkipKey(x) => write!(f, "SKP V{:X}", x),
+                  SkipNotKey(x) => write!(f, "SKNP V{:X}", x),
+                  LoadILong => write!(f, "LD I, LONG"),
+                  Plane(n) => write!(f, "PLANE {:X}", n),
+                  Audio => write!(f, "AUDIO"),
+                  LoadDelay(x) => write!(f, "LD V{:X}, DT", x),
+                  WaitKey(x) => write!(f, "LD V{:X}, K", x),
+                  SetDelay(x) => write!(f, "LD DT, V{:X}", x),
+                  SetSound(x) => write!(f, "LD ST, V{:X}", x),
+                  AddI(x) => write!(f, "ADD I, V{:X}", x),
+                  Font(x) => write!(f, "LD F, V{:X}", x),
+                  BigFont(x) => write!(f, "LD HF, V{:X}", x),
+                  Bcd(x) => write!(f, "LD B, V{:X}", x),
+                  Pitch(x) => write!(f, "PITCH V{:X}", x),
+                  StoreRegs(x) => write!(f, "LD [I], V{:X}", x),
+                  LoadRegs(x) => write!(f, "LD V{:X}, [I]", x),
+                  StoreFlags(x) => write!(f, "LD R, V{:X}", x),
+                  LoadFlags(x) => write!(f, "LD V{:X}, R", x),
+                  Undefined(opcode) => write!(f, "DW {:04X}", opcode)
+            }
+      }
+}
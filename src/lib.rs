@@ -0,0 +1,50 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+/*
+* CHIP-8, SUPER-CHIP and XO-CHIP emulation as a library.
+*
+* The machine itself (`Chip`), instruction decoding, save states and the
+* `Display` trait build without any platform dependencies; without the
+* `std` feature they build under `no_std`, for boards that only have an
+* allocator. Loading ROMs from files, the frame scheduler and the
+* recompiler, assemblers and disassembler need `std`. The SFML frontend,
+* and the runtime recompiled programs link against, need the `sfml`
+* feature (on by default).
+*/
+
+#[macro_use]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate core;
+
+#[cfg(feature = "std")]
+pub mod assembler;
+pub mod audio;
+pub mod cache;
+pub mod chip8;
+pub mod debugger;
+pub mod display;
+#[cfg(feature = "std")]
+pub mod disassembler;
+#[cfg(feature = "sfml")]
+pub mod display_sfml;
+pub mod framebuffer;
+pub mod instruction;
+#[cfg(feature = "std")]
+pub mod octo;
+#[cfg(feature = "std")]
+pub mod recompiler;
+pub mod rewind;
+pub mod rng;
+#[cfg(feature = "sfml")]
+pub mod runtime;
+#[cfg(feature = "std")]
+pub mod scheduler;
+pub mod snapshot;
+pub mod timing;
+
+pub use chip8::{Chip, ChipBuilder, ChipError, Event, MachineState, Quirks, Trace};
+pub use debugger::Debugger;
+pub use display::Display;
+pub use instruction::{decode, Instruction};
+pub use snapshot::{Snapshot, SnapshotError};
@@ -1,77 +1,319 @@
-extern crate time;
-extern crate sfml;
-
-mod chip8;
-mod display;
-mod display_sfml;
-
-use display::Display;
-
-const BEEP_SAMPLE_RATE: u32 = 44100;
-const BEEP_FREQUENCY: f32 = 1000.0;
-fn generate_beep() -> Vec<i16> {
-      const SAMPLES: usize = BEEP_SAMPLE_RATE as usize / 2;
-      let mut data: Vec<i16> = Vec::new();
-      data.resize(SAMPLES, 0);
-
-      // generate half a second of a sine wave
-      for i in 0..SAMPLES {
-            let t = i as f32 / BEEP_SAMPLE_RATE as f32;
-            data[i] = ((BEEP_FREQUENCY * t * 2.0 * 3.1415).sin() * 32767.0).round() as i16;
+extern crate chip8;
+#[cfg(feature = "sfml")]
+extern crate rand;
+
+mod bench;
+
+use chip8::{Chip, ChipBuilder, Debugger, MachineState, Quirks};
+use chip8::assembler::Assembler;
+use chip8::debugger::{Breakpoint, BreakpointError};
+use chip8::disassembler::{disassemble, Syntax};
+use chip8::octo;
+
+// Bare ROM names are also looked up in `roms/`.
+fn rom_path(rom: &str) -> String {
+      if !std::path::Path::new(rom).exists() && std::path::Path::new("roms").join(rom).exists() {
+            format!("roms/{}", rom)
+      } else {
+            rom.to_string()
       }
+}
 
-      data
+// Reports why a command failed, and exits with an error status.
+fn fail<T: std::fmt::Display>(message: T) -> ! {
+      println!("{}", message);
+      std::process::exit(1)
 }
 
 fn main() {
-
-      const CPU_FREQUENCY: f32 = 500.0; // Hz
-      const DRAW_FREQUENCY: f32 = 60.0; // Hz
-
       let rom = match std::env::args().nth(1) {
             Some(v) => v,
-            None => panic!("Need ROM to load!")
+            None => fail("Need ROM to load!")
       };
-      println!("Playing ROM `{}`", rom);
-
-      let beep_raw = generate_beep();
-      let beep_buffer = sfml::audio::SoundBuffer::from_samples(
-            beep_raw.as_slice(),
-            1, // channel_count
-            BEEP_SAMPLE_RATE // sample_rate
-      ).unwrap();
-      beep_buffer.save_to_file("res/test.wav");
-      let mut display = display_sfml::DisplaySFML::new(&beep_buffer);
-      display.init();
+      // `bench rom [instructions]` times the decode cache against the interpreter, without a window
+      if rom == "bench" {
+            let rom = match std::env::args().nth(2) {
+                  Some(v) => v,
+                  None => fail("Need ROM to benchmark!")
+            };
+            let instructions = match std::env::args().nth(3) {
+                  Some(v) => match v.parse() {
+                        Ok(n) => n,
+                        Err(_) => fail("Instruction count must be an integer")
+                  },
+                  None => 10_000_000
+            };
+            bench::run(&rom_path(&rom), Quirks::default(), instructions);
+            return;
+      }
+      // `recompile rom out.rs [machine]` writes a native build of the ROM as Rust source, with the
+      // machine's load address and quirks
+      if rom == "recompile" {
+            let (rom, out) = match (std::env::args().nth(2), std::env::args().nth(3)) {
+                  (Some(rom), Some(out)) => (rom_path(&rom), out),
+                  _ => fail("Need a ROM to recompile and an output file!")
+            };
+            let chip = match machine(std::env::args().nth(4)).build() {
+                  Ok(c) => c,
+                  Err(e) => fail(e)
+            };
+            let mut data = Vec::new();
+            if let Err(e) = std::fs::File::open(&rom).and_then(|mut f| std::io::Read::read_to_end(&mut f, &mut data)) {
+                  fail(format!("Cannot read `{}`: {}", rom, e));
+            }
+            let source = chip8::recompiler::recompile(&rom, &data, chip.load_address(), &chip.quirks);
+            if let Err(e) = std::fs::write(&out, source) {
+                  fail(format!("Cannot write `{}`: {}", out, e));
+            }
+            return;
+      }
+      // `disasm rom [chipper|octo] [machine]` prints the ROM as source for either assembler, as loaded
+      // at the machine's load address
+      if rom == "disasm" {
+            let rom = match std::env::args().nth(2) {
+                  Some(v) => rom_path(&v),
+                  None => fail("Need a ROM to disassemble!")
+            };
+            let mut args: Vec<String> = std::env::args().skip(3).collect();
+            // No syntax is named like a machine, so either may come first
+            let syntax = match args.first().and_then(|v| Syntax::from_name(v)) {
+                  Some(s) => {
+                        args.remove(0);
+                        s
+                  },
+                  None => Syntax::Chipper
+            };
+            if args.len() > 1 {
+                  fail(format!("Unknown syntax `{}`, use `chipper` or `octo`!", args[0]));
+            }
+            let chip = match machine(args.pop()).build() {
+                  Ok(c) => c,
+                  Err(e) => fail(e)
+            };
+            match std::fs::read(&rom) {
+                  Ok(data) => print!("{}", disassemble(&rom, &data, chip.load_address(), syntax)),
+                  Err(e) => fail(format!("Cannot read `{}`: {}", rom, e))
+            }
+            return;
+      }
+      // `asm source out.rom [symbol...]` assembles CHIPPER source, with the symbols defined for IFDEF,
+      // or compiles Octo source if the file name ends in `.8o`
+      if rom == "asm" {
+            let (source, out) = match (std::env::args().nth(2), std::env::args().nth(3)) {
+                  (Some(source), Some(out)) => (source, out),
+                  _ => fail("Need a source file to assemble and an output file!")
+            };
+            let program = if source.ends_with(".8o") {
+                  std::fs::read_to_string(&source).map_err(|e| e.into())
+                        .and_then(|text| octo::compile(&text).map_err(|e| e.into()))
+            } else {
+                  let assembler = std::env::args().skip(4).fold(Assembler::new(), |a, symbol| a.define(&symbol));
+                  assembler.assemble_file(&source)
+            };
+            match program {
+                  Ok(program) => if let Err(e) = std::fs::write(&out, program.data) {
+                        fail(format!("Cannot write `{}`: {}", out, e));
+                  },
+                  Err(e) => fail(format!("{}: {}", source, e))
+            }
+            return;
+      }
+      // `debug rom [machine] [breakpoint...]` runs the ROM without a window, and shows the machine at every break
+      if rom == "debug" {
+            let rom = match std::env::args().nth(2) {
+                  Some(v) => rom_path(&v),
+                  None => fail("Need a ROM to debug!")
+            };
+            let mut args: Vec<String> = std::env::args().skip(3).collect();
+            // No breakpoint is written like a machine name
+            let machine = match args.first().and_then(|v| ChipBuilder::from_name(v)) {
+                  Some(m) => {
+                        args.remove(0);
+                        m
+                  },
+                  None => ChipBuilder::new()
+            };
+            debug(&rom, machine, &args);
+            return;
+      }
+      play(&rom, machine(std::env::args().nth(2)));
+}
 
-      let mut chip = chip8::Chip::new();
-      chip.load_rom(&format!("roms/{}", &rom));
+// The machine to emulate, by name: its memory, screen and quirks.
+fn machine(name: Option<String>) -> ChipBuilder {
+      match name {
+            Some(v) => match ChipBuilder::from_name(&v) {
+                  Some(m) => m,
+                  None => fail(format!("Unknown machine `{}`!", v))
+            },
+            None => ChipBuilder::new()
+      }
+}
 
-      let mut begin_cpu = time::PreciseTime::now();
-      let mut begin_display = begin_cpu.clone();
-      'running: loop {
-            let now = time::PreciseTime::now();
+/**
+* A debugger that stops at the given breakpoints, which have to fit the
+* memory of `chip`; see debugger.rs for how they are written.
+*/
+fn debugger<'a, I>(chip: &Chip, specs: I) -> Result<Debugger, BreakpointError>
+      where I: IntoIterator<Item = &'a str>
+{
+      let mut debugger = Debugger::new();
+      for spec in specs {
+            let breakpoint = Breakpoint::parse(spec)?;
+            breakpoint.check(chip)?;
+            debugger.add_breakpoint(breakpoint);
+      }
+      Ok(debugger)
+}
 
-            let delta_cpu = begin_cpu.to(now);
-            if delta_cpu.num_milliseconds() >= (1000.0 / CPU_FREQUENCY).round() as i64 {
-                  begin_cpu = now.clone();
+/**
+* Runs `rom` on `machine`, with no input, for up to a minute of
+* machine time, printing the debugger panel every time one of
+* `breakpoints` stops it and then carrying on.
+*/
+fn debug(rom: &str, machine: ChipBuilder, breakpoints: &[String]) {
+      const FRAMES: u32 = 60 * 60;
+      const INSTRUCTIONS_PER_FRAME: u32 = 8;
+      // Enough to follow a program, without flooding the terminal
+      const MAX_BREAKS: usize = 100;
 
-                  if chip.running {
-                        chip.cycle();
+      let mut chip = match machine.build().and_then(|mut c| c.load_rom(rom).map(|_| c)) {
+            Ok(c) => c,
+            Err(e) => fail(e)
+      };
+      let mut debugger = match debugger(&chip, breakpoints.iter().map(|s| s.as_str())) {
+            Ok(d) => d,
+            Err(e) => fail(e)
+      };
+      let mut scheduler = chip8::scheduler::Scheduler::new(60, INSTRUCTIONS_PER_FRAME);
+      let mut breaks = 0;
+      for _ in 0..FRAMES {
+            scheduler.run_frame_until(&mut chip, |c| debugger.cycle(c));
+            if debugger.paused() {
+                  println!("{}", debugger.describe(&chip));
+                  breaks += 1;
+                  if breaks == MAX_BREAKS {
+                        println!("Stopping after {} breaks", breaks);
+                        return;
                   }
+                  debugger.resume();
             }
+            if !chip.running() {
+                  break;
+            }
+            chip.tick_timers();
+      }
+      println!("Machine {} after {} instructions", chip.state, chip.instruction_count());
+      if let MachineState::Faulted(_) = chip.state {
+            std::process::exit(1);
+      }
+}
+
+#[cfg(not(feature = "sfml"))]
+fn play(_rom: &str, _machine: ChipBuilder) {
+      println!("Built without the `sfml` feature: only `bench`, `recompile`, `asm`, `disasm` and `debug` are available");
+}
+
+#[cfg(feature = "sfml")]
+fn play(rom: &str, machine: ChipBuilder) {
+      use chip8::{display_sfml, timing, Display};
+      use chip8::timing::Timing;
 
-            let delta_display = begin_display.to(now);
-            if delta_display.num_milliseconds() >= (1000.0 / DRAW_FREQUENCY).round() as i64 {
-                  begin_display = now.clone();
+      const FRAME_RATE: u32 = 60; // Hz
+      // Roughly the 500 Hz the emulator has always run at
+      const INSTRUCTIONS_PER_FRAME: u32 = 8;
+      const REWIND_FRAMES: usize = 60 * 30; // 30 seconds
 
-                  display.update(&mut chip);
-                  if display.should_close() {
-                        break 'running;
+      println!("Playing ROM `{}`", rom);
+
+      let mut audio_stream = display_sfml::AudioStream::new();
+      let mut display = display_sfml::DisplaySFML::new(&mut audio_stream);
+      display.init();
+
+      // A fresh seed per session; pass one in to replay a session exactly
+      let seed = match std::env::var("CHIP8_SEED") {
+            Ok(v) => match v.parse() {
+                  Ok(seed) => seed,
+                  Err(_) => fail("CHIP8_SEED must be an integer")
+            },
+            Err(_) => rand::random()
+      };
+      println!("Random seed: {}", seed);
+      let mut chip = match machine.seed(seed).build() {
+            Ok(c) => c,
+            Err(e) => fail(e)
+      };
+      // VIP timing budgets machine cycles per frame instead of instructions
+      chip.timing = match std::env::var("CHIP8_TIMING") {
+            Ok(v) => match Timing::from_name(&v) {
+                  Some(t) => t,
+                  None => fail(format!("Unknown timing mode `{}`!", v))
+            },
+            Err(_) => Timing::Instructions
+      };
+      // Per frame: instructions, or with VIP timing, the VIP's machine cycles
+      let cycles_per_frame = match chip.timing {
+            Timing::Instructions => match std::env::var("CHIP8_IPF") {
+                  Ok(v) => match v.parse() {
+                        Ok(ipf) => ipf,
+                        Err(_) => fail("CHIP8_IPF must be an integer")
+                  },
+                  Err(_) => INSTRUCTIONS_PER_FRAME
+            },
+            Timing::Vip => timing::VIP_CYCLES_PER_FRAME
+      };
+      // `-` reads the ROM from stdin
+      let loaded = if rom == "-" {
+            let stdin = std::io::stdin();
+            let handle = stdin.lock();
+            chip.load_rom_reader("stdin", handle)
+      } else {
+            chip.load_rom(&rom_path(rom))
+      };
+      if let Err(e) = loaded {
+            fail(e);
+      }
+
+      // Breakpoints, separated by `;`
+      if let Ok(v) = std::env::var("CHIP8_BREAK") {
+            match debugger(&chip, v.split(';').filter(|s| !s.trim().is_empty())) {
+                  Ok(debugger) => *display.debugger() = debugger,
+                  Err(e) => fail(e)
+            }
+      }
+
+      let mut rewind = chip8::rewind::Rewind::new(REWIND_FRAMES);
+      let mut scheduler = chip8::scheduler::Scheduler::new(FRAME_RATE, cycles_per_frame);
+      loop {
+            // Either step one frame back in time, or run and record this one.
+            // Once the CPU stops, keep the window open so the final state
+            // stays on screen.
+            if display.rewind_held() {
+                  if let Some(state) = rewind.pop() {
+                        chip.restore(&state).expect("rewind state does not fit the machine");
+                  }
+            } else {
+                  // While paused, only the instructions stepped through run
+                  let debugger = display.debugger();
+                  if scheduler.run_frame_until(&mut chip, |c| debugger.cycle(c)) {
+                        println!("Machine {}", chip.state);
+                        chip.dump();
                   }
-                  display.draw(&chip);
+                  if debugger.frame_elapses(&chip) {
+                        chip.tick_timers();
+                        rewind.push(&chip.snapshot());
+                  }
+            }
+
+            display.update(&mut chip);
+            if display.should_close() {
+                  break;
             }
+            display.draw(&chip);
+            chip.display.clear_dirty();
+            scheduler.wait();
       }
 
+      println!("{}", scheduler.stats());
       chip.dump();
 }
\ No newline at end of file
@@ -0,0 +1,898 @@
+/*
+* Compiler for Octo, the assembly language most new CHIP-8, SUPER-CHIP
+* and XO-CHIP programs are written in.
+*
+* Source is a stream of whitespace separated tokens; `#` starts a comment.
+* Besides the instructions, the compiler understands:
+*
+* - `: name` labels (`:next name` labels the operand byte of the next
+*   instruction), `:const`, `:alias` for registers, `:org`, `:byte`,
+*   `:pointer`, `:call`, `:unpack`, and `:assert`;
+* - `:macro name args { body }`, expanded wherever `name` appears, with
+*   `CALLS` counting the expansions;
+* - `:calc name { expression }`, evaluated like Octo does: right to left,
+*   with no precedence between binary operators;
+* - `if ... then`, `if ... begin ... else ... end`, and `loop ... again`
+*   with any number of `while` exits, including the comparisons `<`, `>`,
+*   `<=` and `>=`, which are built with `vf`;
+* - XO-CHIP's `i := long`, which reaches all of its 64 KiB.
+*
+* A bare number is a byte, and a bare label a call. Unless the program
+* starts with `: main`, it begins with a jump to `main`, as Octo's do.
+* `:breakpoint`, `:monitor` and `:proto` are accepted and ignored.
+*/
+
+use std::collections::BTreeMap;
+use std::f64::consts::{E, PI};
+
+use assembler::{AsmError, Program};
+use chip8::{MEMORY_SIZE, PROGRAM_BASE};
+
+// Nested macro expansions give up after this many, rather than never end
+const MAX_EXPANSIONS: usize = 100_000;
+
+#[derive(Clone, Debug)]
+struct Token {
+      text: String,
+      line: usize
+}
+
+// Splits the source into tokens; quoted strings are single tokens.
+fn tokenize(source: &str) -> ::std::result::Result<Vec<Token>, AsmError> {
+      let mut tokens = Vec::new();
+      for (number, line) in source.lines().enumerate() {
+            let mut chars = line.char_indices().peekable();
+            while let Some(&(start, c)) = chars.peek() {
+                  if c.is_whitespace() {
+                        chars.next();
+                  } else if c == '#' {
+                        break;
+                  } else if c == '"' {
+                        chars.next();
+                        let end = match chars.find(|&(_, c)| c == '"') {
+                              Some((end, _)) => end,
+                              None => return Err(AsmError { file: None, line: number + 1, message: String::from("unterminated string") })
+                        };
+                        tokens.push(Token { text: String::from(&line[start..end + 1]), line: number + 1 });
+                  } else {
+                        let mut end = line.len();
+                        while let Some(&(i, c)) = chars.peek() {
+                              if c.is_whitespace() {
+                                    end = i;
+                                    break;
+                              }
+                              chars.next();
+                        }
+                        tokens.push(Token { text: String::from(&line[start..end]), line: number + 1 });
+                  }
+            }
+      }
+      Ok(tokens)
+}
+
+fn number(text: &str) -> Option<f64> {
+      let (negative, digits) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text)
+      };
+      let value = if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+            i64::from_str_radix(hex, 16).ok()? as f64
+      } else if let Some(bin) = digits.strip_prefix("0b").or_else(|| digits.strip_prefix("0B")) {
+            i64::from_str_radix(bin, 2).ok()? as f64
+      } else if digits.starts_with(|c: char| c.is_ascii_digit()) {
+            digits.parse::<f64>().ok()?
+      } else {
+            return None;
+      };
+      Some(if negative { -value } else { value })
+}
+
+fn register(text: &str) -> Option<u8> {
+      let mut chars = text.chars();
+      match (chars.next(), chars.next(), chars.next()) {
+            (Some('v'), Some(digit), None) | (Some('V'), Some(digit), None) => digit.to_digit(16).map(|x| x as u8),
+            _ => None
+      }
+}
+
+// How a label is written into the program once it is known.
+#[derive(Clone, Copy, Debug)]
+enum Patch {
+      // The low 12 bits of an opcode
+      Address,
+      // A big-endian 16 bit word
+      Long,
+      // The high nibble given, and the top 4 bits of a 12-bit address
+      UnpackHigh(u8),
+      // The top 8 bits of a 16-bit address
+      UnpackLongHigh,
+      // The low 8 bits
+      UnpackLow
+}
+
+struct Fixup {
+      addr: u32,
+      name: String,
+      patch: Patch,
+      line: usize
+}
+
+struct Macro {
+      args: Vec<String>,
+      body: Vec<Token>,
+      calls: usize
+}
+
+// Open `if ... begin` and `loop` blocks, with the jumps they still have to patch.
+enum Flow {
+      If(u32),
+      Else(u32),
+      Loop(u32, Vec<u32>)
+}
+
+// The right-hand side of a condition.
+enum Operand {
+      Register(u8),
+      Byte(u8)
+}
+
+enum Condition {
+      Eq(u8, Operand),
+      Ne(u8, Operand),
+      Key(u8),
+      NotKey(u8),
+      // `vf` is 1 when the condition holds, 0 otherwise
+      Flag(u8)
+}
+
+struct Compiler {
+      // Unread tokens, last one first
+      tokens: Vec<Token>,
+      line: usize,
+      memory: Vec<u8>,
+      here: u32,
+      // The extent of the program written so far
+      start: u32,
+      end: u32,
+      labels: BTreeMap<String, u32>,
+      constants: BTreeMap<String, f64>,
+      aliases: BTreeMap<String, u8>,
+      macros: BTreeMap<String, Macro>,
+      fixups: Vec<Fixup>,
+      // Whether anything was emitted yet
+      started: bool,
+      flow: Vec<(Flow, usize)>,
+      expansions: usize
+}
+
+type Result<T> = ::std::result::Result<T, String>;
+
+impl Compiler {
+      fn error(&self, message: String) -> AsmError {
+            AsmError { file: None, line: self.line, message }
+      }
+
+      fn next(&mut self) -> Result<String> {
+            match self.tokens.pop() {
+                  Some(token) => {
+                        self.line = token.line;
+                        Ok(token.text)
+                  },
+                  None => Err(String::from("unexpected end of the source"))
+            }
+      }
+      fn peek(&self) -> Option<&str> {
+            self.tokens.last().map(|t| t.text.as_str())
+      }
+      fn expect(&mut self, expected: &str) -> Result<()> {
+            let token = self.next()?;
+            if token != expected {
+                  return Err(format!("expected `{}`, found `{}`", expected, token));
+            }
+            Ok(())
+      }
+
+      fn emit(&mut self, bytes: &[u8]) -> Result<()> {
+            if !self.started {
+                  self.started = true;
+                  self.start = self.here;
+                  self.end = self.here;
+                  // Programs jump to `main` unless they start with it
+                  if !self.labels.contains_key("main") {
+                        self.fixups.push(Fixup { addr: self.here, name: String::from("main"), patch: Patch::Address, line: self.line });
+                        self.emit(&[0x10, 0x00])?;
+                  }
+            }
+            if self.here + bytes.len() as u32 > MEMORY_SIZE as u32 {
+                  return Err(String::from("the program does not fit in memory"));
+            }
+            if self.here < self.start {
+                  return Err(format!("0x{:X} lies before the start of the program", self.here));
+            }
+            for &b in bytes {
+                  self.memory[self.here as usize] = b;
+                  self.here += 1;
+            }
+            self.end = self.end.max(self.here);
+            Ok(())
+      }
+      fn op(&mut self, opcode: u16) -> Result<()> {
+            self.emit(&[(opcode >> 8) as u8, opcode as u8])
+      }
+      // An opcode whose low 12 bits are the address of `name`, now or later.
+      fn jump_to(&mut self, opcode: u16, name: String) -> Result<()> {
+            self.fixup(name, Patch::Address)?;
+            self.op(opcode)
+      }
+      fn fixup(&mut self, name: String, patch: Patch) -> Result<()> {
+            // The jump to main, if any, goes first
+            self.emit(&[])?;
+            self.fixups.push(Fixup { addr: self.here, name, patch, line: self.line });
+            Ok(())
+      }
+      fn patch(&mut self, addr: u32, value: u32, patch: Patch) -> Result<()> {
+            let addr = addr as usize;
+            match patch {
+                  Patch::Address => {
+                        if value > 0xFFF {
+                              return Err(format!("0x{:X} is out of reach of a 12-bit address, use `i := long`", value));
+                        }
+                        self.memory[addr] = self.memory[addr] & 0xF0 | (value >> 8) as u8;
+                        self.memory[addr + 1] = value as u8;
+                  },
+                  Patch::Long => {
+                        self.memory[addr] = (value >> 8) as u8;
+                        self.memory[addr + 1] = value as u8;
+                  },
+                  Patch::UnpackHigh(nibble) => {
+                        if value > 0xFFF {
+                              return Err(format!("0x{:X} does not fit in 12 bits, use `:unpack long`", value));
+                        }
+                        self.memory[addr + 1] = nibble << 4 | (value >> 8) as u8;
+                  },
+                  Patch::UnpackLongHigh => self.memory[addr + 1] = (value >> 8) as u8,
+                  Patch::UnpackLow => self.memory[addr + 1] = value as u8
+            }
+            Ok(())
+      }
+
+      // Labels `offset` bytes into whatever comes next.
+      fn define_label(&mut self, name: String, offset: u32) -> Result<()> {
+            if name != "main" {
+                  self.emit(&[])?;
+            }
+            let addr = self.here + offset;
+            if self.labels.contains_key(&name) || self.constants.contains_key(&name) {
+                  return Err(format!("`{}` is already defined", name));
+            }
+            if register(&name).is_some() || self.aliases.contains_key(&name) {
+                  return Err(format!("`{}` is a register", name));
+            }
+            self.labels.insert(name, addr);
+            Ok(())
+      }
+
+      fn register(&mut self) -> Result<u8> {
+            let token = self.next()?;
+            self.as_register(&token).ok_or_else(|| format!("expected a register, found `{}`", token))
+      }
+      fn as_register(&self, token: &str) -> Option<u8> {
+            register(token).or_else(|| self.aliases.get(token).cloned())
+      }
+
+      // A number, constant, label or `{ calc }` that has to be known now.
+      fn value(&mut self) -> Result<f64> {
+            let token = self.next()?;
+            if token == "{" {
+                  return self.calc_block();
+            }
+            self.known(&token)
+      }
+      fn known(&self, token: &str) -> Result<f64> {
+            if let Some(value) = number(token).or_else(|| self.constants.get(token).cloned()) {
+                  return Ok(value);
+            }
+            match self.labels.get(token) {
+                  Some(&addr) => Ok(addr as f64),
+                  None => Err(format!("`{}` is not defined", token))
+            }
+      }
+      fn integer(&mut self, min: i64, max: i64, what: &str) -> Result<i64> {
+            let value = self.value()?;
+            let value = value.floor() as i64;
+            if value < min || value > max {
+                  return Err(format!("{} does not fit in {}", value, what));
+            }
+            Ok(value)
+      }
+      fn byte(&mut self) -> Result<u8> {
+            Ok(self.integer(-0x80, 0xFF, "a byte")? as u8)
+      }
+      fn nibble(&mut self) -> Result<u8> {
+            Ok(self.integer(0, 0xF, "a nibble")? as u8)
+      }
+
+      // An address operand: a label may be defined later, anything else has to be known.
+      fn address(&mut self, opcode: u16) -> Result<()> {
+            let token = self.next()?;
+            let known = if token == "{" { Some(self.calc_block()?) } else { self.known(&token).ok() };
+            match known {
+                  Some(value) => {
+                        let value = value.floor() as i64;
+                        if !(0..=0xFFF).contains(&value) {
+                              return Err(format!("0x{:X} is out of reach of a 12-bit address", value));
+                        }
+                        self.op(opcode | value as u16)
+                  },
+                  None => self.jump_to(opcode, token)
+            }
+      }
+      // A 16-bit word that may be a label defined later.
+      fn long(&mut self) -> Result<()> {
+            let token = self.next()?;
+            let known = if token == "{" { Some(self.calc_block()?) } else { self.known(&token).ok() };
+            match known {
+                  Some(value) => {
+                        let value = value.floor() as i64;
+                        if !(-0x8000..=0xFFFF).contains(&value) {
+                              return Err(format!("{} does not fit in 16 bits", value));
+                        }
+                        self.op(value as u16)
+                  },
+                  None => {
+                        self.fixup(token, Patch::Long)?;
+                        self.op(0)
+                  }
+            }
+      }
+
+      // The tokens up to the matching `}`, the `{` having been read.
+      fn block(&mut self) -> Result<Vec<Token>> {
+            let mut depth = 1;
+            let mut body = Vec::new();
+            loop {
+                  let line = self.tokens.last().map_or(self.line, |t| t.line);
+                  let token = self.next().map_err(|_| String::from("missing `}`"))?;
+                  match token.as_str() {
+                        "{" => depth += 1,
+                        "}" => {
+                              depth -= 1;
+                              if depth == 0 {
+                                    return Ok(body);
+                              }
+                        },
+                        _ => ()
+                  }
+                  body.push(Token { text: token, line });
+            }
+      }
+      fn calc_block(&mut self) -> Result<f64> {
+            let tokens = self.block()?;
+            let mut calc = Calc { tokens: &tokens, pos: 0, compiler: self };
+            let value = calc.expression()?;
+            if calc.pos != tokens.len() {
+                  return Err(format!("unexpected `{}` in expression", tokens[calc.pos].text));
+            }
+            Ok(value)
+      }
+
+      fn condition(&mut self) -> Result<Condition> {
+            let x = self.register()?;
+            let op = self.next()?;
+            match op.as_str() {
+                  "key" => return Ok(Condition::Key(x)),
+                  "-key" => return Ok(Condition::NotKey(x)),
+                  _ => ()
+            }
+            let rhs = self.next()?;
+            let rhs = match self.as_register(&rhs) {
+                  Some(y) => Operand::Register(y),
+                  None => {
+                        self.tokens.push(Token { text: rhs, line: self.line });
+                        Operand::Byte(self.byte()?)
+                  }
+            };
+            // vf := a - b, and vf is 1 exactly when a >= b
+            let compare = |c: &mut Compiler, a: Operand, b: Operand| -> Result<()> {
+                  match (a, b) {
+                        (Operand::Register(a), Operand::Register(b)) => {
+                              c.op(0x8F00 | (a as u16) << 4)?;
+                              c.op(0x8F05 | (b as u16) << 4)
+                        },
+                        (Operand::Register(a), Operand::Byte(b)) => {
+                              c.op(0x6F00 | b as u16)?;
+                              c.op(0x8F07 | (a as u16) << 4)
+                        },
+                        (Operand::Byte(a), Operand::Register(b)) => {
+                              c.op(0x6F00 | a as u16)?;
+                              c.op(0x8F05 | (b as u16) << 4)
+                        },
+                        (Operand::Byte(_), Operand::Byte(_)) => unreachable!()
+                  }
+            };
+            Ok(match op.as_str() {
+                  "==" => Condition::Eq(x, rhs),
+                  "!=" => Condition::Ne(x, rhs),
+                  ">=" => { compare(self, Operand::Register(x), rhs)?; Condition::Flag(1) },
+                  "<" => { compare(self, Operand::Register(x), rhs)?; Condition::Flag(0) },
+                  "<=" => { compare(self, rhs, Operand::Register(x))?; Condition::Flag(1) },
+                  ">" => { compare(self, rhs, Operand::Register(x))?; Condition::Flag(0) },
+                  _ => return Err(format!("unknown comparison `{}`", op))
+            })
+      }
+      // Skips the next instruction when `condition` is `when`.
+      fn skip(&mut self, condition: Condition, when: bool) -> Result<()> {
+            let (x, skip_eq, operand) = match condition {
+                  Condition::Eq(x, operand) => (x, when, operand),
+                  Condition::Ne(x, operand) => (x, !when, operand),
+                  Condition::Flag(k) => (0xF, when, Operand::Byte(k)),
+                  Condition::Key(x) => return self.op(if when { 0xE09E } else { 0xE0A1 } | (x as u16) << 8),
+                  Condition::NotKey(x) => return self.op(if when { 0xE0A1 } else { 0xE09E } | (x as u16) << 8)
+            };
+            let x = (x as u16) << 8;
+            self.op(match (operand, skip_eq) {
+                  (Operand::Byte(kk), true) => 0x3000 | x | kk as u16,
+                  (Operand::Byte(kk), false) => 0x4000 | x | kk as u16,
+                  (Operand::Register(y), true) => 0x5000 | x | (y as u16) << 4,
+                  (Operand::Register(y), false) => 0x9000 | x | (y as u16) << 4
+            })
+      }
+
+      fn statement(&mut self) -> Result<()> {
+            let token = self.next()?;
+            if let Some(x) = self.as_register(&token) {
+                  return self.assignment(x);
+            }
+            if let Some(value) = number(&token) {
+                  let value = value.floor() as i64;
+                  if !(-0x80..=0xFF).contains(&value) {
+                        return Err(format!("{} does not fit in a byte", value));
+                  }
+                  return self.emit(&[value as u8]);
+            }
+            match token.as_str() {
+                  ":" => {
+                        let name = self.next()?;
+                        self.define_label(name, 0)?;
+                  },
+                  ":next" => {
+                        let name = self.next()?;
+                        self.define_label(name, 1)?;
+                  },
+                  ":const" => {
+                        let name = self.next()?;
+                        let value = self.value()?;
+                        self.constant(name, value)?;
+                  },
+                  ":calc" => {
+                        let name = self.next()?;
+                        self.expect("{")?;
+                        let value = self.calc_block()?;
+                        self.constants.remove(&name);
+                        self.constant(name, value)?;
+                  },
+                  ":alias" => {
+                        let name = self.next()?;
+                        let x = self.register()?;
+                        if self.labels.contains_key(&name) || self.constants.contains_key(&name) {
+                              return Err(format!("`{}` is already defined", name));
+                        }
+                        self.aliases.insert(name, x);
+                  },
+                  ":macro" => {
+                        let name = self.next()?;
+                        let mut args = Vec::new();
+                        loop {
+                              let arg = self.next()?;
+                              if arg == "{" {
+                                    break;
+                              }
+                              args.push(arg);
+                        }
+                        let body = self.block()?;
+                        self.macros.insert(name, Macro { args, body, calls: 0 });
+                  },
+                  ":org" => {
+                        let addr = self.integer(0, MEMORY_SIZE as i64 - 1, "memory")?;
+                        self.here = addr as u32;
+                  },
+                  ":byte" => {
+                        let b = self.byte()?;
+                        self.emit(&[b])?;
+                  },
+                  ":pointer" => self.long()?,
+                  ":call" => self.address(0x2000)?,
+                  ":unpack" => {
+                        let (high, low) = if self.peek() == Some("long") {
+                              self.next()?;
+                              (Patch::UnpackLongHigh, Patch::UnpackLow)
+                        } else {
+                              (Patch::UnpackHigh(self.nibble()?), Patch::UnpackLow)
+                        };
+                        let name = self.next()?;
+                        // v0 gets the high byte, v1 the low one
+                        self.fixup(name.clone(), high)?;
+                        self.op(0x6000)?;
+                        self.fixup(name, low)?;
+                        self.op(0x6100)?;
+                  },
+                  ":assert" => {
+                        let message = match self.peek() {
+                              Some(m) if m.starts_with('"') => self.next()?,
+                              _ => String::from("assertion failed")
+                        };
+                        if self.value()? == 0.0 {
+                              return Err(String::from(message.trim_matches('"')));
+                        }
+                  },
+                  ":breakpoint" | ":proto" => { self.next()?; },
+                  ":monitor" => {
+                        self.next()?;
+                        self.next()?;
+                  },
+                  "return" | ";" => self.op(0x00EE)?,
+                  "clear" => self.op(0x00E0)?,
+                  "scroll-right" => self.op(0x00FB)?,
+                  "scroll-left" => self.op(0x00FC)?,
+                  "exit" => self.op(0x00FD)?,
+                  "lores" => self.op(0x00FE)?,
+                  "hires" => self.op(0x00FF)?,
+                  "scroll-down" => {
+                        let n = self.nibble()?;
+                        self.op(0x00C0 | n as u16)?;
+                  },
+                  "scroll-up" => {
+                        let n = self.nibble()?;
+                        self.op(0x00D0 | n as u16)?;
+                  },
+                  "audio" => self.op(0xF002)?,
+                  "plane" => {
+                        let n = self.nibble()?;
+                        self.op(0xF001 | (n as u16) << 8)?;
+                  },
+                  "jump" => self.address(0x1000)?,
+                  "jump0" => self.address(0xB000)?,
+                  "native" => self.address(0x0000)?,
+                  "sprite" => {
+                        let x = self.register()? as u16;
+                        let y = self.register()? as u16;
+                        let n = self.nibble()? as u16;
+                        self.op(0xD000 | x << 8 | y << 4 | n)?;
+                  },
+                  "bcd" => self.fx(0x33)?,
+                  "saveflags" => self.fx(0x75)?,
+                  "loadflags" => self.fx(0x85)?,
+                  "save" | "load" => {
+                        let x = self.register()? as u16;
+                        if self.peek() == Some("-") {
+                              self.next()?;
+                              let y = self.register()? as u16;
+                              self.op(if token == "save" { 0x5002 } else { 0x5003 } | x << 8 | y << 4)?;
+                        } else {
+                              self.op(if token == "save" { 0xF055 } else { 0xF065 } | x << 8)?;
+                        }
+                  },
+                  "delay" | "buzzer" | "pitch" => {
+                        self.expect(":=")?;
+                        let low = match token.as_str() {
+                              "delay" => 0x15,
+                              "buzzer" => 0x18,
+                              _ => 0x3A
+                        };
+                        self.fx(low)?;
+                  },
+                  "i" => self.index()?,
+                  "if" => {
+                        let condition = self.condition()?;
+                        match self.next()?.as_str() {
+                              "then" => self.skip(condition, false)?,
+                              "begin" => {
+                                    self.skip(condition, true)?;
+                                    let here = self.here;
+                                    self.op(0x1000)?;
+                                    let line = self.line;
+                                    self.flow.push((Flow::If(here), line));
+                              },
+                              other => return Err(format!("expected `then` or `begin`, found `{}`", other))
+                        }
+                  },
+                  "else" => {
+                        let jump = match self.flow.pop() {
+                              Some((Flow::If(jump), _)) => jump,
+                              _ => return Err(String::from("`else` without `if ... begin`"))
+                        };
+                        let here = self.here;
+                        self.op(0x1000)?;
+                        let target = self.here;
+                        self.patch(jump, target, Patch::Address)?;
+                        let line = self.line;
+                        self.flow.push((Flow::Else(here), line));
+                  },
+                  "end" => {
+                        let jump = match self.flow.pop() {
+                              Some((Flow::If(jump), _)) | Some((Flow::Else(jump), _)) => jump,
+                              _ => return Err(String::from("`end` without `if ... begin`"))
+                        };
+                        let target = self.here;
+                        self.patch(jump, target, Patch::Address)?;
+                  },
+                  "loop" => {
+                        // A loop at the very start still goes after the jump to main
+                        self.emit(&[])?;
+                        let (here, line) = (self.here, self.line);
+                        self.flow.push((Flow::Loop(here, Vec::new()), line));
+                  },
+                  "while" => {
+                        let condition = self.condition()?;
+                        self.skip(condition, true)?;
+                        let here = self.here;
+                        match self.flow.iter_mut().rev().find(|f| matches!(f.0, Flow::Loop(..))) {
+                              Some(&mut (Flow::Loop(_, ref mut exits), _)) => exits.push(here),
+                              _ => return Err(String::from("`while` outside of a loop"))
+                        }
+                        self.op(0x1000)?;
+                  },
+                  "again" => {
+                        let (start, exits) = match self.flow.pop() {
+                              Some((Flow::Loop(start, exits), _)) => (start, exits),
+                              _ => return Err(String::from("`again` without `loop`"))
+                        };
+                        if start > 0xFFF {
+                              return Err(String::from("loops have to start below 0x1000"));
+                        }
+                        self.op(0x1000 | start as u16)?;
+                        let target = self.here;
+                        for exit in exits {
+                              self.patch(exit, target, Patch::Address)?;
+                        }
+                  },
+                  _ if self.macros.contains_key(&token) => self.expand(&token)?,
+                  _ if self.constants.contains_key(&token) => {
+                        let b = self.known(&token)?.floor() as i64;
+                        if !(-0x80..=0xFF).contains(&b) {
+                              return Err(format!("{} does not fit in a byte", b));
+                        }
+                        self.emit(&[b as u8])?;
+                  },
+                  _ if token.starts_with(':') || token.starts_with('"') || token == "{" || token == "}" =>
+                        return Err(format!("unexpected `{}`", token)),
+                  // Anything else names a subroutine, possibly defined later
+                  _ => {
+                        self.tokens.push(Token { text: token, line: self.line });
+                        self.address(0x2000)?;
+                  }
+            }
+            Ok(())
+      }
+
+      fn fx(&mut self, low: u16) -> Result<()> {
+            let x = self.register()? as u16;
+            self.op(0xF000 | x << 8 | low)
+      }
+
+      fn constant(&mut self, name: String, value: f64) -> Result<()> {
+            if self.labels.contains_key(&name) || self.constants.contains_key(&name) {
+                  return Err(format!("`{}` is already defined", name));
+            }
+            self.constants.insert(name, value);
+            Ok(())
+      }
+
+      // `i := ...` and `i += vx`.
+      fn index(&mut self) -> Result<()> {
+            match self.next()?.as_str() {
+                  "+=" => self.fx(0x1E),
+                  ":=" => match self.peek() {
+                        Some("hex") => { self.next()?; self.fx(0x29) },
+                        Some("bighex") => { self.next()?; self.fx(0x30) },
+                        Some("long") => {
+                              self.next()?;
+                              self.op(0xF000)?;
+                              self.long()
+                        },
+                        _ => self.address(0xA000)
+                  },
+                  other => Err(format!("unknown operation `i {}`", other))
+            }
+      }
+
+      // `vx := ...`, `vx += ...` and the other register operations.
+      fn assignment(&mut self, x: u8) -> Result<()> {
+            let x16 = (x as u16) << 8;
+            let op = self.next()?;
+            let rhs = self.next()?;
+            if let Some(y) = self.as_register(&rhs) {
+                  let low = match op.as_str() {
+                        ":=" => 0x0,
+                        "|=" => 0x1,
+                        "&=" => 0x2,
+                        "^=" => 0x3,
+                        "+=" => 0x4,
+                        "-=" => 0x5,
+                        ">>=" => 0x6,
+                        "=-" => 0x7,
+                        "<<=" => 0xE,
+                        _ => return Err(format!("unknown operation `{}`", op))
+                  };
+                  return self.op(0x8000 | x16 | (y as u16) << 4 | low);
+            }
+            match (op.as_str(), rhs.as_str()) {
+                  (":=", "key") => self.op(0xF00A | x16),
+                  (":=", "delay") => self.op(0xF007 | x16),
+                  (":=", "random") => {
+                        let kk = self.byte()?;
+                        self.op(0xC000 | x16 | kk as u16)
+                  },
+                  (":=", _) | ("+=", _) | ("-=", _) => {
+                        self.tokens.push(Token { text: rhs, line: self.line });
+                        let kk = self.byte()?;
+                        match op.as_str() {
+                              ":=" => self.op(0x6000 | x16 | kk as u16),
+                              "+=" => self.op(0x7000 | x16 | kk as u16),
+                              _ => self.op(0x7000 | x16 | kk.wrapping_neg() as u16)
+                        }
+                  },
+                  _ => Err(format!("`{}` needs a register, found `{}`", op, rhs))
+            }
+      }
+
+      fn expand(&mut self, name: &str) -> Result<()> {
+            self.expansions += 1;
+            if self.expansions > MAX_EXPANSIONS {
+                  return Err(format!("too many expansions of `{}`, is it recursive?", name));
+            }
+            let count = self.macros[name].args.len();
+            let mut values = Vec::new();
+            for _ in 0..count {
+                  values.push(self.next()?);
+            }
+            let m = self.macros.get_mut(name).unwrap();
+            let calls = m.calls;
+            m.calls += 1;
+            let body: Vec<Token> = m.body.iter().map(|token| {
+                  let text = match m.args.iter().position(|arg| *arg == token.text) {
+                        Some(i) => values[i].clone(),
+                        None if token.text == "CALLS" => calls.to_string(),
+                        None => token.text.clone()
+                  };
+                  Token { text, line: token.line }
+            }).collect();
+            self.tokens.extend(body.into_iter().rev());
+            Ok(())
+      }
+}
+
+// `:calc` expressions: right to left, every binary operator alike.
+struct Calc<'a> {
+      tokens: &'a [Token],
+      pos: usize,
+      compiler: &'a Compiler
+}
+
+impl<'a> Calc<'a> {
+      fn next(&mut self) -> Result<&'a str> {
+            let token = self.tokens.get(self.pos).ok_or_else(|| String::from("incomplete expression"))?;
+            self.pos += 1;
+            Ok(&token.text)
+      }
+
+      fn expression(&mut self) -> Result<f64> {
+            let lhs = self.term()?;
+            let op = match self.tokens.get(self.pos) {
+                  Some(token) if token.text != ")" => token.text.as_str(),
+                  _ => return Ok(lhs)
+            };
+            self.pos += 1;
+            let rhs = self.expression()?;
+            let (a, b) = (lhs as i64, rhs as i64);
+            Ok(match op {
+                  "+" => lhs + rhs,
+                  "-" => lhs - rhs,
+                  "*" => lhs * rhs,
+                  "/" => lhs / rhs,
+                  "%" => lhs % rhs,
+                  "pow" => lhs.powf(rhs),
+                  "min" => lhs.min(rhs),
+                  "max" => lhs.max(rhs),
+                  "&" => (a & b) as f64,
+                  "|" => (a | b) as f64,
+                  "^" => (a ^ b) as f64,
+                  "<<" => a.checked_shl(b as u32).unwrap_or(0) as f64,
+                  ">>" => a.checked_shr(b as u32).unwrap_or(0) as f64,
+                  "<" => (lhs < rhs) as i64 as f64,
+                  ">" => (lhs > rhs) as i64 as f64,
+                  "<=" => (lhs <= rhs) as i64 as f64,
+                  ">=" => (lhs >= rhs) as i64 as f64,
+                  "==" => (lhs == rhs) as i64 as f64,
+                  "!=" => (lhs != rhs) as i64 as f64,
+                  _ => return Err(format!("unknown operator `{}`", op))
+            })
+      }
+
+      fn term(&mut self) -> Result<f64> {
+            let token = self.next()?;
+            let unary = |f: fn(f64) -> f64, calc: &mut Calc| calc.term().map(f);
+            match token {
+                  "(" => {
+                        let value = self.expression()?;
+                        if self.next()? != ")" {
+                              return Err(String::from("missing `)`"));
+                        }
+                        Ok(value)
+                  },
+                  "-" => unary(|v| -v, self),
+                  "~" => unary(|v| !(v as i64) as f64, self),
+                  "!" => unary(|v| (v == 0.0) as i64 as f64, self),
+                  "sin" => unary(f64::sin, self),
+                  "cos" => unary(f64::cos, self),
+                  "tan" => unary(f64::tan, self),
+                  "exp" => unary(f64::exp, self),
+                  "log" => unary(f64::ln, self),
+                  "abs" => unary(f64::abs, self),
+                  "sqrt" => unary(f64::sqrt, self),
+                  "sign" => unary(f64::signum, self),
+                  "ceil" => unary(f64::ceil, self),
+                  "floor" => unary(f64::floor, self),
+                  // The byte the program has at an address so far
+                  "@" => {
+                        let addr = self.term()? as i64;
+                        match self.compiler.memory.get(addr as usize) {
+                              Some(&b) if addr >= 0 => Ok(b as f64),
+                              _ => Err(format!("0x{:X} is outside of memory", addr))
+                        }
+                  },
+                  "PI" => Ok(PI),
+                  "E" => Ok(E),
+                  "HERE" => Ok(self.compiler.here as f64),
+                  _ => self.compiler.known(token)
+            }
+      }
+}
+
+/**
+* Compiles Octo source into a program loaded at 0x200, with the address
+* of every label, so that tools can show source names next to addresses.
+*/
+pub fn compile(source: &str) -> ::std::result::Result<Program, AsmError> {
+      let mut tokens = tokenize(source)?;
+      tokens.reverse();
+      let mut c = Compiler {
+            tokens,
+            line: 1,
+            memory: vec![0; MEMORY_SIZE],
+            here: PROGRAM_BASE as u32,
+            start: PROGRAM_BASE as u32,
+            end: PROGRAM_BASE as u32,
+            labels: BTreeMap::new(),
+            constants: BTreeMap::new(),
+            aliases: BTreeMap::new(),
+            macros: BTreeMap::new(),
+            fixups: Vec::new(),
+            started: false,
+            flow: Vec::new(),
+            expansions: 0
+      };
+      while !c.tokens.is_empty() {
+            c.statement().map_err(|message| c.error(message))?;
+      }
+      if let Some(&(ref flow, line)) = c.flow.last() {
+            let message = match *flow {
+                  Flow::Loop(..) => "`loop` without `again`",
+                  _ => "`if ... begin` without `end`"
+            };
+            return Err(AsmError { file: None, line, message: String::from(message) });
+      }
+
+      for fixup in std::mem::take(&mut c.fixups) {
+            c.line = fixup.line;
+            let addr = match c.labels.get(&fixup.name) {
+                  Some(&addr) => addr,
+                  None if fixup.name == "main" => return Err(c.error(String::from("the program has no `main` label"))),
+                  None => return Err(c.error(format!("`{}` is not defined", fixup.name)))
+            };
+            c.patch(fixup.addr, addr, fixup.patch).map_err(|message| c.error(message))?;
+      }
+
+      let labels = c.labels.iter().map(|(name, &addr)| (name.clone(), addr as u16)).collect();
+      Ok(Program { base: c.start as u16, data: c.memory[c.start as usize..c.end as usize].to_vec(), labels })
+}
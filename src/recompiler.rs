@@ -0,0 +1,259 @@
+/*
+* Static recompiler: turns a ROM into the Rust source of a standalone program.
+*
+* Control flow is discovered from the load address by following jumps,
+* calls, returns and skips. Every basic block becomes a function that runs
+* its instructions natively, and a dispatcher maps the program counter to
+* those functions. The generated program links against `runtime` for the
+* display, keypad and timers.
+*
+* Whatever cannot be resolved statically runs on the interpreter instead:
+* computed jumps (Bnnn) land on addresses without a block, and a block whose
+* bytes no longer match the ROM (self-modifying code) is interpreted until
+* control reaches an intact block again.
+*/
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use chip8::Quirks;
+use instruction;
+use instruction::Instruction;
+
+pub struct Block {
+      pub start: u16,
+      pub instructions: Vec<(u16, Instruction)>,
+      // Where execution continues when the block runs into the next one
+      pub fallthrough: Option<u16>
+}
+
+// How control leaves an instruction.
+enum Flow {
+      Next,
+      // Ends the block; execution may continue at any of these addresses
+      End(Vec<u16>)
+}
+
+struct Rom<'a> {
+      data: &'a [u8],
+      base: u16
+}
+
+impl<'a> Rom<'a> {
+      // The opcode at `addr`, if all of it lies within the ROM.
+      fn opcode(&self, addr: u16) -> Option<u16> {
+            let offset = addr.checked_sub(self.base)? as usize;
+            if offset + 2 > self.data.len() {
+                  return None;
+            }
+            Some((self.data[offset] as u16) << 8 | self.data[offset + 1] as u16)
+      }
+      fn instruction(&self, addr: u16) -> Option<Instruction> {
+            self.opcode(addr).map(instruction::decode)
+      }
+      // Where a taken skip at `addr` lands; F000 nnnn is skipped as a whole.
+      fn skip_target(&self, addr: u16) -> u16 {
+            match self.opcode(addr.wrapping_add(2)) {
+                  Some(0xF000) => addr.wrapping_add(6),
+                  _ => addr.wrapping_add(4)
+            }
+      }
+
+      fn flow(&self, addr: u16, instruction: &Instruction, quirks: &Quirks) -> Flow {
+            use instruction::Instruction::*;
+
+            let next = addr.wrapping_add(instruction.len());
+            match *instruction {
+                  Jump(target) if target == addr => Flow::End(vec![]),
+                  Jump(target) => Flow::End(vec![target]),
+                  Call(target) => Flow::End(vec![target, next]),
+                  SkipEqImm(..) | SkipNeImm(..) | SkipEqReg(..) | SkipNeReg(..)
+                        | SkipKey(_) | SkipNotKey(_) => Flow::End(vec![next, self.skip_target(addr)]),
+                  // Computed or unknown destinations are left to the interpreter
+                  Return | Exit | JumpOffset(_) | Sys(_) | Undefined(_) => Flow::End(vec![]),
+                  // These may stay on the same instruction until something external happens
+                  WaitKey(_) => Flow::End(vec![next]),
+                  Draw(..) if quirks.display_wait => Flow::End(vec![next]),
+                  // Writes to memory may change the code that follows
+                  StoreRange(..) | Bcd(_) | StoreRegs(_) => Flow::End(vec![next]),
+                  _ => Flow::Next
+            }
+      }
+}
+
+/**
+* Splits the code reachable from `base` into basic blocks, keyed by start
+* address. Only addresses within the ROM are followed.
+*/
+pub fn discover(data: &[u8], base: u16, quirks: &Quirks) -> BTreeMap<u16, Block> {
+      let rom = Rom { data, base };
+      let mut leaders = BTreeSet::new();
+      let mut seen = BTreeSet::new();
+      let mut worklist = vec![base];
+      leaders.insert(base);
+
+      while let Some(addr) = worklist.pop() {
+            if !seen.insert(addr) {
+                  continue;
+            }
+            let instruction = match rom.instruction(addr) {
+                  Some(i) => i,
+                  None => continue
+            };
+            match rom.flow(addr, &instruction, quirks) {
+                  Flow::Next => worklist.push(addr.wrapping_add(instruction.len())),
+                  Flow::End(targets) => for target in targets {
+                        leaders.insert(target);
+                        worklist.push(target);
+                  }
+            }
+      }
+
+      let mut blocks = BTreeMap::new();
+      for &start in leaders.iter() {
+            if !seen.contains(&start) || rom.opcode(start).is_none() {
+                  continue;
+            }
+            let mut block = Block { start, instructions: Vec::new(), fallthrough: None };
+            let mut addr = start;
+            while let Some(instruction) = rom.instruction(addr) {
+                  block.instructions.push((addr, instruction));
+                  if let Flow::End(_) = rom.flow(addr, &instruction, quirks) {
+                        break;
+                  }
+                  addr = addr.wrapping_add(instruction.len());
+                  if leaders.contains(&addr) || rom.opcode(addr).is_none() {
+                        block.fallthrough = Some(addr);
+                        break;
+                  }
+            }
+            blocks.insert(start, block);
+      }
+      blocks
+}
+
+fn reg(r: u8) -> String {
+      format!("c.registers[0x{:X}]", r)
+}
+
+// Native code for one instruction, or `None` to have it executed by the interpreter.
+fn translate(rom: &Rom, addr: u16, instruction: &Instruction, quirks: &Quirks) -> Option<String> {
+      use instruction::Instruction::*;
+
+      let vf_reset = if quirks.vf_reset { format!(" {} = 0;", reg(0xF)) } else { String::new() };
+      let shift_source = |x: u8, y: u8| if quirks.shift_uses_vy { y } else { x };
+      let skip = |cond: String| format!("c.program_counter = if {} {{ 0x{:03X} }} else {{ 0x{:03X} }};",
+                                        cond, rom.skip_target(addr), addr.wrapping_add(2));
+      Some(match *instruction {
+            Jump(target) if target != addr => format!("c.program_counter = 0x{:03X};", target),
+            SkipEqImm(x, kk) => skip(format!("{} == 0x{:02X}", reg(x), kk)),
+            SkipNeImm(x, kk) => skip(format!("{} != 0x{:02X}", reg(x), kk)),
+            SkipEqReg(x, y) => skip(format!("{} == {}", reg(x), reg(y))),
+            SkipNeReg(x, y) => skip(format!("{} != {}", reg(x), reg(y))),
+            SkipKey(x) => skip(format!("c.keypad[({} & 0xF) as usize]", reg(x))),
+            SkipNotKey(x) => skip(format!("!c.keypad[({} & 0xF) as usize]", reg(x))),
+            LoadImm(x, kk) => format!("{} = 0x{:02X};", reg(x), kk),
+            AddImm(x, kk) => format!("{0} = {0}.wrapping_add(0x{1:02X});", reg(x), kk),
+            Move(x, y) => format!("{} = {};", reg(x), reg(y)),
+            Or(x, y) => format!("{0} |= {1};{2}", reg(x), reg(y), vf_reset),
+            And(x, y) => format!("{0} &= {1};{2}", reg(x), reg(y), vf_reset),
+            Xor(x, y) => format!("{0} ^= {1};{2}", reg(x), reg(y), vf_reset),
+            Add(x, y) => format!("let (v, carry) = {0}.overflowing_add({1}); {0} = v; {2} = carry as u8;",
+                                 reg(x), reg(y), reg(0xF)),
+            Sub(x, y) => format!("let (a, b) = ({}, {}); {} = a.wrapping_sub(b); {} = (a > b) as u8;",
+                                 reg(x), reg(y), reg(x), reg(0xF)),
+            SubN(x, y) => format!("let (a, b) = ({}, {}); {} = a.wrapping_sub(b); {} = (a > b) as u8;",
+                                  reg(y), reg(x), reg(x), reg(0xF)),
+            ShiftRight(x, y) => format!("let v = {}; {} = v & 0x1; {} = v >> 1;",
+                                        reg(shift_source(x, y)), reg(0xF), reg(x)),
+            ShiftLeft(x, y) => format!("let v = {}; {} = v >> 7; {} = v << 1;",
+                                       reg(shift_source(x, y)), reg(0xF), reg(x)),
+            LoadI(nnn) => format!("c.index = 0x{:03X};", nnn),
+            AddI(x) => format!("c.index = c.index.wrapping_add({} as u16);", reg(x)),
+            LoadDelay(x) => format!("{} = c.delay_timer;", reg(x)),
+            SetDelay(x) => format!("c.delay_timer = {};", reg(x)),
+            SetSound(x) => format!("c.sound_timer = {};", reg(x)),
+            _ => return None
+      })
+}
+
+/**
+* Rust source for the native part of a recompiled program: the ROM, a
+* function per block and the `step` dispatcher, which runs one block (or
+* one interpreted instruction) and returns what it cost. `recompile` wraps
+* this in a program; it can also be built into anything that links this crate.
+*/
+pub fn translate_rom(data: &[u8], base: u16, quirks: &Quirks) -> String {
+      let rom = Rom { data, base };
+      let blocks = discover(data, base, quirks);
+      let mut out = String::new();
+
+      writeln!(out, "use chip8::{{Chip, ChipError, Instruction}};\n").unwrap();
+      writeln!(out, "pub const BASE: usize = 0x{:03X};", base).unwrap();
+      writeln!(out, "pub const ROM: [u8; {}] = [", data.len()).unwrap();
+      for line in data.chunks(16) {
+            let bytes: Vec<String> = line.iter().map(|b| format!("0x{:02X}", b)).collect();
+            writeln!(out, "      {},", bytes.join(", ")).unwrap();
+      }
+      writeln!(out, "];\n").unwrap();
+
+      for block in blocks.values() {
+            let start = block.start as usize;
+            let end = start + block.instructions.iter().map(|(_, i)| i.len() as usize).sum::<usize>();
+            writeln!(out, "fn block_{:04x}(c: &mut Chip) -> Result<u32, ChipError> {{", block.start).unwrap();
+            writeln!(out, "      // Stopped, or stopping within the block: leave it to the interpreter").unwrap();
+            writeln!(out, "      if !c.running() || !c.watchdog_allows({}) {{", block.instructions.len()).unwrap();
+            writeln!(out, "            return c.cycle();\n      }}").unwrap();
+            writeln!(out, "      // Self-modified: leave it to the interpreter").unwrap();
+            writeln!(out, "      if c.memory[0x{:03X}..0x{:03X}] != ROM[0x{:03X}..0x{:03X}] {{",
+                     start, end, start - base as usize, end - base as usize).unwrap();
+            writeln!(out, "            return c.cycle();\n      }}").unwrap();
+            for (count, (addr, instruction)) in block.instructions.iter().enumerate() {
+                  writeln!(out, "      // {:03X}: {}", addr, instruction).unwrap();
+                  match translate(&rom, *addr, instruction, quirks) {
+                        Some(code) => {
+                              writeln!(out, "      {{ {} }}", code).unwrap();
+                              writeln!(out, "      c.count_instructions(1);").unwrap();
+                        }
+                        None => {
+                              writeln!(out, "      c.program_counter = 0x{:03X};", addr).unwrap();
+                              writeln!(out, "      c.execute(Instruction::{:?})?;", instruction).unwrap();
+                              writeln!(out, "      c.count_instructions(1);").unwrap();
+                              // Halts, EXIT and the like end the block where they happen
+                              writeln!(out, "      if !c.running() {{\n            return Ok({});\n      }}",
+                                       count + 1).unwrap();
+                        }
+                  }
+            }
+            if let Some(next) = block.fallthrough {
+                  writeln!(out, "      c.program_counter = 0x{:03X};", next).unwrap();
+            }
+            writeln!(out, "      Ok({})\n}}\n", block.instructions.len()).unwrap();
+      }
+
+      writeln!(out, "pub fn step(c: &mut Chip) -> Result<u32, ChipError> {{").unwrap();
+      writeln!(out, "      match c.program_counter {{").unwrap();
+      for start in blocks.keys() {
+            writeln!(out, "            0x{:03X} => block_{:04x}(c),", start, start).unwrap();
+      }
+      writeln!(out, "            _ => c.cycle()\n      }}\n}}").unwrap();
+      out
+}
+
+// Rust source for a standalone build of `data`, which runs as `name`.
+pub fn recompile(name: &str, data: &[u8], base: u16, quirks: &Quirks) -> String {
+      let mut out = String::new();
+
+      writeln!(out, "// Recompiled from `{}` by `chip8 recompile`; {} blocks.",
+               name, discover(data, base, quirks).len()).unwrap();
+      writeln!(out, "extern crate chip8;\n").unwrap();
+      writeln!(out, "use chip8::Quirks;").unwrap();
+      writeln!(out, "use chip8::runtime;").unwrap();
+      out.push_str(&translate_rom(data, base, quirks));
+
+      writeln!(out, "\nfn main() {{").unwrap();
+      writeln!(out, "      let quirks = {:?};", quirks).unwrap();
+      writeln!(out, "      runtime::run({:?}, BASE as u16, &ROM, quirks, step);", name).unwrap();
+      writeln!(out, "}}").unwrap();
+      out
+}
@@ -0,0 +1,152 @@
+/*
+* Rewind buffer: a ring of machine states, one per frame, that can be
+* popped to step emulation backwards.
+*
+* Only the newest state is kept in full. Every older state is stored as the
+* difference to the state that followed it (XOR, with runs of unchanged
+* bytes compressed away), which is usually a few dozen bytes per frame.
+*/
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+use snapshot::Snapshot;
+
+pub struct Rewind {
+      // Most states kept, including the newest one
+      capacity: usize,
+      // Newest state, serialized
+      latest: Option<Vec<u8>>,
+      // deltas[i] turns state i + 1 back into state i; the back is the newest
+      deltas: VecDeque<Vec<u8>>
+}
+
+fn put_varint(out: &mut Vec<u8>, mut v: usize) {
+      while v >= 0x80 {
+            out.push((v as u8) | 0x80);
+            v >>= 7;
+      }
+      out.push(v as u8);
+}
+fn get_varint(data: &[u8], pos: &mut usize) -> usize {
+      let mut v = 0;
+      let mut shift = 0;
+      loop {
+            let byte = data[*pos];
+            *pos += 1;
+            v |= ((byte & 0x7F) as usize) << shift;
+            if byte & 0x80 == 0 {
+                  return v;
+            }
+            shift += 7;
+      }
+}
+
+/**
+* Delta encoding of `a XOR b` (`from` and `to` here), as a sequence of
+*   varint  count of unchanged bytes to skip
+*   varint  count of changed bytes that follow
+*   ...     the changed bytes, XORed
+* after a leading 0x00. A leading 0xFF instead marks a full copy of `b`,
+* for states whose length differs.
+*/
+fn diff(from: &[u8], to: &[u8]) -> Vec<u8> {
+      let mut out = Vec::new();
+      if from.len() != to.len() {
+            out.push(0xFF);
+            out.extend_from_slice(to);
+            return out;
+      }
+      out.push(0x00);
+
+      let mut i = 0;
+      while i < from.len() {
+            let start = i;
+            while i < from.len() && from[i] == to[i] {
+                  i += 1;
+            }
+            let skip = i - start;
+            let start = i;
+            while i < from.len() && from[i] != to[i] {
+                  i += 1;
+            }
+            put_varint(&mut out, skip);
+            put_varint(&mut out, i - start);
+            for j in start..i {
+                  out.push(from[j] ^ to[j]);
+            }
+      }
+      out
+}
+
+fn patch(from: &[u8], delta: &[u8]) -> Vec<u8> {
+      if delta[0] == 0xFF {
+            return delta[1..].to_vec();
+      }
+
+      let mut out = from.to_vec();
+      let mut pos = 1;
+      let mut i = 0;
+      while pos < delta.len() {
+            i += get_varint(delta, &mut pos);
+            let changed = get_varint(delta, &mut pos);
+            for j in 0..changed {
+                  out[i + j] ^= delta[pos + j];
+            }
+            i += changed;
+            pos += changed;
+      }
+      out
+}
+
+impl Rewind {
+      pub fn new(capacity: usize) -> Rewind {
+            Rewind {
+                  capacity: capacity.max(1),
+                  latest: None,
+                  deltas: VecDeque::new()
+            }
+      }
+
+      // Records a new state, dropping the oldest one once the buffer is full.
+      pub fn push(&mut self, snapshot: &Snapshot) {
+            let bytes = snapshot.to_bytes();
+            if let Some(latest) = self.latest.take() {
+                  self.deltas.push_back(diff(&bytes, &latest));
+                  if self.deltas.len() >= self.capacity {
+                        self.deltas.pop_front();
+                  }
+            }
+            self.latest = Some(bytes);
+      }
+
+      /**
+      * Steps one state back: discards the newest state and returns the one
+      * recorded before it. The oldest state is never discarded, so holding
+      * rewind stops there.
+      */
+      pub fn pop(&mut self) -> Option<Snapshot> {
+            let delta = self.deltas.pop_back()?;
+            let previous = patch(self.latest.as_ref()?, &delta);
+            let snapshot = Snapshot::from_bytes(&previous).ok();
+            self.latest = Some(previous);
+            snapshot
+      }
+
+      pub fn len(&self) -> usize {
+            self.deltas.len() + if self.latest.is_some() { 1 } else { 0 }
+      }
+      pub fn is_empty(&self) -> bool {
+            self.latest.is_none()
+      }
+
+      // Bytes held by the buffer, for diagnostics.
+      pub fn memory_used(&self) -> usize {
+            self.latest.as_ref().map_or(0, |l| l.len()) + self.deltas.iter().map(|d| d.len()).sum::<usize>()
+      }
+
+      pub fn clear(&mut self) {
+            self.latest = None;
+            self.deltas.clear();
+      }
+}
@@ -0,0 +1,50 @@
+/*
+* Random number sources for Cxkk. The machine owns its generator, so a
+* given seed always replays the same sequence, and save states can capture
+* the generator along with the rest of the machine.
+*/
+
+pub trait Rng {
+      // Restarts the sequence from `seed`.
+      fn seed(&mut self, seed: u64);
+      fn next_u8(&mut self) -> u8;
+      // Opaque generator state, for save states.
+      fn state(&self) -> u64;
+      fn set_state(&mut self, state: u64);
+}
+
+/**
+* xorshift64* -- small, fast, and its whole state fits in a u64.
+* --> https://en.wikipedia.org/wiki/Xorshift#xorshift*
+*/
+#[derive(Clone, Debug)]
+pub struct XorShift {
+      state: u64
+}
+
+impl XorShift {
+      pub fn new(seed: u64) -> XorShift {
+            let mut rng = XorShift { state: 0 };
+            rng.seed(seed);
+            rng
+      }
+}
+
+impl Rng for XorShift {
+      fn seed(&mut self, seed: u64) {
+            // An all-zero state would only ever produce zeros
+            self.state = if seed == 0 { 0x9E3779B97F4A7C15 } else { seed };
+      }
+      fn next_u8(&mut self) -> u8 {
+            self.state ^= self.state >> 12;
+            self.state ^= self.state << 25;
+            self.state ^= self.state >> 27;
+            (self.state.wrapping_mul(0x2545F4914F6CDD1D) >> 56) as u8
+      }
+      fn state(&self) -> u64 {
+            self.state
+      }
+      fn set_state(&mut self, state: u64) {
+            self.state = state;
+      }
+}
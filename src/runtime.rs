@@ -0,0 +1,44 @@
+/*
+* What programs built by the recompiler link against: a machine to hold
+* their state, the SFML window for display, keypad and sound, and the frame
+* scheduler that ticks the timers. `step` runs compiled code, and hands
+* anything it does not know to the interpreter.
+*/
+
+use chip8::{Chip, ChipError, Quirks};
+use display::Display;
+use display_sfml::{AudioStream, DisplaySFML};
+use scheduler::Scheduler;
+
+const FRAME_RATE: u32 = 60; // Hz
+const INSTRUCTIONS_PER_FRAME: u32 = 8;
+
+pub fn run(name: &str, base: u16, rom: &[u8], quirks: Quirks, step: fn(&mut Chip) -> Result<u32, ChipError>) {
+      let mut chip = Chip::new(quirks);
+      let loaded = chip.set_load_address(base).and_then(|_| chip.load_rom_bytes(rom));
+      if let Err(e) = loaded {
+            println!("{}", e);
+            return;
+      }
+      chip.rom = name.to_string();
+
+      let mut audio_stream = AudioStream::new();
+      let mut display = DisplaySFML::new(&mut audio_stream);
+      display.init();
+
+      let mut scheduler = Scheduler::new(FRAME_RATE, INSTRUCTIONS_PER_FRAME);
+      loop {
+            if scheduler.run_frame_with(&mut chip, step) {
+                  println!("Machine {}", chip.state);
+            }
+            chip.tick_timers();
+
+            display.update(&mut chip);
+            if display.should_close() {
+                  break;
+            }
+            display.draw(&chip);
+            chip.display.clear_dirty();
+            scheduler.wait();
+      }
+}
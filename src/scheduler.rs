@@ -0,0 +1,123 @@
+/*
+* Frame pacing: the emulator advances in 60Hz frames, each running a fixed
+* budget of CPU work, and sleeps between them instead of spinning on the
+* clock. Deadlines are kept on an absolute schedule, so sleeping slightly
+* too long on one frame does not push every later frame back.
+*/
+
+use std::fmt;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chip8::{Chip, ChipError, MachineState};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameStats {
+      pub frames: u64,
+      // Deadlines that had already passed by the time a frame was done
+      pub missed: u64,
+      // How late frames started compared to their deadline
+      pub total_drift: Duration,
+      pub max_drift: Duration
+}
+
+impl fmt::Display for FrameStats {
+      fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+            let average = if self.frames == 0 { 0.0 } else { ms(self.total_drift) / self.frames as f64 };
+            write!(f, "{} frames, {} missed, drift {:.3} ms on average, {:.3} ms at most",
+                   self.frames, self.missed, average, ms(self.max_drift))
+      }
+}
+
+pub struct Scheduler {
+      frame: Duration,
+      next: Instant,
+      // Cost units (instructions, or VIP machine cycles) granted per frame
+      cycles_per_frame: u32,
+      // Cost units left in this frame, negative when an instruction overran it
+      budget: i64,
+      stats: FrameStats
+}
+
+impl Scheduler {
+      pub fn new(frame_rate: u32, cycles_per_frame: u32) -> Scheduler {
+            let frame = Duration::from_nanos(1_000_000_000 / frame_rate as u64);
+            Scheduler {
+                  frame,
+                  next: Instant::now() + frame,
+                  cycles_per_frame,
+                  budget: 0,
+                  stats: FrameStats::default()
+            }
+      }
+
+      pub fn stats(&self) -> FrameStats {
+            self.stats
+      }
+
+      /**
+      * Runs one frame's worth of instructions on `chip`. A Dxyn waiting for
+      * vertical blank idles away the rest of the frame. Returns whether the
+      * machine stopped during the frame.
+      */
+      pub fn run_frame(&mut self, chip: &mut Chip) -> bool {
+            self.run_frame_with(chip, Chip::cycle)
+      }
+      // Same, with `step` advancing the machine and returning the cost of what it ran.
+      pub fn run_frame_with<F>(&mut self, chip: &mut Chip, mut step: F) -> bool
+            where F: FnMut(&mut Chip) -> Result<u32, ChipError>
+      {
+            self.run_frame_until(chip, |chip| step(chip).map(Some))
+      }
+      /**
+      * Same again, except that `step` may return `None` instead of a cost to
+      * end the frame early, as a debugger does when it pauses the machine.
+      * The rest of the frame's budget is dropped.
+      */
+      pub fn run_frame_until<F>(&mut self, chip: &mut Chip, mut step: F) -> bool
+            where F: FnMut(&mut Chip) -> Result<Option<u32>, ChipError>
+      {
+            if !chip.running() {
+                  return false;
+            }
+            self.budget += self.cycles_per_frame as i64;
+            while self.budget > 0 && chip.running() && !chip.waiting_for_vblank() {
+                  match step(chip) {
+                        Ok(Some(cost)) => self.budget -= cost as i64,
+                        Ok(None) => self.budget = self.budget.min(0),
+                        Err(e) => chip.state = MachineState::Faulted(e)
+                  }
+            }
+            if chip.waiting_for_vblank() {
+                  self.budget = self.budget.min(0);
+            }
+            !chip.running()
+      }
+
+      /**
+      * Sleeps until the next frame is due. When the frame took longer than
+      * its slot, the deadlines already passed are counted as missed and
+      * skipped, rather than caught up on with a burst of frames.
+      */
+      pub fn wait(&mut self) {
+            let now = Instant::now();
+            if now < self.next {
+                  thread::sleep(self.next - now);
+            } else {
+                  let late = now - self.next;
+                  let missed = (late.as_nanos() / self.frame.as_nanos()) as u64;
+                  self.stats.missed += missed;
+                  self.next += self.frame * missed as u32;
+            }
+
+            let woke = Instant::now();
+            let drift = if woke > self.next { woke - self.next } else { Duration::from_secs(0) };
+            self.stats.total_drift += drift;
+            if drift > self.stats.max_drift {
+                  self.stats.max_drift = drift;
+            }
+            self.stats.frames += 1;
+            self.next += self.frame;
+      }
+}
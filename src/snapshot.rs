@@ -0,0 +1,409 @@
+/*
+* Save states: a complete copy of a running machine, and the binary file
+* format they are stored in.
+*
+* Layout (all integers little-endian):
+*   "CH8S"            magic
+*   u16               format version
+*   ...               fields, in the order `Snapshot::to_bytes` writes them
+* Variable-length fields (strings, memory, display, stack) are prefixed with
+* their length as a u32. The machine state is a tag byte followed by the
+* fields of that state, or of the fault that stopped it.
+*
+* Version 2 replaced the running flag of version 1 with the full machine
+* state and the instruction count; version 1 states are not read.
+*/
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::error::Error;
+#[cfg(feature = "std")]
+use std::fs::File;
+#[cfg(feature = "std")]
+use std::io::prelude::*;
+
+use audio::PATTERN_SIZE;
+use chip8::{ChipError, MachineState, Quirks};
+
+const MAGIC: &[u8; 4] = b"CH8S";
+pub const VERSION: u16 = 2;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SnapshotError {
+      // Not a save state at all
+      BadMagic,
+      // Written by a newer (or unknown) version of the format
+      UnsupportedVersion(u16),
+      // The data ends before the last field
+      Truncated,
+      // The state does not fit the machine it is being restored into
+      Incompatible(String),
+      Io(String)
+}
+
+impl fmt::Display for SnapshotError {
+      fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match *self {
+                  SnapshotError::BadMagic => write!(f, "not a save state"),
+                  SnapshotError::UnsupportedVersion(v) => write!(f, "unsupported save state version {}", v),
+                  SnapshotError::Truncated => write!(f, "save state is truncated"),
+                  SnapshotError::Incompatible(ref cause) => write!(f, "incompatible save state: {}", cause),
+                  SnapshotError::Io(ref cause) => write!(f, "could not access save state: {}", cause)
+            }
+      }
+}
+
+#[cfg(feature = "std")]
+impl Error for SnapshotError {}
+
+/**
+* Everything that defines a running `Chip`; see `Chip::snapshot` and
+* `Chip::restore`.
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+      // Identity of the loaded ROM: its path, length and FNV-1a hash
+      pub rom: String,
+      pub rom_size: u32,
+      pub rom_hash: u32,
+      pub memory: Vec<u8>,
+      pub registers: [u8; 16],
+      pub stack: Vec<u16>,
+      pub stack_pointer: u16,
+      pub program_counter: u16,
+      pub index: u16,
+      pub state: MachineState,
+      pub instructions: u64,
+      pub display: Vec<u8>,
+      pub plane: u8,
+      pub hires: bool,
+      pub rpl: [u8; 16],
+      pub delay_timer: u8,
+      pub sound_timer: u8,
+      pub key_pressed: u8,
+      pub keypad: [bool; 16],
+      pub wait: u8,
+      pub vblank: bool,
+      pub audio_pattern: [u8; PATTERN_SIZE],
+      pub audio_pitch: u8,
+      pub audio_position: f64,
+      pub quirks: Quirks,
+      pub rng_seed: u64,
+      pub rng_state: u64
+}
+
+// FNV-1a, to tell ROMs apart without keeping a copy of them
+pub fn rom_hash(data: &[u8]) -> u32 {
+      let mut hash: u32 = 0x811C9DC5;
+      for &byte in data {
+            hash ^= byte as u32;
+            hash = hash.wrapping_mul(0x01000193);
+      }
+      hash
+}
+
+fn quirks_to_bits(q: &Quirks) -> u8 {
+      (q.shift_uses_vy as u8)
+            | (q.load_store_increment as u8) << 1
+            | (q.jump_uses_vx as u8) << 2
+            | (q.vf_reset as u8) << 3
+            | (q.wrap_sprites as u8) << 4
+            | (q.display_wait as u8) << 5
+}
+fn quirks_from_bits(bits: u8) -> Quirks {
+      Quirks {
+            shift_uses_vy: bits & 0x01 != 0,
+            load_store_increment: bits & 0x02 != 0,
+            jump_uses_vx: bits & 0x04 != 0,
+            vf_reset: bits & 0x08 != 0,
+            wrap_sprites: bits & 0x10 != 0,
+            display_wait: bits & 0x20 != 0
+      }
+}
+
+fn put_state(out: &mut Vec<u8>, state: &MachineState) {
+      match *state {
+            MachineState::Running => out.push(0),
+            MachineState::Halted { pc } => {
+                  out.push(1);
+                  put_u16(out, pc);
+            },
+            MachineState::Exited => out.push(2),
+            MachineState::WatchdogExpired { instructions } => {
+                  out.push(3);
+                  put_u64(out, instructions);
+            },
+            MachineState::Faulted(ref e) => {
+                  out.push(4);
+                  put_error(out, e);
+            }
+      }
+}
+fn put_error(out: &mut Vec<u8>, error: &ChipError) {
+      let fault = |out: &mut Vec<u8>, tag, pc, opcode| {
+            out.push(tag);
+            put_u16(out, pc);
+            put_u16(out, opcode);
+      };
+      match *error {
+            ChipError::UndefinedInstruction { pc, opcode } => fault(out, 0, pc, opcode),
+            ChipError::MachineCode { pc, opcode } => fault(out, 1, pc, opcode),
+            ChipError::StackOverflow { pc, opcode } => fault(out, 2, pc, opcode),
+            ChipError::StackUnderflow { pc, opcode } => fault(out, 3, pc, opcode),
+            ChipError::MemoryOutOfBounds { pc, opcode, addr } => {
+                  fault(out, 4, pc, opcode);
+                  put_u32(out, addr as u32);
+            },
+            ChipError::Runaway { pc, opcode } => fault(out, 5, pc, opcode),
+            ChipError::RomIo { ref path, ref cause } => {
+                  out.push(6);
+                  put_vec(out, path.as_bytes());
+                  put_vec(out, cause.as_bytes());
+            },
+            ChipError::RomEmpty => out.push(7),
+            ChipError::RomTooLarge { size, max } => {
+                  out.push(8);
+                  put_u32(out, size as u32);
+                  put_u32(out, max as u32);
+            },
+            ChipError::InvalidLoadAddress(addr) => {
+                  out.push(9);
+                  put_u16(out, addr);
+            },
+            ChipError::InvalidConfig(ref cause) => {
+                  out.push(10);
+                  put_vec(out, cause.as_bytes());
+            }
+      }
+}
+
+struct Reader<'a> {
+      data: &'a [u8],
+      pos: usize
+}
+
+impl<'a> Reader<'a> {
+      fn bytes(&mut self, len: usize) -> Result<&'a [u8], SnapshotError> {
+            if self.pos + len > self.data.len() {
+                  return Err(SnapshotError::Truncated);
+            }
+            let slice = &self.data[self.pos..self.pos + len];
+            self.pos += len;
+            Ok(slice)
+      }
+      fn u8(&mut self) -> Result<u8, SnapshotError> {
+            Ok(self.bytes(1)?[0])
+      }
+      fn bool(&mut self) -> Result<bool, SnapshotError> {
+            Ok(self.u8()? != 0)
+      }
+      fn u16(&mut self) -> Result<u16, SnapshotError> {
+            let b = self.bytes(2)?;
+            Ok(b[0] as u16 | (b[1] as u16) << 8)
+      }
+      fn u32(&mut self) -> Result<u32, SnapshotError> {
+            let b = self.bytes(4)?;
+            Ok((0..4).fold(0, |acc, i| acc | (b[i] as u32) << (8 * i)))
+      }
+      fn u64(&mut self) -> Result<u64, SnapshotError> {
+            let b = self.bytes(8)?;
+            Ok((0..8).fold(0, |acc, i| acc | (b[i] as u64) << (8 * i)))
+      }
+      fn array16(&mut self) -> Result<[u8; 16], SnapshotError> {
+            let mut out = [0; 16];
+            out.copy_from_slice(self.bytes(16)?);
+            Ok(out)
+      }
+      fn vec(&mut self) -> Result<Vec<u8>, SnapshotError> {
+            let len = self.u32()? as usize;
+            Ok(self.bytes(len)?.to_vec())
+      }
+      fn string(&mut self, what: &str) -> Result<String, SnapshotError> {
+            String::from_utf8(self.vec()?)
+                  .map_err(|_| SnapshotError::Incompatible(format!("{} is not UTF-8", what)))
+      }
+      fn state(&mut self) -> Result<MachineState, SnapshotError> {
+            Ok(match self.u8()? {
+                  0 => MachineState::Running,
+                  1 => MachineState::Halted { pc: self.u16()? },
+                  2 => MachineState::Exited,
+                  3 => MachineState::WatchdogExpired { instructions: self.u64()? },
+                  4 => MachineState::Faulted(self.error()?),
+                  tag => return Err(SnapshotError::Incompatible(format!("unknown machine state {}", tag)))
+            })
+      }
+      fn error(&mut self) -> Result<ChipError, SnapshotError> {
+            let tag = self.u8()?;
+            if tag <= 5 {
+                  let pc = self.u16()?;
+                  let opcode = self.u16()?;
+                  return Ok(match tag {
+                        0 => ChipError::UndefinedInstruction { pc, opcode },
+                        1 => ChipError::MachineCode { pc, opcode },
+                        2 => ChipError::StackOverflow { pc, opcode },
+                        3 => ChipError::StackUnderflow { pc, opcode },
+                        4 => ChipError::MemoryOutOfBounds { pc, opcode, addr: self.u32()? as usize },
+                        _ => ChipError::Runaway { pc, opcode }
+                  });
+            }
+            Ok(match tag {
+                  6 => ChipError::RomIo { path: self.string("ROM path")?, cause: self.string("fault")? },
+                  7 => ChipError::RomEmpty,
+                  8 => ChipError::RomTooLarge { size: self.u32()? as usize, max: self.u32()? as usize },
+                  9 => ChipError::InvalidLoadAddress(self.u16()?),
+                  10 => ChipError::InvalidConfig(self.string("fault")?),
+                  _ => return Err(SnapshotError::Incompatible(format!("unknown fault {}", tag)))
+            })
+      }
+}
+
+fn put_u16(out: &mut Vec<u8>, v: u16) {
+      out.extend_from_slice(&[v as u8, (v >> 8) as u8]);
+}
+fn put_u32(out: &mut Vec<u8>, v: u32) {
+      for i in 0..4 {
+            out.push((v >> (8 * i)) as u8);
+      }
+}
+fn put_u64(out: &mut Vec<u8>, v: u64) {
+      for i in 0..8 {
+            out.push((v >> (8 * i)) as u8);
+      }
+}
+fn put_vec(out: &mut Vec<u8>, v: &[u8]) {
+      put_u32(out, v.len() as u32);
+      out.extend_from_slice(v);
+}
+
+impl Snapshot {
+      pub fn to_bytes(&self) -> Vec<u8> {
+            let mut out = Vec::with_capacity(self.memory.len() + self.display.len() + 256);
+            out.extend_from_slice(MAGIC);
+            put_u16(&mut out, VERSION);
+
+            put_vec(&mut out, self.rom.as_bytes());
+            put_u32(&mut out, self.rom_size);
+            put_u32(&mut out, self.rom_hash);
+            put_vec(&mut out, &self.memory);
+            out.extend_from_slice(&self.registers);
+            put_u32(&mut out, self.stack.len() as u32);
+            for &addr in self.stack.iter() {
+                  put_u16(&mut out, addr);
+            }
+            put_u16(&mut out, self.stack_pointer);
+            put_u16(&mut out, self.program_counter);
+            put_u16(&mut out, self.index);
+            put_state(&mut out, &self.state);
+            put_u64(&mut out, self.instructions);
+            put_vec(&mut out, &self.display);
+            out.push(self.plane);
+            out.push(self.hires as u8);
+            out.extend_from_slice(&self.rpl);
+            out.push(self.delay_timer);
+            out.push(self.sound_timer);
+            out.push(self.key_pressed);
+            put_u16(&mut out, self.keypad.iter().enumerate().fold(0, |acc, (i, &k)| acc | (k as u16) << i));
+            out.push(self.wait);
+            out.push(self.vblank as u8);
+            out.extend_from_slice(&self.audio_pattern);
+            out.push(self.audio_pitch);
+            put_u64(&mut out, self.audio_position.to_bits());
+            out.push(quirks_to_bits(&self.quirks));
+            put_u64(&mut out, self.rng_seed);
+            put_u64(&mut out, self.rng_state);
+            out
+      }
+
+      pub fn from_bytes(data: &[u8]) -> Result<Snapshot, SnapshotError> {
+            let mut r = Reader { data, pos: 0 };
+            if r.bytes(MAGIC.len()).map_err(|_| SnapshotError::BadMagic)? != MAGIC {
+                  return Err(SnapshotError::BadMagic);
+            }
+            let version = r.u16()?;
+            if version != VERSION {
+                  return Err(SnapshotError::UnsupportedVersion(version));
+            }
+
+            let rom = r.string("ROM name")?;
+            let rom_size = r.u32()?;
+            let rom_hash = r.u32()?;
+            let memory = r.vec()?;
+            let registers = r.array16()?;
+            let stack_len = r.u32()? as usize;
+            let mut stack = Vec::with_capacity(stack_len.min(data.len()));
+            for _ in 0..stack_len {
+                  stack.push(r.u16()?);
+            }
+            let stack_pointer = r.u16()?;
+            let program_counter = r.u16()?;
+            let index = r.u16()?;
+            let state = r.state()?;
+            let instructions = r.u64()?;
+            let display = r.vec()?;
+            let plane = r.u8()?;
+            let hires = r.bool()?;
+            let rpl = r.array16()?;
+            let delay_timer = r.u8()?;
+            let sound_timer = r.u8()?;
+            let key_pressed = r.u8()?;
+            let keys = r.u16()?;
+            let mut keypad = [false; 16];
+            for (i, key) in keypad.iter_mut().enumerate() {
+                  *key = keys & (1 << i) != 0;
+            }
+            let wait = r.u8()?;
+            let vblank = r.bool()?;
+            let audio_pattern = r.array16()?;
+            let audio_pitch = r.u8()?;
+            let audio_position = f64::from_bits(r.u64()?);
+            let quirks = quirks_from_bits(r.u8()?);
+            let rng_seed = r.u64()?;
+            let rng_state = r.u64()?;
+
+            Ok(Snapshot {
+                  rom,
+                  rom_size,
+                  rom_hash,
+                  memory,
+                  registers,
+                  stack,
+                  stack_pointer,
+                  program_counter,
+                  index,
+                  state,
+                  instructions,
+                  display,
+                  plane,
+                  hires,
+                  rpl,
+                  delay_timer,
+                  sound_timer,
+                  key_pressed,
+                  keypad,
+                  wait,
+                  vblank,
+                  audio_pattern,
+                  audio_pitch,
+                  audio_position,
+                  quirks,
+                  rng_seed,
+                  rng_state
+            })
+      }
+
+      #[cfg(feature = "std")]
+      pub fn save(&self, path: &str) -> Result<(), SnapshotError> {
+            let mut file = File::create(path).map_err(|e| SnapshotError::Io(e.to_string()))?;
+            file.write_all(&self.to_bytes()).map_err(|e| SnapshotError::Io(e.to_string()))
+      }
+
+      #[cfg(feature = "std")]
+      pub fn load(path: &str) -> Result<Snapshot, SnapshotError> {
+            let mut file = File::open(path).map_err(|e| SnapshotError::Io(e.to_string()))?;
+            let mut contents = Vec::new();
+            file.read_to_end(&mut contents).map_err(|e| SnapshotError::Io(e.to_string()))?;
+            Snapshot::from_bytes(&contents)
+      }
+}
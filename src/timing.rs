@@ -0,0 +1,102 @@
+/*
+* How long instructions take. By default every instruction costs the same,
+* and the frontend picks how many run per second. In VIP mode each
+* instruction costs what it did on the COSMAC VIP, in machine cycles of its
+* 1802 CPU (8 clock periods at 1.7609 MHz), so the frontend can instead run
+* a fixed budget of cycles per 60Hz frame.
+*
+* The costs follow the structure of the original interpreter: a fixed
+* fetch & dispatch overhead, plus the work done by each routine, which for
+* some instructions depends on the data it handles.
+*/
+
+use chip8::Chip;
+use instruction::Instruction;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Timing {
+      // Every instruction costs 1
+      Instructions,
+      // Every instruction costs its COSMAC VIP machine cycles
+      Vip
+}
+
+impl Timing {
+      pub fn from_name(name: &str) -> Option<Timing> {
+            match name {
+                  "instructions" => Some(Timing::Instructions),
+                  "vip" => Some(Timing::Vip),
+                  _ => None
+            }
+      }
+}
+
+// Machine cycles in one 60Hz frame: 1760900 / 8 / 60
+pub const VIP_FRAME_CYCLES: u32 = 3668;
+// Taken every frame by the display interrupt routine and the video DMA
+pub const VIP_INTERRUPT_CYCLES: u32 = 1122;
+// What is left for the interpreter in each frame
+pub const VIP_CYCLES_PER_FRAME: u32 = VIP_FRAME_CYCLES - VIP_INTERRUPT_CYCLES;
+
+// Fetching the opcode and jumping to its routine
+const FETCH: u32 = 40;
+// Extra cost of a skip that is taken
+const SKIP: u32 = 4;
+
+/**
+* Machine cycles `instruction` takes on the VIP, given the machine it is
+* about to run on. Instructions the VIP never had are charged like a
+* register load.
+*/
+pub fn vip_cycles(chip: &Chip, instruction: &Instruction) -> u32 {
+      use instruction::Instruction::*;
+
+      let reg = |r: u8| chip.registers[r as usize];
+      let skip = |taken: bool| if taken { SKIP } else { 0 };
+      FETCH + match *instruction {
+            // One byte of display memory cleared per 4 cycles
+            Clear => 24 + 256 * 4,
+            Return => 10,
+            Jump(_) => 12,
+            Call(_) => 26,
+            SkipEqImm(x, kk) => 10 + skip(reg(x) == kk),
+            SkipNeImm(x, kk) => 10 + skip(reg(x) != kk),
+            SkipEqReg(x, y) => 14 + skip(reg(x) == reg(y)),
+            SkipNeReg(x, y) => 14 + skip(reg(x) != reg(y)),
+            LoadImm(..) => 6,
+            AddImm(..) => 10,
+            // The ALU instructions are assembled into a scratch routine before running
+            Move(..) | Or(..) | And(..) | Xor(..) | Add(..) | Sub(..)
+                  | ShiftRight(..) | SubN(..) | ShiftLeft(..) => 44,
+            LoadI(_) => 12,
+            // Crossing into another page takes a long branch; the offset
+            // register is V0, or VX with the jump quirk
+            JumpOffset(addr) => {
+                  let offset = if chip.quirks.jump_uses_vx { reg((addr >> 8) as u8) } else { reg(0) };
+                  22 + if (addr + offset as u16) & 0xFF00 != addr & 0xFF00 { 2 } else { 0 }
+            },
+            Random(..) => 36,
+            // Sprite rows are shifted into place one bit at a time; a draw
+            // stalled until vertical blank only costs the fetch
+            Draw(x, _, rows) => if chip.waiting_for_vblank() {
+                  0
+            } else {
+                  let rows = if rows == 0 { 16 } else { rows as u32 };
+                  46 + rows * (20 + 4 * (reg(x) as u32 & 0x7))
+            },
+            SkipKey(x) => 14 + skip(chip.keypad[reg(x) as usize & 0xF]),
+            SkipNotKey(x) => 14 + skip(!chip.keypad[reg(x) as usize & 0xF]),
+            LoadDelay(_) | SetDelay(_) | SetSound(_) => 10,
+            // Per poll of the keypad
+            WaitKey(_) => 19,
+            AddI(_) => 16,
+            Font(_) => 16,
+            // Each digit is found by repeated subtraction
+            Bcd(x) => {
+                  let v = reg(x) as u32;
+                  80 + 16 * (v / 100 + v / 10 % 10 + v % 10)
+            },
+            StoreRegs(x) | LoadRegs(x) => 14 + 14 * (x as u32 + 1),
+            _ => 6
+      }
+}
@@ -0,0 +1,97 @@
+#![cfg(feature = "std")]
+
+/*
+* The bundled .SRC files must assemble to exactly the ROMs shipped next to
+* them, and every disassembled ROM must assemble back to itself.
+*/
+
+extern crate chip8;
+
+use std::path::Path;
+
+use chip8::assembler::{assemble, AsmError, Assembler};
+use chip8::disassembler::{disassemble, Syntax};
+
+#[test]
+fn sources_assemble_to_their_roms() {
+      let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("roms");
+      let mut count = 0;
+      for entry in std::fs::read_dir(dir.join("sources")).expect("roms/sources is missing") {
+            let path = entry.unwrap().path();
+            let name = path.file_stem().unwrap().to_string_lossy().to_lowercase();
+            let rom = std::fs::read(dir.join(format!("{}.rom", name))).expect("no ROM for source");
+            let program = Assembler::new().assemble_file(&path)
+                  .unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+            assert_eq!(program.base, 0x200, "{}", name);
+            assert!(program.data == rom, "{} assembles to different bytes", name);
+            count += 1;
+      }
+      assert_eq!(count, 10);
+}
+
+#[test]
+fn disassembled_roms_assemble_back() {
+      let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("roms");
+      for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if !path.is_file() {
+                  continue;
+            }
+            let rom = std::fs::read(&path).unwrap();
+            let source = disassemble("rom", &rom, 0x200, Syntax::Chipper);
+            let program = assemble(&source).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+            assert!(program.data == rom, "{} does not round-trip", path.display());
+      }
+}
+
+#[test]
+fn blinky_builds_for_super_chip() {
+      let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("roms/sources/BLINKY.SRC");
+      let program = Assembler::new().define("SUPER").assemble_file(&path).unwrap();
+      // START turns on high resolution before anything else
+      let start = program.labels["START"] as usize - 0x200;
+      assert_eq!(&program.data[start..start + 2], &[0x00, 0xFF]);
+}
+
+#[test]
+fn errors_report_their_line() {
+      let cases = [
+            ("CLS\nJP NOWHERE\n", 2, "symbol `NOWHERE` is not defined"),
+            ("LD V0, 1\n\nLD V0, 256\n", 3, "256 does not fit in a byte"),
+            ("; comment\nMOVE V0, V1\n", 2, "unknown instruction `MOVE`"),
+            ("LOOP: CLS\nLOOP: CLS\n", 2, "symbol `LOOP` is already defined"),
+            ("SCR\n", 1, "`SCR` needs OPTION SCHIP11"),
+            ("IFDEF X\nCLS\n", 1, "IFDEF without ENDIF"),
+            ("X = (1 + 2\n", 1, "missing `)`")
+      ];
+      for &(source, line, message) in cases.iter() {
+            let e = assemble(source).unwrap_err();
+            assert_eq!((e.line, e.message.as_str()), (line, message), "{:?}", source);
+      }
+}
+
+#[test]
+fn files_cannot_include_themselves() {
+      let dir = std::env::temp_dir().join(format!("chip8-include-{}", std::process::id()));
+      std::fs::create_dir_all(&dir).unwrap();
+      for &(name, text) in [("SELF.SRC", "CLS\nINCLUDE SELF.SRC\n"), ("A.SRC", "CLS\nINCLUDE B.SRC\n"),
+                            ("B.SRC", "LD V0, 1\n\nINCLUDE A.SRC\n"), ("C.SRC", "CLS\n")].iter() {
+            std::fs::write(dir.join(name), text).unwrap();
+      }
+      let error = |file: &str| -> AsmError {
+            *Assembler::new().assemble_file(dir.join(file)).unwrap_err().downcast().unwrap()
+      };
+
+      let e = error("SELF.SRC");
+      assert_eq!((e.file, e.line, e.message.as_str()), (None, 2, "`SELF.SRC` includes itself"));
+      // Through another file, the error is on the line that closes the cycle
+      let e = error("A.SRC");
+      assert_eq!((e.file.as_deref(), e.line, e.message.as_str()), (Some("B.SRC"), 3, "`A.SRC` includes itself"));
+      let e = Assembler::new().include_dir(&dir).assemble("INCLUDE B.SRC\n").unwrap_err();
+      assert_eq!((e.file.as_deref(), e.line, e.message.as_str()), (Some("A.SRC"), 2, "`B.SRC` includes itself"));
+
+      // The same file twice, one after the other, is not a cycle
+      let program = Assembler::new().include_dir(&dir).assemble("INCLUDE C.SRC\nINCLUDE C.SRC\n").unwrap();
+      assert_eq!(program.data, [0x00, 0xE0, 0x00, 0xE0]);
+      std::fs::remove_dir_all(&dir).unwrap();
+}
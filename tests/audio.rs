@@ -0,0 +1,52 @@
+/*
+* Rendering the sound pattern: the default pattern is a square wave whose
+* frequency follows the pitch register, doubling every 48 steps.
+*/
+
+extern crate chip8;
+
+use chip8::audio::{Audio, SAMPLE_RATE};
+
+// Sign changes in one second of output; a square wave has two per period.
+fn transitions(audio: &mut Audio) -> usize {
+      let mut out = vec![0i16; SAMPLE_RATE as usize];
+      audio.render(true, &mut out);
+      out.windows(2).filter(|pair| pair[0] != pair[1]).count()
+}
+
+#[test]
+fn default_pattern_beeps_at_1000hz() {
+      let mut audio = Audio::default();
+      assert_eq!(audio.playback_rate(), 4000.0);
+      // 1000Hz, give or take the edge that lands between two samples
+      let edges = transitions(&mut audio);
+      assert!((1999..=2001).contains(&edges), "{} edges", edges);
+}
+
+#[test]
+fn pitch_follows_the_semitone_table() {
+      let mut audio = Audio::new();
+      audio.pitch = 64 + 48;
+      assert_eq!(audio.playback_rate(), 8000.0);
+      let edges = transitions(&mut audio);
+      assert!((3999..=4001).contains(&edges), "{} edges", edges);
+
+      audio.pitch = 64 - 48;
+      assert_eq!(audio.playback_rate(), 2000.0);
+      audio.pitch = 64 + 24;
+      assert!((audio.playback_rate() - 4000.0 * 2f64.sqrt()).abs() < 1e-9);
+      audio.pitch = 64 + 12;
+      assert!((audio.playback_rate() - 4000.0 * 2f64.powf(0.25)).abs() < 1e-9);
+}
+
+#[test]
+fn silence_holds_the_playback_position() {
+      let mut audio = Audio::new();
+      let mut out = [0i16; 10];
+      audio.render(true, &mut out);
+      let position = audio.position();
+
+      audio.render(false, &mut out);
+      assert!(out.iter().all(|&sample| sample == 0));
+      assert_eq!(audio.position(), position);
+}
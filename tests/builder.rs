@@ -0,0 +1,73 @@
+/*
+* ChipBuilder: the presets make the machines they describe, and settings no
+* machine can have are refused when building.
+*/
+
+extern crate chip8;
+
+use chip8::{ChipBuilder, ChipError, Quirks};
+use chip8::chip8::{FONT_SET, MEMORY_SIZE};
+
+#[test]
+fn presets_describe_their_machines() {
+      let default = ChipBuilder::default().build().unwrap();
+      assert_eq!(default.memory.len(), MEMORY_SIZE);
+      assert_eq!(default.stack.len(), 16);
+      assert_eq!(default.program_counter, 0x200);
+      assert_eq!(default.quirks, Quirks::default());
+      assert_eq!((default.display_width(), default.display_height()), (64, 32));
+
+      let vip = ChipBuilder::vip().build().unwrap();
+      assert_eq!((vip.memory.len(), vip.stack.len()), (0x1000, 12));
+      assert_eq!(vip.quirks, Quirks::vip());
+
+      let eti660 = ChipBuilder::eti660().build().unwrap();
+      assert_eq!(eti660.memory.len(), 0x1000);
+      assert_eq!(eti660.program_counter, 0x600);
+      assert_eq!(eti660.load_address(), 0x600);
+      assert_eq!((eti660.display_width(), eti660.display_height()), (64, 48));
+
+      let schip = ChipBuilder::schip().build().unwrap();
+      assert_eq!(schip.memory.len(), 0x1000);
+      assert_eq!(schip.quirks, Quirks::schip());
+
+      let xochip = ChipBuilder::xochip().build().unwrap();
+      assert_eq!(xochip.memory.len(), MEMORY_SIZE);
+      assert_eq!(xochip.quirks, Quirks::xochip());
+
+      for &(name, quirks) in [("default", Quirks::default()), ("chip8", Quirks::vip()),
+                              ("eti660", Quirks::vip()), ("xochip", Quirks::xochip())].iter() {
+            assert_eq!(ChipBuilder::from_name(name).unwrap().build().unwrap().quirks, quirks, "{}", name);
+      }
+      assert!(ChipBuilder::from_name("pdp11").is_none());
+}
+
+#[test]
+fn settings_apply_on_top_of_presets() {
+      let mut font = FONT_SET;
+      font[0] = 0xFF;
+      let chip = ChipBuilder::vip().stack_depth(2).font(&font, 0x100).seed(9).build().unwrap();
+      assert_eq!(chip.stack.len(), 2);
+      assert_eq!(&chip.memory[0x100..0x150], &font[..]);
+      assert_eq!(chip.rng_seed(), 9);
+      assert_eq!(chip.quirks, Quirks::vip());
+}
+
+#[test]
+fn impossible_machines_are_refused() {
+      let refused = |builder: ChipBuilder| match builder.build() {
+            Err(e) => e,
+            Ok(_) => panic!("built an impossible machine")
+      };
+      assert!(matches!(refused(ChipBuilder::new().memory_size(0)), ChipError::InvalidConfig(_)));
+      assert!(matches!(refused(ChipBuilder::new().memory_size(MEMORY_SIZE + 1)), ChipError::InvalidConfig(_)));
+      assert!(matches!(refused(ChipBuilder::new().stack_depth(0)), ChipError::InvalidConfig(_)));
+      assert_eq!(refused(ChipBuilder::vip().program_start(0xFFF)), ChipError::InvalidLoadAddress(0xFFF));
+      assert!(matches!(refused(ChipBuilder::vip().big_font(&[0; 160], 0xFA0)), ChipError::InvalidConfig(_)));
+      assert!(matches!(refused(ChipBuilder::new().display(0, 32)), ChipError::InvalidConfig(_)));
+      assert!(matches!(refused(ChipBuilder::new().hires_display(256, 64)), ChipError::InvalidConfig(_)));
+
+      // The edges themselves are fine
+      assert!(ChipBuilder::vip().program_start(0xFFE).build().is_ok());
+      assert!(ChipBuilder::new().display(128, 64).build().is_ok());
+}
@@ -0,0 +1,81 @@
+/*
+* The decode cache only changes how fast instructions are found: runs with
+* and without it end in the same state, including for programs that write
+* over code they already ran.
+*/
+
+extern crate chip8;
+
+use chip8::{Chip, ChipBuilder};
+
+fn machine(cached: bool) -> Chip {
+      let mut chip = ChipBuilder::new().seed(7).build().unwrap();
+      chip.set_decode_cache(cached);
+      chip
+}
+
+// Runs `frames` frames of 10 instructions, stopping early if the machine does.
+fn run(chip: &mut Chip, frames: usize) {
+      for _ in 0..frames {
+            for _ in 0..10 {
+                  if chip.cycle().is_err() {
+                        return;
+                  }
+            }
+            chip.tick_timers();
+      }
+}
+
+#[test]
+fn cached_runs_match_the_interpreter() {
+      let roms: [(&str, &[u8]); 4] = [("brix", include_bytes!("../roms/brix.rom")),
+                                      ("15puzzle", include_bytes!("../roms/15puzzle.rom")),
+                                      ("blinky", include_bytes!("../roms/blinky.rom")),
+                                      ("bcd", include_bytes!("../roms/bcd.rom"))];
+      for &(rom, data) in roms.iter() {
+            let mut plain = machine(false);
+            let mut cached = machine(true);
+            plain.load_rom_bytes(data).unwrap();
+            cached.load_rom_bytes(data).unwrap();
+            run(&mut plain, 600);
+            run(&mut cached, 600);
+            assert_eq!(cached.snapshot(), plain.snapshot(), "{}", rom);
+            assert_eq!(cached.instruction_count(), plain.instruction_count());
+      }
+}
+
+#[test]
+fn stores_invalidate_code_they_overwrite() {
+      // LD V0, 05; (202) ADD V1, 01; LD I, 203; LD [I], V0; JP 202
+      // The store rewrites the second byte of the ADD at 202, so the entry
+      // before the written address has to go as well.
+      let program = [0x60, 0x05, 0x71, 0x01, 0xA2, 0x03, 0xF0, 0x55, 0x12, 0x02];
+      let mut plain = machine(false);
+      let mut cached = machine(true);
+      plain.load_rom_bytes(&program).unwrap();
+      cached.load_rom_bytes(&program).unwrap();
+
+      for _ in 0..6 {
+            cached.cycle().unwrap();
+      }
+      assert_eq!(cached.registers[1], 6);
+
+      for _ in 0..6 {
+            plain.cycle().unwrap();
+      }
+      for _ in 0..14 {
+            plain.cycle().unwrap();
+            cached.cycle().unwrap();
+      }
+      assert_eq!(cached.snapshot(), plain.snapshot());
+
+      // Writing straight into memory needs invalidate_code
+      cached.memory[0x203] = 0x10;
+      cached.invalidate_code(0x203, 1);
+      plain.memory[0x203] = 0x10;
+      for _ in 0..5 {
+            plain.cycle().unwrap();
+            cached.cycle().unwrap();
+      }
+      assert_eq!(cached.registers, plain.registers);
+}
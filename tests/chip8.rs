@@ -0,0 +1,398 @@
+/*
+* The machine as a host sees it: what its timers, random numbers, ROM
+* loading and stopped states do across runs, how the opcodes whose
+* behaviour depends on the quirks differ between interpreters, and what
+* the SUPER-CHIP and XO-CHIP opcodes do to the screen and registers.
+*/
+
+extern crate chip8;
+
+use chip8::{Chip, ChipBuilder, ChipError, MachineState, Quirks};
+use chip8::chip8::{BIG_FONT_BASE, BIG_FONT_SET};
+
+fn load(program: &[u8]) -> Chip {
+      let mut chip = ChipBuilder::new().build().unwrap();
+      chip.load_rom_bytes(program).unwrap();
+      chip
+}
+
+fn load_with(quirks: Quirks, program: &[u8]) -> Chip {
+      let mut chip = ChipBuilder::new().quirks(quirks).build().unwrap();
+      chip.load_rom_bytes(program).unwrap();
+      chip
+}
+
+fn steps(chip: &mut Chip, count: usize) {
+      for _ in 0..count {
+            chip.cycle().unwrap();
+      }
+}
+
+// Runs `count` instructions, collecting V0 after each.
+fn run(chip: &mut Chip, count: usize) -> Vec<u8> {
+      (0..count).map(|_| {
+            chip.cycle().unwrap();
+            chip.registers[0]
+      }).collect()
+}
+
+#[test]
+fn timers_only_count_down_on_ticks() {
+      // LD V0, 03; LD DT, V0; LD ST, V0; LD V1, DT
+      let mut chip = load(&[0x60, 0x03, 0xF0, 0x15, 0xF0, 0x18, 0xF1, 0x07]);
+      for _ in 0..3 {
+            chip.cycle().unwrap();
+      }
+      assert_eq!((chip.delay_timer, chip.sound_timer), (3, 3));
+
+      // Any number of instructions run within a frame leave the timers alone
+      chip.cycle().unwrap();
+      assert_eq!(chip.registers[1], 3);
+
+      chip.tick_timers();
+      assert_eq!((chip.delay_timer, chip.sound_timer), (2, 2));
+      for _ in 0..5 {
+            chip.tick_timers();
+      }
+      assert_eq!((chip.delay_timer, chip.sound_timer), (0, 0));
+}
+
+#[test]
+fn seeded_random_numbers_replay() {
+      // RND V0, FF; JP 200
+      let program = [0xC0, 0xFF, 0x12, 0x00];
+      let mut chip = ChipBuilder::new().seed(42).build().unwrap();
+      chip.load_rom_bytes(&program).unwrap();
+      let first = run(&mut chip, 64);
+
+      let mut again = ChipBuilder::new().seed(42).build().unwrap();
+      again.load_rom_bytes(&program).unwrap();
+      assert_eq!(run(&mut again, 64), first);
+
+      // Reloading resets the machine, which restarts the sequence from the seed
+      again.load_rom_bytes(&program).unwrap();
+      assert_eq!(run(&mut again, 64), first);
+
+      let mut other = ChipBuilder::new().seed(43).build().unwrap();
+      other.load_rom_bytes(&program).unwrap();
+      assert_ne!(run(&mut other, 64), first);
+
+      // Picking up from a saved generator state continues the same sequence
+      let mut resumed = load(&program);
+      run(&mut chip, 10);
+      resumed.seed_rng(42);
+      resumed.set_rng_state(chip.rng_state());
+      resumed.program_counter = chip.program_counter;
+      assert_eq!(run(&mut resumed, 16), run(&mut chip, 16));
+}
+
+#[test]
+fn roms_must_fit_in_memory() {
+      let mut chip = load(&[0x12, 0x00]);
+      assert_eq!(chip.load_rom_bytes(&[]), Err(ChipError::RomEmpty));
+
+      let max = chip.memory.len() - 0x200;
+      assert_eq!(chip.load_rom_bytes(&vec![0x12; max + 1]),
+                 Err(ChipError::RomTooLarge { size: max + 1, max }));
+      // A failed load leaves the machine as it was
+      assert_eq!(chip.fetch(), 0x1200);
+
+      chip.load_rom_bytes(&vec![0x12; max]).unwrap();
+      assert_eq!(chip.memory[chip.memory.len() - 1], 0x12);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn unreadable_roms_name_their_path() {
+      let mut chip = load(&[0x12, 0x00]);
+      match chip.load_rom("no/such/rom.ch8") {
+            Err(ChipError::RomIo { path, .. }) => assert_eq!(path, "no/such/rom.ch8"),
+            other => panic!("expected an I/O error, got {:?}", other)
+      }
+}
+
+#[test]
+fn stopped_machines_report_why() {
+      // LD V0, 01; JP 202
+      let mut chip = load(&[0x60, 0x01, 0x12, 0x02]);
+      chip.cycle().unwrap();
+      assert!(chip.running());
+      chip.cycle().unwrap();
+      assert_eq!(chip.state, MachineState::Halted { pc: 0x202 });
+      // A stopped machine runs nothing, at no cost
+      assert_eq!(chip.cycle(), Ok(0));
+      assert_eq!(chip.instruction_count(), 2);
+
+      // Falling off the end of the program into zeroed memory
+      let mut chip = load(&[0x60, 0x01]);
+      chip.cycle().unwrap();
+      let error = ChipError::Runaway { pc: 0x202, opcode: 0x0000 };
+      assert_eq!(chip.cycle(), Err(error.clone()));
+      assert_eq!(chip.state, MachineState::Faulted(error));
+
+      // EXIT
+      let mut chip = load(&[0x00, 0xFD]);
+      chip.cycle().unwrap();
+      assert_eq!(chip.state, MachineState::Exited);
+
+      // ADD V0, 01; JP 200
+      let mut chip = load(&[0x70, 0x01, 0x12, 0x00]);
+      chip.set_watchdog(Some(5));
+      for _ in 0..10 {
+            chip.cycle().unwrap();
+      }
+      assert_eq!(chip.state, MachineState::WatchdogExpired { instructions: 5 });
+      assert_eq!(chip.registers[0], 3);
+}
+
+#[test]
+fn shift_quirk_picks_the_source_register() {
+      // LD V0, 02; LD V1, 81; SHR V0, V1
+      let shr = [0x60, 0x02, 0x61, 0x81, 0x80, 0x16];
+      let mut chip = load_with(Quirks::default(), &shr);
+      steps(&mut chip, 3);
+      assert_eq!((chip.registers[0], chip.registers[0xF]), (0x01, 0));
+      let mut chip = load_with(Quirks::vip(), &shr);
+      steps(&mut chip, 3);
+      assert_eq!((chip.registers[0], chip.registers[0xF]), (0x40, 1));
+
+      // LD V0, 02; LD V1, 81; SHL V0, V1
+      let shl = [0x60, 0x02, 0x61, 0x81, 0x80, 0x1E];
+      let mut chip = load_with(Quirks::default(), &shl);
+      steps(&mut chip, 3);
+      assert_eq!((chip.registers[0], chip.registers[0xF]), (0x04, 0));
+      let mut chip = load_with(Quirks::vip(), &shl);
+      steps(&mut chip, 3);
+      assert_eq!((chip.registers[0], chip.registers[0xF]), (0x02, 1));
+}
+
+#[test]
+fn load_store_quirk_moves_i() {
+      // LD I, 300; LD V0, 11; LD V1, 22; LD [I], V1; LD V0, 00; LD V1, 00; LD V1, [I]
+      let program = [0xA3, 0x00, 0x60, 0x11, 0x61, 0x22, 0xF1, 0x55, 0x60, 0x00, 0x61, 0x00, 0xF1, 0x65];
+      let mut chip = load_with(Quirks::default(), &program);
+      steps(&mut chip, 4);
+      assert_eq!((&chip.memory[0x300..0x302], chip.index), (&[0x11, 0x22][..], 0x300));
+      steps(&mut chip, 3);
+      assert_eq!((chip.registers[0], chip.registers[1], chip.index), (0x11, 0x22, 0x300));
+
+      let mut chip = load_with(Quirks::vip(), &program);
+      steps(&mut chip, 4);
+      assert_eq!((&chip.memory[0x300..0x302], chip.index), (&[0x11, 0x22][..], 0x302));
+      // Reads from past what was stored, where memory is still blank
+      steps(&mut chip, 3);
+      assert_eq!((chip.registers[0], chip.registers[1], chip.index), (0x00, 0x00, 0x304));
+}
+
+#[test]
+fn jump_quirk_picks_the_offset_register() {
+      // LD V0, 10; LD V2, 20; JP V0, 240
+      let program = [0x60, 0x10, 0x62, 0x20, 0xB2, 0x40];
+      for &(quirks, target) in [(Quirks::default(), 0x250), (Quirks::vip(), 0x250), (Quirks::schip(), 0x260)].iter() {
+            let mut chip = load_with(quirks, &program);
+            steps(&mut chip, 3);
+            assert_eq!(chip.program_counter, target, "{:?}", quirks);
+      }
+}
+
+#[test]
+fn vf_reset_quirk_clears_the_flag_on_logic() {
+      // LD VF, 05; LD V0, 0F; LD V1, F0; OR V0, V1
+      let program = [0x6F, 0x05, 0x60, 0x0F, 0x61, 0xF0, 0x80, 0x11];
+      let mut chip = load_with(Quirks::default(), &program);
+      steps(&mut chip, 4);
+      assert_eq!((chip.registers[0], chip.registers[0xF]), (0xFF, 0x05));
+      let mut chip = load_with(Quirks::vip(), &program);
+      steps(&mut chip, 4);
+      assert_eq!((chip.registers[0], chip.registers[0xF]), (0xFF, 0x00));
+}
+
+#[test]
+fn sprites_clip_or_wrap_at_the_edges() {
+      // LD V0, 3E; LD V1, 1F; LD I, 20A; DRW V0, V1, 2; JP 208; (20A) FF FF
+      let program = [0x60, 0x3E, 0x61, 0x1F, 0xA2, 0x0A, 0xD0, 0x12, 0x12, 0x08, 0xFF, 0xFF];
+      let lit = |chip: &Chip, x: usize, y: usize| chip.pixel(x, y) != 0;
+      for &quirks in [Quirks::default(), Quirks::vip()].iter() {
+            let mut chip = load_with(quirks, &program);
+            chip.tick_timers();
+            steps(&mut chip, 4);
+            assert!(lit(&chip, 62, 31) && lit(&chip, 63, 31));
+            assert!(!lit(&chip, 0, 31) && !lit(&chip, 62, 0) && !lit(&chip, 0, 0));
+      }
+      let mut chip = load_with(Quirks::xochip(), &program);
+      steps(&mut chip, 4);
+      assert!(lit(&chip, 62, 31) && lit(&chip, 63, 31));
+      assert!(lit(&chip, 5, 31) && lit(&chip, 62, 0) && lit(&chip, 5, 0));
+      assert!(!lit(&chip, 6, 31));
+}
+
+#[test]
+fn display_wait_quirk_stalls_draws_until_the_tick() {
+      // LD I, 206; DRW V0, V0, 1; JP 204; (206) 80
+      let program = [0xA2, 0x06, 0xD0, 0x01, 0x12, 0x04, 0x80, 0x00];
+      let mut chip = load_with(Quirks::default(), &program);
+      steps(&mut chip, 2);
+      assert_eq!((chip.program_counter, chip.pixel(0, 0)), (0x204, 1));
+
+      let mut chip = load_with(Quirks::vip(), &program);
+      steps(&mut chip, 3);
+      assert!(chip.waiting_for_vblank());
+      assert_eq!((chip.program_counter, chip.pixel(0, 0)), (0x202, 0));
+      chip.tick_timers();
+      steps(&mut chip, 1);
+      assert_eq!((chip.program_counter, chip.pixel(0, 0)), (0x204, 1));
+}
+
+// The lit pixels of the current screen.
+fn lit_pixels(chip: &Chip) -> Vec<(usize, usize)> {
+      let mut lit = Vec::new();
+      for y in 0..chip.display_height() {
+            for x in 0..chip.display_width() {
+                  if chip.pixel(x, y) != 0 {
+                        lit.push((x, y));
+                  }
+            }
+      }
+      lit
+}
+
+#[test]
+fn super_chip_scrolls_the_screen() {
+      // HIGH; LD I, 214; DRW V0, V0, 1; SCD 3; SCR; SCL; SCU 2; SCR; LOW; JP 212; (214) 80
+      let program = [0x00, 0xFF, 0xA2, 0x14, 0xD0, 0x01, 0x00, 0xC3, 0x00, 0xFB, 0x00, 0xFC,
+                     0x00, 0xD2, 0x00, 0xFB, 0x00, 0xFE, 0x12, 0x12, 0x80, 0x00];
+      let mut chip = load_with(Quirks::schip(), &program);
+      steps(&mut chip, 3);
+      assert_eq!(lit_pixels(&chip), vec![(0, 0)]);
+      steps(&mut chip, 1);
+      assert_eq!(lit_pixels(&chip), vec![(0, 3)]);
+      steps(&mut chip, 1);
+      assert_eq!(lit_pixels(&chip), vec![(4, 3)]);
+      steps(&mut chip, 1);
+      assert_eq!(lit_pixels(&chip), vec![(0, 3)]);
+      steps(&mut chip, 1);
+      assert_eq!(lit_pixels(&chip), vec![(0, 1)]);
+
+      steps(&mut chip, 1);
+      assert_eq!(lit_pixels(&chip), vec![(4, 1)]);
+
+      // Scrolling left past the edge loses the pixels: run SCL twice more
+      for expected in [vec![(0, 1)], vec![]].iter() {
+            chip.program_counter = 0x20A;
+            steps(&mut chip, 1);
+            assert_eq!(&lit_pixels(&chip), expected);
+      }
+}
+
+#[test]
+fn super_chip_switches_resolution() {
+      // HIGH; LD V0, 7F; LD V1, 3F; LD I, 20E; DRW V0, V1, 1; LOW; JP 20C; (20E) 80
+      let program = [0x00, 0xFF, 0x60, 0x7F, 0x61, 0x3F, 0xA2, 0x0E, 0xD0, 0x11, 0x00, 0xFE,
+                     0x12, 0x0C, 0x80, 0x00];
+      let mut chip = load_with(Quirks::schip(), &program);
+      steps(&mut chip, 1);
+      assert!(chip.hires);
+      assert_eq!((chip.display_width(), chip.display_height()), (128, 64));
+      steps(&mut chip, 4);
+      assert_eq!(lit_pixels(&chip), vec![(127, 63)]);
+
+      // Switching clears the screen
+      steps(&mut chip, 1);
+      assert!(!chip.hires);
+      assert_eq!((chip.display_width(), chip.display_height()), (64, 32));
+      assert_eq!(lit_pixels(&chip), vec![]);
+}
+
+#[test]
+fn super_chip_draws_16x16_sprites() {
+      // HIGH; LD I, 20C; DRW V0, V0, 0; DRW V0, V0, 0; JP 20A; (20C) 16 rows of FFFF
+      let mut program = vec![0x00, 0xFF, 0xA2, 0x0C, 0xD0, 0x00, 0xD0, 0x00, 0x12, 0x08, 0x12, 0x08];
+      program.extend_from_slice(&[0xFF; 32]);
+      let mut chip = load_with(Quirks::schip(), &program);
+      steps(&mut chip, 3);
+      let lit = lit_pixels(&chip);
+      assert_eq!(lit.len(), 16 * 16);
+      assert!(lit.contains(&(15, 15)) && !lit.contains(&(16, 0)) && !lit.contains(&(0, 16)));
+      assert_eq!(chip.registers[0xF], 0);
+
+      steps(&mut chip, 1);
+      assert_eq!(lit_pixels(&chip), vec![]);
+      assert_eq!(chip.registers[0xF], 1);
+}
+
+#[test]
+fn super_chip_big_font_and_flags() {
+      // LD V2, 07; LD HF, V2
+      let mut chip = load_with(Quirks::schip(), &[0x62, 0x07, 0xF2, 0x30]);
+      steps(&mut chip, 2);
+      assert_eq!(chip.index, BIG_FONT_BASE + 70);
+      let digit = chip.index as usize;
+      assert_eq!(&chip.memory[digit..digit + 10], &BIG_FONT_SET[70..80]);
+
+      // LD V0, 01; LD V1, 02; LD V2, 03; LD R, V2; LD V0, 00; LD V1, 00; LD V2, 00; LD V1, R
+      let program = [0x60, 0x01, 0x61, 0x02, 0x62, 0x03, 0xF2, 0x75,
+                     0x60, 0x00, 0x61, 0x00, 0x62, 0x00, 0xF1, 0x85];
+      let mut chip = load_with(Quirks::schip(), &program);
+      steps(&mut chip, 4);
+      assert_eq!(&chip.rpl[..4], &[1, 2, 3, 0]);
+      steps(&mut chip, 4);
+      // Only the registers asked for come back
+      assert_eq!(&chip.registers[..3], &[1, 2, 0]);
+}
+
+#[test]
+fn long_index_load_is_four_bytes() {
+      // LD I, long 1234; SE V0, 00; LD I, long 5678; JP 20A
+      let program = [0xF0, 0x00, 0x12, 0x34, 0x30, 0x00, 0xF0, 0x00, 0x56, 0x78, 0x12, 0x0A];
+      let mut chip = load_with(Quirks::xochip(), &program);
+      steps(&mut chip, 1);
+      assert_eq!((chip.program_counter, chip.index), (0x204, 0x1234));
+      // A taken skip steps over both words
+      steps(&mut chip, 1);
+      assert_eq!((chip.program_counter, chip.index), (0x20A, 0x1234));
+
+      // Not taken, the long load runs
+      let mut chip = load_with(Quirks::xochip(), &program);
+      chip.registers[0] = 1;
+      steps(&mut chip, 3);
+      assert_eq!((chip.program_counter, chip.index), (0x20A, 0x5678));
+}
+
+#[test]
+fn register_ranges_go_either_way() {
+      // LD V1, 01; LD V2, 02; LD V3, 03; LD I, 300; LD [I], V1-V3; LD I, 310; LD [I], V3-V1
+      let mut program = vec![0x61, 0x01, 0x62, 0x02, 0x63, 0x03, 0xA3, 0x00, 0x51, 0x32, 0xA3, 0x10, 0x53, 0x12];
+      // LD V1, 00; LD V2, 00; LD V3, 00; LD V3-V1, [I]
+      program.extend_from_slice(&[0x61, 0x00, 0x62, 0x00, 0x63, 0x00, 0x53, 0x13]);
+      let mut chip = load_with(Quirks::xochip(), &program);
+      steps(&mut chip, 5);
+      assert_eq!(&chip.memory[0x300..0x304], &[1, 2, 3, 0]);
+      // I is left alone, whatever the load/store quirk says
+      assert_eq!(chip.index, 0x300);
+      steps(&mut chip, 2);
+      assert_eq!(&chip.memory[0x310..0x314], &[3, 2, 1, 0]);
+
+      steps(&mut chip, 4);
+      assert_eq!(&chip.registers[..5], &[0, 1, 2, 3, 0]);
+      assert_eq!(chip.index, 0x310);
+}
+
+#[test]
+fn planes_select_what_draws_and_clears() {
+      // LD I, 214; PLANE 2; DRW V0, V1, 1; PLANE 3; DRW V0, V1, 1; PLANE 2; CLS; PLANE 1; CLS; JP 212; (214) 80 80
+      let program = [0xA2, 0x14, 0xF2, 0x01, 0xD0, 0x11, 0xF3, 0x01, 0xD0, 0x11, 0xF2, 0x01,
+                     0x00, 0xE0, 0xF1, 0x01, 0x00, 0xE0, 0x12, 0x12, 0x80, 0x80];
+      let mut chip = load_with(Quirks::xochip(), &program);
+      steps(&mut chip, 3);
+      assert_eq!((chip.pixel(0, 0), chip.registers[0xF]), (2, 0));
+      // Both planes take a row each: the first lights plane 1, the second
+      // turns off plane 2
+      steps(&mut chip, 2);
+      assert_eq!((chip.pixel(0, 0), chip.registers[0xF]), (1, 1));
+      assert_eq!(lit_pixels(&chip), vec![(0, 0)]);
+      // CLS only blanks the selected planes
+      steps(&mut chip, 2);
+      assert_eq!(chip.pixel(0, 0), 1);
+      steps(&mut chip, 2);
+      assert_eq!(lit_pixels(&chip), vec![]);
+}
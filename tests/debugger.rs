@@ -0,0 +1,211 @@
+#![cfg(feature = "std")]
+
+/*
+* Run control: pausing ends the frame, steps run one instruction at a time,
+* and stepping over a call runs the whole subroutine. Breakpoints stop the
+* machine at the instruction that fired them.
+*/
+
+extern crate chip8;
+
+use chip8::{Chip, ChipBuilder, Debugger};
+use chip8::debugger::{Breakpoint, Expr, Hit};
+use chip8::scheduler::Scheduler;
+
+// CALL 206; LD V1, 01; JP 204; (206) LD V0, 05; RET
+const PROGRAM: [u8; 10] = [0x22, 0x06, 0x61, 0x01, 0x12, 0x04, 0x60, 0x05, 0x00, 0xEE];
+
+// LD V3, 10; LD I, 300; LD B, V3; LD ST, V3; CLS; LD I, 302; DRW V0, V0, 1; DRW V0, V0, 1; LD V1, K
+const WATCHED: [u8; 18] = [0x63, 0x10, 0xA3, 0x00, 0xF3, 0x33, 0xF3, 0x18, 0x00, 0xE0,
+                           0xA3, 0x02, 0xD0, 0x01, 0xD0, 0x01, 0xF1, 0x0A];
+
+fn load(program: &[u8]) -> Chip {
+      let mut chip = ChipBuilder::new().build().unwrap();
+      chip.load_rom_bytes(program).unwrap();
+      chip
+}
+fn machine() -> Chip {
+      load(&PROGRAM)
+}
+
+// Runs until the debugger stops, and returns where.
+fn run_to_break(chip: &mut Chip, debugger: &mut Debugger) -> Option<Hit> {
+      let mut scheduler = Scheduler::new(60, 100);
+      debugger.resume();
+      for _ in 0..10 {
+            scheduler.run_frame_until(chip, |c| debugger.cycle(c));
+            if debugger.paused() {
+                  return debugger.hit();
+            }
+            chip.tick_timers();
+      }
+      None
+}
+
+#[test]
+fn paused_machine_only_runs_its_steps() {
+      let mut chip = machine();
+      let mut scheduler = Scheduler::new(60, 100);
+      let mut debugger = Debugger::new();
+
+      debugger.pause();
+      scheduler.run_frame_until(&mut chip, |c| debugger.cycle(c));
+      assert_eq!(chip.program_counter, 0x200);
+
+      debugger.step();
+      scheduler.run_frame_until(&mut chip, |c| debugger.cycle(c));
+      assert_eq!(chip.program_counter, 0x206);
+      assert_eq!(chip.instruction_count(), 1);
+
+      debugger.step();
+      debugger.step();
+      scheduler.run_frame_until(&mut chip, |c| debugger.cycle(c));
+      assert_eq!(chip.program_counter, 0x202);
+      assert_eq!(chip.registers[0], 5);
+      assert!(debugger.paused());
+
+      debugger.resume();
+      scheduler.run_frame_until(&mut chip, |c| debugger.cycle(c));
+      assert_eq!(chip.registers[1], 1);
+      assert!(!chip.running());
+}
+
+#[test]
+fn step_over_runs_the_whole_call() {
+      let mut chip = machine();
+      let mut scheduler = Scheduler::new(60, 100);
+      let mut debugger = Debugger::new();
+
+      debugger.pause();
+      debugger.step_over(&chip);
+      assert!(!debugger.paused());
+      scheduler.run_frame_until(&mut chip, |c| debugger.cycle(c));
+      assert!(debugger.paused());
+      assert_eq!(chip.program_counter, 0x202);
+      assert_eq!(chip.stack_pointer, 0);
+      assert_eq!(chip.registers[0], 5);
+      assert_eq!(chip.instruction_count(), 3);
+
+      // Anything other than a call is a single step
+      debugger.step_over(&chip);
+      scheduler.run_frame_until(&mut chip, |c| debugger.cycle(c));
+      assert_eq!(chip.program_counter, 0x204);
+}
+
+#[test]
+fn panel_marks_the_program_counter() {
+      let mut chip = machine();
+      let mut debugger = Debugger::new();
+      debugger.pause();
+      chip.cycle().unwrap();
+
+      let panel = debugger.describe(&chip);
+      assert!(panel.starts_with("Machine running (paused)"));
+      assert!(panel.contains("PC 0206  I 0000  SP 1"));
+      assert!(panel.contains("Stack 0202"));
+      assert!(panel.contains("> 0206  6005  LD V0, 05"));
+      assert!(panel.contains("  0200  2206  CALL 206"));
+}
+
+#[test]
+fn conditions_follow_rust_precedence() {
+      let mut chip = machine();
+      chip.registers[3] = 0x10;
+      chip.index = 0x301;
+      let eval = |text: &str| Expr::parse(text).unwrap().eval(&chip);
+
+      assert_eq!(eval("V3 == 0x10 && I > 0x300"), 1);
+      assert_eq!(eval("v3 == 16 && I > 0x301"), 0);
+      assert_eq!(eval("1 + 2 * 3 << 1"), 14);
+      assert_eq!(eval("V3 & 0x30 == 0x10"), 1);
+      assert_eq!(eval("!(PC == 0x200) || -1 < 0"), 1);
+      assert_eq!(eval("[0x200] == 0x22 && [PC + 1] == 6"), 1);
+      assert_eq!(eval("0b101 % 0"), 0);
+
+      let error = Expr::parse("V3 == (1").unwrap_err();
+      assert_eq!(error.to_string(), "bad breakpoint `V3 == (1`: missing `)`");
+      assert!(Expr::parse("VG == 1").is_err());
+      assert!(Breakpoint::parse("write").is_err());
+      assert!(Breakpoint::parse("pc 0x200 when V0").is_err());
+      assert!(Breakpoint::parse("change X").is_err());
+
+      // Addresses have to lie in memory rather than wrap around it
+      let error = Breakpoint::parse("pc 0x12345").unwrap_err();
+      assert_eq!(error.to_string(), "bad breakpoint `pc 0x12345`: address 0x12345 is outside memory");
+      assert!(Breakpoint::parse("write 0x10000").is_err());
+      assert!(Breakpoint::parse("access 65535").is_ok());
+}
+
+#[test]
+fn breakpoints_stop_at_the_instruction_that_fired_them() {
+      let mut chip = load(&WATCHED);
+      let mut debugger = Debugger::new();
+      for spec in ["pc 0x206", "write 0x301", "change I if I == 0x300", "sound", "collision", "keywait",
+                   "read 0x302 if VF == 1"].iter() {
+            debugger.add_breakpoint(Breakpoint::parse(spec).unwrap());
+      }
+
+      // PC breakpoints stop before the instruction runs, and not again when continuing
+      assert_eq!(run_to_break(&mut chip, &mut debugger), Some(Hit { breakpoint: 2, pc: 0x202 }));
+      assert_eq!(run_to_break(&mut chip, &mut debugger), Some(Hit { breakpoint: 1, pc: 0x204 }));
+      assert_eq!(chip.memory[0x302], 6);
+      assert_eq!(run_to_break(&mut chip, &mut debugger), Some(Hit { breakpoint: 0, pc: 0x206 }));
+      assert_eq!(chip.program_counter, 0x206);
+      assert_eq!(run_to_break(&mut chip, &mut debugger), Some(Hit { breakpoint: 3, pc: 0x206 }));
+      // The first sprite draws, and the second one collides; the collision
+      // comes first in the list, so it is the one reported
+      assert_eq!(run_to_break(&mut chip, &mut debugger), Some(Hit { breakpoint: 4, pc: 0x20E }));
+      assert_eq!(chip.registers[0xF], 1);
+      assert!(debugger.describe(&chip).contains("Break: collision at 020E"));
+      assert_eq!(run_to_break(&mut chip, &mut debugger), Some(Hit { breakpoint: 5, pc: 0x210 }));
+      assert_eq!(run_to_break(&mut chip, &mut debugger), None);
+}
+
+#[test]
+fn conditions_stop_when_they_turn_true() {
+      let mut chip = machine();
+      let mut debugger = Debugger::new();
+      debugger.add_breakpoint(Breakpoint::parse("if V0 == 5").unwrap());
+
+      assert_eq!(run_to_break(&mut chip, &mut debugger), Some(Hit { breakpoint: 0, pc: 0x208 }));
+      // Still true, so continuing runs on to the end
+      assert_eq!(run_to_break(&mut chip, &mut debugger), None);
+      assert!(!chip.running());
+}
+
+#[test]
+fn breakpoints_must_fit_the_machine() {
+      let chip = ChipBuilder::vip().build().unwrap();
+      for spec in ["pc 0x5000", "write 0x2000", "access 0x1000"].iter() {
+            let error = Breakpoint::parse(spec).unwrap().check(&chip).unwrap_err();
+            assert_eq!(error.spec, *spec);
+            assert!(error.message.contains("outside"), "{}", error);
+      }
+      for spec in ["pc 0xFFE", "read 0xFFF", "change V3", "sound"].iter() {
+            assert_eq!(Breakpoint::parse(spec).unwrap().check(&chip), Ok(()));
+      }
+      // Anything past 64K is outside every machine
+      assert!(Breakpoint::parse("pc 0x10000").is_err());
+}
+
+#[test]
+fn removing_the_last_memory_breakpoint_stops_tracing() {
+      let mut chip = load(&WATCHED);
+      let mut debugger = Debugger::new();
+      debugger.add_breakpoint(Breakpoint::parse("write 0x3FF").unwrap());
+      debugger.cycle(&mut chip).unwrap();
+      assert!(chip.trace().is_some());
+
+      debugger.add_breakpoint(Breakpoint::parse("change V1").unwrap());
+      debugger.remove_breakpoint(0);
+      debugger.cycle(&mut chip).unwrap();
+      assert!(chip.trace().is_none());
+
+      debugger.remove_breakpoint(0);
+      debugger.add_breakpoint(Breakpoint::parse("sound").unwrap());
+      debugger.cycle(&mut chip).unwrap();
+      assert!(chip.trace().is_some());
+      debugger.remove_breakpoint(0);
+      debugger.cycle(&mut chip).unwrap();
+      assert!(chip.trace().is_none());
+}
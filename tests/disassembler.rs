@@ -0,0 +1,64 @@
+#![cfg(feature = "std")]
+
+/*
+* The disassembler on small programs: what it takes for code and what for
+* data, and how the addresses the code refers to are labelled.
+*/
+
+extern crate chip8;
+
+use chip8::disassembler::{disassemble, Syntax};
+
+// CALL 208; LD I, 20E; JP 204; two unreached bytes; (208) LD V0, 01; RET;
+// two more unreached bytes; (20E) a sprite row pair
+const PROGRAM: [u8; 16] = [0x22, 0x08, 0xA2, 0x0E, 0x12, 0x04, 0xAB, 0xCD,
+                           0x60, 0x01, 0x00, 0xEE, 0xFF, 0x00, 0xF0, 0x90];
+
+#[test]
+fn labels_name_what_they_point_at() {
+      let source = disassemble("test.ch8", &PROGRAM, 0x200, Syntax::Chipper);
+      assert_eq!(source, "\
+; Disassembled from `test.ch8` by `chip8 disasm`
+        OPTION BINARY
+        ALIGN OFF
+
+        CALL sub_208
+        LD I, data_20E
+label_204:
+        JP label_204
+        DB #AB, #CD
+sub_208:
+        LD V0, #01
+        RET
+        DB #FF, #00
+data_20E:
+        DB #F0, #90
+");
+}
+
+#[test]
+fn octo_starts_at_main() {
+      let source = disassemble("test.ch8", &PROGRAM, 0x200, Syntax::Octo);
+      assert!(source.contains("\n: main\n        sub_208\n        i := data_20E\n: label_204\n"));
+      assert!(source.contains("\n        return\n        0xFF 0x00\n: data_20E\n        0xF0 0x90\n"));
+}
+
+#[test]
+fn unreached_and_split_code_is_data() {
+      // LD I, 203 points into the middle of the JP, which cannot take a label
+      let source = disassemble("test.ch8", &[0xA2, 0x03, 0x12, 0x02], 0x200, Syntax::Chipper);
+      assert!(source.contains("        LD I, #203\nlabel_202:\n        JP label_202\n"));
+
+      // JP 203 lands on an odd address, so the byte before it is data, and
+      // the 0nnn found there is written as bytes with its mnemonic
+      let source = disassemble("test.ch8", &[0x12, 0x03, 0x12, 0x02, 0x00], 0x200, Syntax::Chipper);
+      assert!(source.contains("        JP label_203\n        DB #12\nlabel_203:\n        DB #02, #00  ; SYS 200\n"));
+
+      // Nothing is reachable past an EXIT
+      let source = disassemble("test.ch8", &[0x00, 0xFD, 0x60, 0x01], 0x200, Syntax::Chipper);
+      assert!(source.contains("        EXIT\n        DB #60, #01\n"));
+
+      // Other load addresses keep their labels absolute
+      let source = disassemble("test.ch8", &[0x16, 0x00], 0x600, Syntax::Chipper);
+      assert!(source.contains("        ORG #600\n\nlabel_600:\n        JP label_600\n"));
+}
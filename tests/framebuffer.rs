@@ -0,0 +1,88 @@
+/*
+* The packed framebuffer: sprites XOR into rows and report collisions,
+* scrolling clips at the edges of the current resolution, and writes keep
+* track of the rows they dirtied.
+*/
+
+extern crate chip8;
+
+use chip8::framebuffer::{row_mask, Framebuffer};
+
+// A row with the pixels at columns `xs` lit.
+fn lit(xs: &[usize]) -> u128 {
+      xs.iter().fold(0, |acc, &x| acc | 1 << (127 - x))
+}
+
+#[test]
+fn xor_reports_collisions() {
+      let mut fb = Framebuffer::default();
+      assert!(!fb.xor_row(0, 3, lit(&[0, 1])));
+      assert_eq!(fb.pixel(0, 3), 1);
+
+      // Only overlapping pixels collide, and XOR turns them off
+      assert!(!fb.xor_row(0, 3, lit(&[2])));
+      assert!(fb.xor_row(0, 3, lit(&[1, 4])));
+      assert_eq!(fb.row(0, 3), lit(&[0, 2, 4]));
+
+      // Planes are independent; a pixel's colour combines them
+      assert!(!fb.xor_row(1, 3, lit(&[0])));
+      assert_eq!(fb.pixel(0, 3), 3);
+      assert_eq!(fb.pixel(2, 3), 1);
+}
+
+#[test]
+fn scrolling_clips_at_the_edges() {
+      let mut fb = Framebuffer::new();
+      fb.xor_row(0, 0, lit(&[0, 62, 63]));
+      fb.xor_row(0, 31, lit(&[5]));
+
+      // Lores: 64x32, scrolling right pushes the last columns out
+      fb.scroll(0x1, 2, 0, 64, 32);
+      assert_eq!(fb.row(0, 0), lit(&[2]));
+      assert_eq!(fb.row(0, 0) & !row_mask(64), 0);
+      // and down, the last rows
+      fb.scroll(0x1, 0, 1, 64, 32);
+      assert_eq!(fb.row(0, 0), 0);
+      assert_eq!(fb.row(0, 1), lit(&[2]));
+      assert_eq!(fb.row(0, 31), 0);
+
+      // Hires: 128x64, the same pixels stay on screen
+      let mut fb = Framebuffer::new();
+      fb.xor_row(0, 0, lit(&[62, 63]));
+      fb.xor_row(0, 31, lit(&[5]));
+      fb.scroll(0x1, 2, 1, 128, 64);
+      assert_eq!(fb.row(0, 1), lit(&[64, 65]));
+      assert_eq!(fb.row(0, 32), lit(&[7]));
+      fb.scroll(0x1, -4, -1, 128, 64);
+      assert_eq!(fb.row(0, 0), lit(&[60, 61]));
+      assert_eq!(fb.row(0, 31), lit(&[3]));
+      fb.scroll(0x1, -4, 0, 128, 64);
+      assert_eq!(fb.row(0, 31), 0);
+
+      // Unselected planes stay put
+      fb.xor_row(1, 10, lit(&[0]));
+      fb.scroll(0x1, 0, 4, 128, 64);
+      assert_eq!(fb.row(1, 10), lit(&[0]));
+}
+
+#[test]
+fn writes_mark_rows_dirty() {
+      let mut fb = Framebuffer::new();
+      // Everything starts out dirty, to get drawn at least once
+      assert_eq!(fb.dirty_rows(), !0);
+      fb.clear_dirty();
+      assert!(!fb.is_dirty());
+
+      fb.xor_row(0, 5, 0);
+      assert!(!fb.is_dirty());
+      fb.xor_row(0, 5, lit(&[1]));
+      fb.xor_row(1, 9, lit(&[1]));
+      assert_eq!(fb.dirty_rows(), 1 << 5 | 1 << 9);
+      fb.clear_dirty();
+      assert_eq!(fb.dirty_rows(), 0);
+
+      fb.clear(0x2);
+      assert_eq!(fb.dirty_rows(), !0);
+      assert_eq!(fb.row(0, 5), lit(&[1]));
+      assert_eq!(fb.row(1, 9), 0);
+}
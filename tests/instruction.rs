@@ -0,0 +1,24 @@
+/*
+* Decoding and encoding are inverses over the whole opcode space.
+*/
+
+extern crate chip8;
+
+use chip8::{decode, Instruction};
+
+#[test]
+fn every_opcode_round_trips() {
+      for opcode in 0..=0xFFFFu16 {
+            let instruction = decode(opcode);
+            assert_eq!(instruction.encode(), opcode, "{:?}", instruction);
+            assert_eq!(decode(instruction.encode()), instruction);
+      }
+}
+
+#[test]
+fn sys_operands_alias_other_opcodes() {
+      assert_eq!(Instruction::Sys(0x0E0).encode(), 0x00E0);
+      assert_eq!(decode(0x00E0), Instruction::Clear);
+      assert_eq!(decode(0x0123), Instruction::Sys(0x123));
+      assert_eq!(decode(0xF000).len(), 4);
+}
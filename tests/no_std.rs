@@ -0,0 +1,33 @@
+/*
+* The core must behave the same with and without `std`. This test builds
+* examples/frames.rs against a `no_std` build of the library, in a target
+* directory of its own, and compares its report with the one this (std)
+* build produces.
+*/
+
+extern crate chip8;
+
+use std::process::Command;
+
+#[path = "../examples/frames.rs"]
+mod frames;
+
+#[test]
+fn no_std_build_draws_the_same_screens() {
+      let expected = frames::report();
+      assert!(!expected.is_empty(), "no bundled ROMs found");
+
+      let output = Command::new(env!("CARGO"))
+            .current_dir(env!("CARGO_MANIFEST_DIR"))
+            .args(["run", "--quiet", "--example", "frames", "--no-default-features", "--target-dir"])
+            .arg(concat!(env!("CARGO_TARGET_TMPDIR"), "/no_std"))
+            .output()
+            .expect("cannot run cargo");
+      assert!(output.status.success(), "no_std build failed:\n{}", String::from_utf8_lossy(&output.stderr));
+
+      let actual = String::from_utf8(output.stdout).expect("report is not UTF-8");
+      for (want, got) in expected.lines().zip(actual.lines()) {
+            assert_eq!(want, got);
+      }
+      assert_eq!(expected.lines().count(), actual.lines().count());
+}
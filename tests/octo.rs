@@ -0,0 +1,94 @@
+#![cfg(feature = "std")]
+
+/*
+* The Octo compiler against a program that uses each of its constructs,
+* and against the disassembler, whose Octo output for every bundled ROM
+* has to compile back to the same bytes.
+*/
+
+extern crate chip8;
+
+use std::path::Path;
+
+use chip8::disassembler::{disassemble, Syntax};
+use chip8::octo::compile;
+
+const PROGRAM: &str = "
+# Exercises constants, aliases, macros, calc and structured control flow
+:const SPEED 3
+:alias x v5
+:calc DOUBLE { SPEED * 2 + 1 }   # right to left: 3 * ( 2 + 1 )
+:macro bump reg { reg += SPEED }
+
+: main
+      x := 0
+      loop
+            bump x
+            if x == 9 then x := 1
+            while x != DOUBLE
+            if x < v6 begin
+                  v6 := 1
+            else
+                  v6 := 2
+            end
+      again
+      i := long data
+      sprite x x 5
+      jump main
+
+: data
+      0x3C 0x42
+      :byte { DOUBLE }
+";
+
+#[test]
+fn compiles_every_construct() {
+      let program = compile(PROGRAM).unwrap();
+      let expected = [
+            0x65, 0x00, 0x75, 0x03, 0x45, 0x09, 0x65, 0x01, 0x45, 0x09, 0x12, 0x1C,
+            0x8F, 0x50, 0x8F, 0x65, 0x3F, 0x00, 0x12, 0x18, 0x66, 0x01, 0x12, 0x1A,
+            0x66, 0x02, 0x12, 0x02, 0xF0, 0x00, 0x02, 0x24, 0xD5, 0x55, 0x12, 0x00,
+            0x3C, 0x42, 0x09
+      ];
+      assert_eq!(program.base, 0x200);
+      assert_eq!(program.data, &expected[..]);
+      assert_eq!(program.labels["main"], 0x200);
+      assert_eq!(program.label_at(0x224), Some("data"));
+      assert_eq!(program.label_at(0x202), None);
+}
+
+#[test]
+fn jumps_to_main_unless_it_comes_first() {
+      let program = compile(": helper return\n: main helper").unwrap();
+      assert_eq!(program.data, &[0x12, 0x04, 0x00, 0xEE, 0x22, 0x02][..]);
+}
+
+#[test]
+fn errors_report_their_line() {
+      let cases = [
+            (": main\n  jump nowhere\n", 2, "`nowhere` is not defined"),
+            (": main\n\n  v0 := 300\n", 3, "300 does not fit in a byte"),
+            (": main\n  else\n", 2, "`else` without `if ... begin`"),
+            (": main\n  loop\n  v0 += 1\n", 2, "`loop` without `again`"),
+            ("clear\n", 1, "the program has no `main` label")
+      ];
+      for &(source, line, message) in cases.iter() {
+            let e = compile(source).unwrap_err();
+            assert_eq!((e.line, e.message.as_str()), (line, message), "{:?}", source);
+      }
+}
+
+#[test]
+fn disassembled_roms_compile_back() {
+      let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("roms");
+      for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if !path.is_file() {
+                  continue;
+            }
+            let rom = std::fs::read(&path).unwrap();
+            let source = disassemble("rom", &rom, 0x200, Syntax::Octo);
+            let program = compile(&source).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+            assert!(program.data == rom, "{} does not round-trip", path.display());
+      }
+}
@@ -0,0 +1,1237 @@
+use chip8::{Chip, ChipError, Instruction};
+
+pub const BASE: usize = 0x200;
+pub const ROM: [u8; 280] = [
+      0x6E, 0x05, 0x65, 0x00, 0x6B, 0x06, 0x6A, 0x00, 0xA3, 0x0C, 0xDA, 0xB1, 0x7A, 0x04, 0x3A, 0x40,
+      0x12, 0x08, 0x7B, 0x02, 0x3B, 0x12, 0x12, 0x06, 0x6C, 0x20, 0x6D, 0x1F, 0xA3, 0x10, 0xDC, 0xD1,
+      0x22, 0xF6, 0x60, 0x00, 0x61, 0x00, 0xA3, 0x12, 0xD0, 0x11, 0x70, 0x08, 0xA3, 0x0E, 0xD0, 0x11,
+      0x60, 0x40, 0xF0, 0x15, 0xF0, 0x07, 0x30, 0x00, 0x12, 0x34, 0xC6, 0x0F, 0x67, 0x1E, 0x68, 0x01,
+      0x69, 0xFF, 0xA3, 0x0E, 0xD6, 0x71, 0xA3, 0x10, 0xDC, 0xD1, 0x60, 0x04, 0xE0, 0xA1, 0x7C, 0xFE,
+      0x60, 0x06, 0xE0, 0xA1, 0x7C, 0x02, 0x60, 0x3F, 0x8C, 0x02, 0xDC, 0xD1, 0xA3, 0x0E, 0xD6, 0x71,
+      0x86, 0x84, 0x87, 0x94, 0x60, 0x3F, 0x86, 0x02, 0x61, 0x1F, 0x87, 0x12, 0x47, 0x1F, 0x12, 0xAC,
+      0x46, 0x00, 0x68, 0x01, 0x46, 0x3F, 0x68, 0xFF, 0x47, 0x00, 0x69, 0x01, 0xD6, 0x71, 0x3F, 0x01,
+      0x12, 0xAA, 0x47, 0x1F, 0x12, 0xAA, 0x60, 0x05, 0x80, 0x75, 0x3F, 0x00, 0x12, 0xAA, 0x60, 0x01,
+      0xF0, 0x18, 0x80, 0x60, 0x61, 0xFC, 0x80, 0x12, 0xA3, 0x0C, 0xD0, 0x71, 0x60, 0xFE, 0x89, 0x03,
+      0x22, 0xF6, 0x75, 0x01, 0x22, 0xF6, 0x45, 0x60, 0x12, 0xDE, 0x12, 0x46, 0x69, 0xFF, 0x80, 0x60,
+      0x80, 0xC5, 0x3F, 0x01, 0x12, 0xCA, 0x61, 0x02, 0x80, 0x15, 0x3F, 0x01, 0x12, 0xE0, 0x80, 0x15,
+      0x3F, 0x01, 0x12, 0xEE, 0x80, 0x15, 0x3F, 0x01, 0x12, 0xE8, 0x60, 0x20, 0xF0, 0x18, 0xA3, 0x0E,
+      0x7E, 0xFF, 0x80, 0xE0, 0x80, 0x04, 0x61, 0x00, 0xD0, 0x11, 0x3E, 0x00, 0x12, 0x30, 0x12, 0xDE,
+      0x78, 0xFF, 0x48, 0xFE, 0x68, 0xFF, 0x12, 0xEE, 0x78, 0x01, 0x48, 0x02, 0x68, 0x01, 0x60, 0x04,
+      0xF0, 0x18, 0x69, 0xFF, 0x12, 0x70, 0xA3, 0x14, 0xF5, 0x33, 0xF2, 0x65, 0xF1, 0x29, 0x63, 0x37,
+      0x64, 0x00, 0xD3, 0x45, 0x73, 0x05, 0xF2, 0x29, 0xD3, 0x45, 0x00, 0xEE, 0xE0, 0x00, 0x80, 0x00,
+      0xFC, 0x00, 0xAA, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+fn block_0200(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(3) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x200..0x206] != ROM[0x000..0x006] {
+            return c.cycle();
+      }
+      // 200: LD VE, 05
+      { c.registers[0xE] = 0x05; }
+      c.count_instructions(1);
+      // 202: LD V5, 00
+      { c.registers[0x5] = 0x00; }
+      c.count_instructions(1);
+      // 204: LD VB, 06
+      { c.registers[0xB] = 0x06; }
+      c.count_instructions(1);
+      c.program_counter = 0x206;
+      Ok(3)
+}
+
+fn block_0206(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(1) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x206..0x208] != ROM[0x006..0x008] {
+            return c.cycle();
+      }
+      // 206: LD VA, 00
+      { c.registers[0xA] = 0x00; }
+      c.count_instructions(1);
+      c.program_counter = 0x208;
+      Ok(1)
+}
+
+fn block_0208(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(4) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x208..0x210] != ROM[0x008..0x010] {
+            return c.cycle();
+      }
+      // 208: LD I, 30C
+      { c.index = 0x30C; }
+      c.count_instructions(1);
+      // 20A: DRW VA, VB, 1
+      c.program_counter = 0x20A;
+      c.execute(Instruction::Draw(10, 11, 1))?;
+      c.count_instructions(1);
+      if !c.running() {
+            return Ok(2);
+      }
+      // 20C: ADD VA, 04
+      { c.registers[0xA] = c.registers[0xA].wrapping_add(0x04); }
+      c.count_instructions(1);
+      // 20E: SE VA, 40
+      { c.program_counter = if c.registers[0xA] == 0x40 { 0x212 } else { 0x210 }; }
+      c.count_instructions(1);
+      Ok(4)
+}
+
+fn block_0210(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(1) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x210..0x212] != ROM[0x010..0x012] {
+            return c.cycle();
+      }
+      // 210: JP 208
+      { c.program_counter = 0x208; }
+      c.count_instructions(1);
+      Ok(1)
+}
+
+fn block_0212(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(2) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x212..0x216] != ROM[0x012..0x016] {
+            return c.cycle();
+      }
+      // 212: ADD VB, 02
+      { c.registers[0xB] = c.registers[0xB].wrapping_add(0x02); }
+      c.count_instructions(1);
+      // 214: SE VB, 12
+      { c.program_counter = if c.registers[0xB] == 0x12 { 0x218 } else { 0x216 }; }
+      c.count_instructions(1);
+      Ok(2)
+}
+
+fn block_0216(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(1) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x216..0x218] != ROM[0x016..0x018] {
+            return c.cycle();
+      }
+      // 216: JP 206
+      { c.program_counter = 0x206; }
+      c.count_instructions(1);
+      Ok(1)
+}
+
+fn block_0218(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(5) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x218..0x222] != ROM[0x018..0x022] {
+            return c.cycle();
+      }
+      // 218: LD VC, 20
+      { c.registers[0xC] = 0x20; }
+      c.count_instructions(1);
+      // 21A: LD VD, 1F
+      { c.registers[0xD] = 0x1F; }
+      c.count_instructions(1);
+      // 21C: LD I, 310
+      { c.index = 0x310; }
+      c.count_instructions(1);
+      // 21E: DRW VC, VD, 1
+      c.program_counter = 0x21E;
+      c.execute(Instruction::Draw(12, 13, 1))?;
+      c.count_instructions(1);
+      if !c.running() {
+            return Ok(4);
+      }
+      // 220: CALL 2F6
+      c.program_counter = 0x220;
+      c.execute(Instruction::Call(758))?;
+      c.count_instructions(1);
+      if !c.running() {
+            return Ok(5);
+      }
+      Ok(5)
+}
+
+fn block_0222(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(7) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x222..0x230] != ROM[0x022..0x030] {
+            return c.cycle();
+      }
+      // 222: LD V0, 00
+      { c.registers[0x0] = 0x00; }
+      c.count_instructions(1);
+      // 224: LD V1, 00
+      { c.registers[0x1] = 0x00; }
+      c.count_instructions(1);
+      // 226: LD I, 312
+      { c.index = 0x312; }
+      c.count_instructions(1);
+      // 228: DRW V0, V1, 1
+      c.program_counter = 0x228;
+      c.execute(Instruction::Draw(0, 1, 1))?;
+      c.count_instructions(1);
+      if !c.running() {
+            return Ok(4);
+      }
+      // 22A: ADD V0, 08
+      { c.registers[0x0] = c.registers[0x0].wrapping_add(0x08); }
+      c.count_instructions(1);
+      // 22C: LD I, 30E
+      { c.index = 0x30E; }
+      c.count_instructions(1);
+      // 22E: DRW V0, V1, 1
+      c.program_counter = 0x22E;
+      c.execute(Instruction::Draw(0, 1, 1))?;
+      c.count_instructions(1);
+      if !c.running() {
+            return Ok(7);
+      }
+      c.program_counter = 0x230;
+      Ok(7)
+}
+
+fn block_0230(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(2) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x230..0x234] != ROM[0x030..0x034] {
+            return c.cycle();
+      }
+      // 230: LD V0, 40
+      { c.registers[0x0] = 0x40; }
+      c.count_instructions(1);
+      // 232: LD DT, V0
+      { c.delay_timer = c.registers[0x0]; }
+      c.count_instructions(1);
+      c.program_counter = 0x234;
+      Ok(2)
+}
+
+fn block_0234(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(2) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x234..0x238] != ROM[0x034..0x038] {
+            return c.cycle();
+      }
+      // 234: LD V0, DT
+      { c.registers[0x0] = c.delay_timer; }
+      c.count_instructions(1);
+      // 236: SE V0, 00
+      { c.program_counter = if c.registers[0x0] == 0x00 { 0x23A } else { 0x238 }; }
+      c.count_instructions(1);
+      Ok(2)
+}
+
+fn block_0238(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(1) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x238..0x23A] != ROM[0x038..0x03A] {
+            return c.cycle();
+      }
+      // 238: JP 234
+      { c.program_counter = 0x234; }
+      c.count_instructions(1);
+      Ok(1)
+}
+
+fn block_023a(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(6) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x23A..0x246] != ROM[0x03A..0x046] {
+            return c.cycle();
+      }
+      // 23A: RND V6, 0F
+      c.program_counter = 0x23A;
+      c.execute(Instruction::Random(6, 15))?;
+      c.count_instructions(1);
+      if !c.running() {
+            return Ok(1);
+      }
+      // 23C: LD V7, 1E
+      { c.registers[0x7] = 0x1E; }
+      c.count_instructions(1);
+      // 23E: LD V8, 01
+      { c.registers[0x8] = 0x01; }
+      c.count_instructions(1);
+      // 240: LD V9, FF
+      { c.registers[0x9] = 0xFF; }
+      c.count_instructions(1);
+      // 242: LD I, 30E
+      { c.index = 0x30E; }
+      c.count_instructions(1);
+      // 244: DRW V6, V7, 1
+      c.program_counter = 0x244;
+      c.execute(Instruction::Draw(6, 7, 1))?;
+      c.count_instructions(1);
+      if !c.running() {
+            return Ok(6);
+      }
+      c.program_counter = 0x246;
+      Ok(6)
+}
+
+fn block_0246(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(4) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x246..0x24E] != ROM[0x046..0x04E] {
+            return c.cycle();
+      }
+      // 246: LD I, 310
+      { c.index = 0x310; }
+      c.count_instructions(1);
+      // 248: DRW VC, VD, 1
+      c.program_counter = 0x248;
+      c.execute(Instruction::Draw(12, 13, 1))?;
+      c.count_instructions(1);
+      if !c.running() {
+            return Ok(2);
+      }
+      // 24A: LD V0, 04
+      { c.registers[0x0] = 0x04; }
+      c.count_instructions(1);
+      // 24C: SKNP V0
+      { c.program_counter = if !c.keypad[(c.registers[0x0] & 0xF) as usize] { 0x250 } else { 0x24E }; }
+      c.count_instructions(1);
+      Ok(4)
+}
+
+fn block_024e(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(1) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x24E..0x250] != ROM[0x04E..0x050] {
+            return c.cycle();
+      }
+      // 24E: ADD VC, FE
+      { c.registers[0xC] = c.registers[0xC].wrapping_add(0xFE); }
+      c.count_instructions(1);
+      c.program_counter = 0x250;
+      Ok(1)
+}
+
+fn block_0250(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(2) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x250..0x254] != ROM[0x050..0x054] {
+            return c.cycle();
+      }
+      // 250: LD V0, 06
+      { c.registers[0x0] = 0x06; }
+      c.count_instructions(1);
+      // 252: SKNP V0
+      { c.program_counter = if !c.keypad[(c.registers[0x0] & 0xF) as usize] { 0x256 } else { 0x254 }; }
+      c.count_instructions(1);
+      Ok(2)
+}
+
+fn block_0254(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(1) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x254..0x256] != ROM[0x054..0x056] {
+            return c.cycle();
+      }
+      // 254: ADD VC, 02
+      { c.registers[0xC] = c.registers[0xC].wrapping_add(0x02); }
+      c.count_instructions(1);
+      c.program_counter = 0x256;
+      Ok(1)
+}
+
+fn block_0256(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(12) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x256..0x26E] != ROM[0x056..0x06E] {
+            return c.cycle();
+      }
+      // 256: LD V0, 3F
+      { c.registers[0x0] = 0x3F; }
+      c.count_instructions(1);
+      // 258: AND VC, V0
+      { c.registers[0xC] &= c.registers[0x0]; }
+      c.count_instructions(1);
+      // 25A: DRW VC, VD, 1
+      c.program_counter = 0x25A;
+      c.execute(Instruction::Draw(12, 13, 1))?;
+      c.count_instructions(1);
+      if !c.running() {
+            return Ok(3);
+      }
+      // 25C: LD I, 30E
+      { c.index = 0x30E; }
+      c.count_instructions(1);
+      // 25E: DRW V6, V7, 1
+      c.program_counter = 0x25E;
+      c.execute(Instruction::Draw(6, 7, 1))?;
+      c.count_instructions(1);
+      if !c.running() {
+            return Ok(5);
+      }
+      // 260: ADD V6, V8
+      { let (v, carry) = c.registers[0x6].overflowing_add(c.registers[0x8]); c.registers[0x6] = v; c.registers[0xF] = carry as u8; }
+      c.count_instructions(1);
+      // 262: ADD V7, V9
+      { let (v, carry) = c.registers[0x7].overflowing_add(c.registers[0x9]); c.registers[0x7] = v; c.registers[0xF] = carry as u8; }
+      c.count_instructions(1);
+      // 264: LD V0, 3F
+      { c.registers[0x0] = 0x3F; }
+      c.count_instructions(1);
+      // 266: AND V6, V0
+      { c.registers[0x6] &= c.registers[0x0]; }
+      c.count_instructions(1);
+      // 268: LD V1, 1F
+      { c.registers[0x1] = 0x1F; }
+      c.count_instructions(1);
+      // 26A: AND V7, V1
+      { c.registers[0x7] &= c.registers[0x1]; }
+      c.count_instructions(1);
+      // 26C: SNE V7, 1F
+      { c.program_counter = if c.registers[0x7] != 0x1F { 0x270 } else { 0x26E }; }
+      c.count_instructions(1);
+      Ok(12)
+}
+
+fn block_026e(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(1) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x26E..0x270] != ROM[0x06E..0x070] {
+            return c.cycle();
+      }
+      // 26E: JP 2AC
+      { c.program_counter = 0x2AC; }
+      c.count_instructions(1);
+      Ok(1)
+}
+
+fn block_0270(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(1) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x270..0x272] != ROM[0x070..0x072] {
+            return c.cycle();
+      }
+      // 270: SNE V6, 00
+      { c.program_counter = if c.registers[0x6] != 0x00 { 0x274 } else { 0x272 }; }
+      c.count_instructions(1);
+      Ok(1)
+}
+
+fn block_0272(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(1) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x272..0x274] != ROM[0x072..0x074] {
+            return c.cycle();
+      }
+      // 272: LD V8, 01
+      { c.registers[0x8] = 0x01; }
+      c.count_instructions(1);
+      c.program_counter = 0x274;
+      Ok(1)
+}
+
+fn block_0274(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(1) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x274..0x276] != ROM[0x074..0x076] {
+            return c.cycle();
+      }
+      // 274: SNE V6, 3F
+      { c.program_counter = if c.registers[0x6] != 0x3F { 0x278 } else { 0x276 }; }
+      c.count_instructions(1);
+      Ok(1)
+}
+
+fn block_0276(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(1) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x276..0x278] != ROM[0x076..0x078] {
+            return c.cycle();
+      }
+      // 276: LD V8, FF
+      { c.registers[0x8] = 0xFF; }
+      c.count_instructions(1);
+      c.program_counter = 0x278;
+      Ok(1)
+}
+
+fn block_0278(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(1) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x278..0x27A] != ROM[0x078..0x07A] {
+            return c.cycle();
+      }
+      // 278: SNE V7, 00
+      { c.program_counter = if c.registers[0x7] != 0x00 { 0x27C } else { 0x27A }; }
+      c.count_instructions(1);
+      Ok(1)
+}
+
+fn block_027a(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(1) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x27A..0x27C] != ROM[0x07A..0x07C] {
+            return c.cycle();
+      }
+      // 27A: LD V9, 01
+      { c.registers[0x9] = 0x01; }
+      c.count_instructions(1);
+      c.program_counter = 0x27C;
+      Ok(1)
+}
+
+fn block_027c(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(2) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x27C..0x280] != ROM[0x07C..0x080] {
+            return c.cycle();
+      }
+      // 27C: DRW V6, V7, 1
+      c.program_counter = 0x27C;
+      c.execute(Instruction::Draw(6, 7, 1))?;
+      c.count_instructions(1);
+      if !c.running() {
+            return Ok(1);
+      }
+      // 27E: SE VF, 01
+      { c.program_counter = if c.registers[0xF] == 0x01 { 0x282 } else { 0x280 }; }
+      c.count_instructions(1);
+      Ok(2)
+}
+
+fn block_0280(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(1) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x280..0x282] != ROM[0x080..0x082] {
+            return c.cycle();
+      }
+      // 280: JP 2AA
+      { c.program_counter = 0x2AA; }
+      c.count_instructions(1);
+      Ok(1)
+}
+
+fn block_0282(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(1) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x282..0x284] != ROM[0x082..0x084] {
+            return c.cycle();
+      }
+      // 282: SNE V7, 1F
+      { c.program_counter = if c.registers[0x7] != 0x1F { 0x286 } else { 0x284 }; }
+      c.count_instructions(1);
+      Ok(1)
+}
+
+fn block_0284(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(1) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x284..0x286] != ROM[0x084..0x086] {
+            return c.cycle();
+      }
+      // 284: JP 2AA
+      { c.program_counter = 0x2AA; }
+      c.count_instructions(1);
+      Ok(1)
+}
+
+fn block_0286(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(3) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x286..0x28C] != ROM[0x086..0x08C] {
+            return c.cycle();
+      }
+      // 286: LD V0, 05
+      { c.registers[0x0] = 0x05; }
+      c.count_instructions(1);
+      // 288: SUB V0, V7
+      { let (a, b) = (c.registers[0x0], c.registers[0x7]); c.registers[0x0] = a.wrapping_sub(b); c.registers[0xF] = (a > b) as u8; }
+      c.count_instructions(1);
+      // 28A: SE VF, 00
+      { c.program_counter = if c.registers[0xF] == 0x00 { 0x28E } else { 0x28C }; }
+      c.count_instructions(1);
+      Ok(3)
+}
+
+fn block_028c(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(1) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x28C..0x28E] != ROM[0x08C..0x08E] {
+            return c.cycle();
+      }
+      // 28C: JP 2AA
+      { c.program_counter = 0x2AA; }
+      c.count_instructions(1);
+      Ok(1)
+}
+
+fn block_028e(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(10) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x28E..0x2A2] != ROM[0x08E..0x0A2] {
+            return c.cycle();
+      }
+      // 28E: LD V0, 01
+      { c.registers[0x0] = 0x01; }
+      c.count_instructions(1);
+      // 290: LD ST, V0
+      { c.sound_timer = c.registers[0x0]; }
+      c.count_instructions(1);
+      // 292: LD V0, V6
+      { c.registers[0x0] = c.registers[0x6]; }
+      c.count_instructions(1);
+      // 294: LD V1, FC
+      { c.registers[0x1] = 0xFC; }
+      c.count_instructions(1);
+      // 296: AND V0, V1
+      { c.registers[0x0] &= c.registers[0x1]; }
+      c.count_instructions(1);
+      // 298: LD I, 30C
+      { c.index = 0x30C; }
+      c.count_instructions(1);
+      // 29A: DRW V0, V7, 1
+      c.program_counter = 0x29A;
+      c.execute(Instruction::Draw(0, 7, 1))?;
+      c.count_instructions(1);
+      if !c.running() {
+            return Ok(7);
+      }
+      // 29C: LD V0, FE
+      { c.registers[0x0] = 0xFE; }
+      c.count_instructions(1);
+      // 29E: XOR V9, V0
+      { c.registers[0x9] ^= c.registers[0x0]; }
+      c.count_instructions(1);
+      // 2A0: CALL 2F6
+      c.program_counter = 0x2A0;
+      c.execute(Instruction::Call(758))?;
+      c.count_instructions(1);
+      if !c.running() {
+            return Ok(10);
+      }
+      Ok(10)
+}
+
+fn block_02a2(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(2) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x2A2..0x2A6] != ROM[0x0A2..0x0A6] {
+            return c.cycle();
+      }
+      // 2A2: ADD V5, 01
+      { c.registers[0x5] = c.registers[0x5].wrapping_add(0x01); }
+      c.count_instructions(1);
+      // 2A4: CALL 2F6
+      c.program_counter = 0x2A4;
+      c.execute(Instruction::Call(758))?;
+      c.count_instructions(1);
+      if !c.running() {
+            return Ok(2);
+      }
+      Ok(2)
+}
+
+fn block_02a6(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(1) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x2A6..0x2A8] != ROM[0x0A6..0x0A8] {
+            return c.cycle();
+      }
+      // 2A6: SNE V5, 60
+      { c.program_counter = if c.registers[0x5] != 0x60 { 0x2AA } else { 0x2A8 }; }
+      c.count_instructions(1);
+      Ok(1)
+}
+
+fn block_02a8(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(1) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x2A8..0x2AA] != ROM[0x0A8..0x0AA] {
+            return c.cycle();
+      }
+      // 2A8: JP 2DE
+      { c.program_counter = 0x2DE; }
+      c.count_instructions(1);
+      Ok(1)
+}
+
+fn block_02aa(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(1) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x2AA..0x2AC] != ROM[0x0AA..0x0AC] {
+            return c.cycle();
+      }
+      // 2AA: JP 246
+      { c.program_counter = 0x246; }
+      c.count_instructions(1);
+      Ok(1)
+}
+
+fn block_02ac(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(4) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x2AC..0x2B4] != ROM[0x0AC..0x0B4] {
+            return c.cycle();
+      }
+      // 2AC: LD V9, FF
+      { c.registers[0x9] = 0xFF; }
+      c.count_instructions(1);
+      // 2AE: LD V0, V6
+      { c.registers[0x0] = c.registers[0x6]; }
+      c.count_instructions(1);
+      // 2B0: SUB V0, VC
+      { let (a, b) = (c.registers[0x0], c.registers[0xC]); c.registers[0x0] = a.wrapping_sub(b); c.registers[0xF] = (a > b) as u8; }
+      c.count_instructions(1);
+      // 2B2: SE VF, 01
+      { c.program_counter = if c.registers[0xF] == 0x01 { 0x2B6 } else { 0x2B4 }; }
+      c.count_instructions(1);
+      Ok(4)
+}
+
+fn block_02b4(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(1) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x2B4..0x2B6] != ROM[0x0B4..0x0B6] {
+            return c.cycle();
+      }
+      // 2B4: JP 2CA
+      { c.program_counter = 0x2CA; }
+      c.count_instructions(1);
+      Ok(1)
+}
+
+fn block_02b6(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(3) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x2B6..0x2BC] != ROM[0x0B6..0x0BC] {
+            return c.cycle();
+      }
+      // 2B6: LD V1, 02
+      { c.registers[0x1] = 0x02; }
+      c.count_instructions(1);
+      // 2B8: SUB V0, V1
+      { let (a, b) = (c.registers[0x0], c.registers[0x1]); c.registers[0x0] = a.wrapping_sub(b); c.registers[0xF] = (a > b) as u8; }
+      c.count_instructions(1);
+      // 2BA: SE VF, 01
+      { c.program_counter = if c.registers[0xF] == 0x01 { 0x2BE } else { 0x2BC }; }
+      c.count_instructions(1);
+      Ok(3)
+}
+
+fn block_02bc(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(1) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x2BC..0x2BE] != ROM[0x0BC..0x0BE] {
+            return c.cycle();
+      }
+      // 2BC: JP 2E0
+      { c.program_counter = 0x2E0; }
+      c.count_instructions(1);
+      Ok(1)
+}
+
+fn block_02be(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(2) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x2BE..0x2C2] != ROM[0x0BE..0x0C2] {
+            return c.cycle();
+      }
+      // 2BE: SUB V0, V1
+      { let (a, b) = (c.registers[0x0], c.registers[0x1]); c.registers[0x0] = a.wrapping_sub(b); c.registers[0xF] = (a > b) as u8; }
+      c.count_instructions(1);
+      // 2C0: SE VF, 01
+      { c.program_counter = if c.registers[0xF] == 0x01 { 0x2C4 } else { 0x2C2 }; }
+      c.count_instructions(1);
+      Ok(2)
+}
+
+fn block_02c2(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(1) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x2C2..0x2C4] != ROM[0x0C2..0x0C4] {
+            return c.cycle();
+      }
+      // 2C2: JP 2EE
+      { c.program_counter = 0x2EE; }
+      c.count_instructions(1);
+      Ok(1)
+}
+
+fn block_02c4(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(2) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x2C4..0x2C8] != ROM[0x0C4..0x0C8] {
+            return c.cycle();
+      }
+      // 2C4: SUB V0, V1
+      { let (a, b) = (c.registers[0x0], c.registers[0x1]); c.registers[0x0] = a.wrapping_sub(b); c.registers[0xF] = (a > b) as u8; }
+      c.count_instructions(1);
+      // 2C6: SE VF, 01
+      { c.program_counter = if c.registers[0xF] == 0x01 { 0x2CA } else { 0x2C8 }; }
+      c.count_instructions(1);
+      Ok(2)
+}
+
+fn block_02c8(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(1) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x2C8..0x2CA] != ROM[0x0C8..0x0CA] {
+            return c.cycle();
+      }
+      // 2C8: JP 2E8
+      { c.program_counter = 0x2E8; }
+      c.count_instructions(1);
+      Ok(1)
+}
+
+fn block_02ca(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(9) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x2CA..0x2DC] != ROM[0x0CA..0x0DC] {
+            return c.cycle();
+      }
+      // 2CA: LD V0, 20
+      { c.registers[0x0] = 0x20; }
+      c.count_instructions(1);
+      // 2CC: LD ST, V0
+      { c.sound_timer = c.registers[0x0]; }
+      c.count_instructions(1);
+      // 2CE: LD I, 30E
+      { c.index = 0x30E; }
+      c.count_instructions(1);
+      // 2D0: ADD VE, FF
+      { c.registers[0xE] = c.registers[0xE].wrapping_add(0xFF); }
+      c.count_instructions(1);
+      // 2D2: LD V0, VE
+      { c.registers[0x0] = c.registers[0xE]; }
+      c.count_instructions(1);
+      // 2D4: ADD V0, V0
+      { let (v, carry) = c.registers[0x0].overflowing_add(c.registers[0x0]); c.registers[0x0] = v; c.registers[0xF] = carry as u8; }
+      c.count_instructions(1);
+      // 2D6: LD V1, 00
+      { c.registers[0x1] = 0x00; }
+      c.count_instructions(1);
+      // 2D8: DRW V0, V1, 1
+      c.program_counter = 0x2D8;
+      c.execute(Instruction::Draw(0, 1, 1))?;
+      c.count_instructions(1);
+      if !c.running() {
+            return Ok(8);
+      }
+      // 2DA: SE VE, 00
+      { c.program_counter = if c.registers[0xE] == 0x00 { 0x2DE } else { 0x2DC }; }
+      c.count_instructions(1);
+      Ok(9)
+}
+
+fn block_02dc(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(1) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x2DC..0x2DE] != ROM[0x0DC..0x0DE] {
+            return c.cycle();
+      }
+      // 2DC: JP 230
+      { c.program_counter = 0x230; }
+      c.count_instructions(1);
+      Ok(1)
+}
+
+fn block_02de(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(1) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x2DE..0x2E0] != ROM[0x0DE..0x0E0] {
+            return c.cycle();
+      }
+      // 2DE: JP 2DE
+      c.program_counter = 0x2DE;
+      c.execute(Instruction::Jump(734))?;
+      c.count_instructions(1);
+      if !c.running() {
+            return Ok(1);
+      }
+      Ok(1)
+}
+
+fn block_02e0(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(2) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x2E0..0x2E4] != ROM[0x0E0..0x0E4] {
+            return c.cycle();
+      }
+      // 2E0: ADD V8, FF
+      { c.registers[0x8] = c.registers[0x8].wrapping_add(0xFF); }
+      c.count_instructions(1);
+      // 2E2: SNE V8, FE
+      { c.program_counter = if c.registers[0x8] != 0xFE { 0x2E6 } else { 0x2E4 }; }
+      c.count_instructions(1);
+      Ok(2)
+}
+
+fn block_02e4(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(1) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x2E4..0x2E6] != ROM[0x0E4..0x0E6] {
+            return c.cycle();
+      }
+      // 2E4: LD V8, FF
+      { c.registers[0x8] = 0xFF; }
+      c.count_instructions(1);
+      c.program_counter = 0x2E6;
+      Ok(1)
+}
+
+fn block_02e6(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(1) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x2E6..0x2E8] != ROM[0x0E6..0x0E8] {
+            return c.cycle();
+      }
+      // 2E6: JP 2EE
+      { c.program_counter = 0x2EE; }
+      c.count_instructions(1);
+      Ok(1)
+}
+
+fn block_02e8(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(2) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x2E8..0x2EC] != ROM[0x0E8..0x0EC] {
+            return c.cycle();
+      }
+      // 2E8: ADD V8, 01
+      { c.registers[0x8] = c.registers[0x8].wrapping_add(0x01); }
+      c.count_instructions(1);
+      // 2EA: SNE V8, 02
+      { c.program_counter = if c.registers[0x8] != 0x02 { 0x2EE } else { 0x2EC }; }
+      c.count_instructions(1);
+      Ok(2)
+}
+
+fn block_02ec(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(1) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x2EC..0x2EE] != ROM[0x0EC..0x0EE] {
+            return c.cycle();
+      }
+      // 2EC: LD V8, 01
+      { c.registers[0x8] = 0x01; }
+      c.count_instructions(1);
+      c.program_counter = 0x2EE;
+      Ok(1)
+}
+
+fn block_02ee(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(4) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x2EE..0x2F6] != ROM[0x0EE..0x0F6] {
+            return c.cycle();
+      }
+      // 2EE: LD V0, 04
+      { c.registers[0x0] = 0x04; }
+      c.count_instructions(1);
+      // 2F0: LD ST, V0
+      { c.sound_timer = c.registers[0x0]; }
+      c.count_instructions(1);
+      // 2F2: LD V9, FF
+      { c.registers[0x9] = 0xFF; }
+      c.count_instructions(1);
+      // 2F4: JP 270
+      { c.program_counter = 0x270; }
+      c.count_instructions(1);
+      Ok(4)
+}
+
+fn block_02f6(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(2) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x2F6..0x2FA] != ROM[0x0F6..0x0FA] {
+            return c.cycle();
+      }
+      // 2F6: LD I, 314
+      { c.index = 0x314; }
+      c.count_instructions(1);
+      // 2F8: LD B, V5
+      c.program_counter = 0x2F8;
+      c.execute(Instruction::Bcd(5))?;
+      c.count_instructions(1);
+      if !c.running() {
+            return Ok(2);
+      }
+      Ok(2)
+}
+
+fn block_02fa(c: &mut Chip) -> Result<u32, ChipError> {
+      // Stopped, or stopping within the block: leave it to the interpreter
+      if !c.running() || !c.watchdog_allows(9) {
+            return c.cycle();
+      }
+      // Self-modified: leave it to the interpreter
+      if c.memory[0x2FA..0x30C] != ROM[0x0FA..0x10C] {
+            return c.cycle();
+      }
+      // 2FA: LD V2, [I]
+      c.program_counter = 0x2FA;
+      c.execute(Instruction::LoadRegs(2))?;
+      c.count_instructions(1);
+      if !c.running() {
+            return Ok(1);
+      }
+      // 2FC: LD F, V1
+      c.program_counter = 0x2FC;
+      c.execute(Instruction::Font(1))?;
+      c.count_instructions(1);
+      if !c.running() {
+            return Ok(2);
+      }
+      // 2FE: LD V3, 37
+      { c.registers[0x3] = 0x37; }
+      c.count_instructions(1);
+      // 300: LD V4, 00
+      { c.registers[0x4] = 0x00; }
+      c.count_instructions(1);
+      // 302: DRW V3, V4, 5
+      c.program_counter = 0x302;
+      c.execute(Instruction::Draw(3, 4, 5))?;
+      c.count_instructions(1);
+      if !c.running() {
+            return Ok(5);
+      }
+      // 304: ADD V3, 05
+      { c.registers[0x3] = c.registers[0x3].wrapping_add(0x05); }
+      c.count_instructions(1);
+      // 306: LD F, V2
+      c.program_counter = 0x306;
+      c.execute(Instruction::Font(2))?;
+      c.count_instructions(1);
+      if !c.running() {
+            return Ok(7);
+      }
+      // 308: DRW V3, V4, 5
+      c.program_counter = 0x308;
+      c.execute(Instruction::Draw(3, 4, 5))?;
+      c.count_instructions(1);
+      if !c.running() {
+            return Ok(8);
+      }
+      // 30A: RET
+      c.program_counter = 0x30A;
+      c.execute(Instruction::Return)?;
+      c.count_instructions(1);
+      if !c.running() {
+            return Ok(9);
+      }
+      Ok(9)
+}
+
+pub fn step(c: &mut Chip) -> Result<u32, ChipError> {
+      match c.program_counter {
+            0x200 => block_0200(c),
+            0x206 => block_0206(c),
+            0x208 => block_0208(c),
+            0x210 => block_0210(c),
+            0x212 => block_0212(c),
+            0x216 => block_0216(c),
+            0x218 => block_0218(c),
+            0x222 => block_0222(c),
+            0x230 => block_0230(c),
+            0x234 => block_0234(c),
+            0x238 => block_0238(c),
+            0x23A => block_023a(c),
+            0x246 => block_0246(c),
+            0x24E => block_024e(c),
+            0x250 => block_0250(c),
+            0x254 => block_0254(c),
+            0x256 => block_0256(c),
+            0x26E => block_026e(c),
+            0x270 => block_0270(c),
+            0x272 => block_0272(c),
+            0x274 => block_0274(c),
+            0x276 => block_0276(c),
+            0x278 => block_0278(c),
+            0x27A => block_027a(c),
+            0x27C => block_027c(c),
+            0x280 => block_0280(c),
+            0x282 => block_0282(c),
+            0x284 => block_0284(c),
+            0x286 => block_0286(c),
+            0x28C => block_028c(c),
+            0x28E => block_028e(c),
+            0x2A2 => block_02a2(c),
+            0x2A6 => block_02a6(c),
+            0x2A8 => block_02a8(c),
+            0x2AA => block_02aa(c),
+            0x2AC => block_02ac(c),
+            0x2B4 => block_02b4(c),
+            0x2B6 => block_02b6(c),
+            0x2BC => block_02bc(c),
+            0x2BE => block_02be(c),
+            0x2C2 => block_02c2(c),
+            0x2C4 => block_02c4(c),
+            0x2C8 => block_02c8(c),
+            0x2CA => block_02ca(c),
+            0x2DC => block_02dc(c),
+            0x2DE => block_02de(c),
+            0x2E0 => block_02e0(c),
+            0x2E4 => block_02e4(c),
+            0x2E6 => block_02e6(c),
+            0x2E8 => block_02e8(c),
+            0x2EC => block_02ec(c),
+            0x2EE => block_02ee(c),
+            0x2F6 => block_02f6(c),
+            0x2FA => block_02fa(c),
+            _ => c.cycle()
+      }
+}
@@ -0,0 +1,89 @@
+#![cfg(feature = "std")]
+
+/*
+* Recompiled code against the interpreter. `recompiled/brix.rs` is what
+* `recompiler::translate_rom` makes of roms/brix.rom, built into this test;
+* run with CHIP8_BLESS=1 to write it again after changing the recompiler.
+*/
+
+extern crate chip8;
+
+use std::env;
+use std::fs;
+
+use chip8::{Chip, MachineState, Quirks};
+use chip8::recompiler::translate_rom;
+
+mod brix {
+      include!("recompiled/brix.rs");
+}
+
+const TRANSLATION: &str = "tests/recompiled/brix.rs";
+
+fn machine() -> Chip {
+      let mut chip = Chip::new(Quirks::default());
+      chip.set_load_address(brix::BASE as u16).unwrap();
+      chip.load_rom_bytes(&brix::ROM).unwrap();
+      chip
+}
+
+fn assert_same(native: &Chip, interpreted: &Chip) {
+      assert_eq!(native.program_counter, interpreted.program_counter);
+      assert_eq!(native.registers, interpreted.registers, "at {:03X}", native.program_counter);
+      assert_eq!(native.index, interpreted.index, "at {:03X}", native.program_counter);
+      assert!(native.memory == interpreted.memory, "memory differs at {:03X}", native.program_counter);
+      assert_eq!(native.snapshot().display, interpreted.snapshot().display);
+      assert_eq!(native.state, interpreted.state);
+}
+
+#[test]
+fn translation_is_up_to_date() {
+      let source = translate_rom(&fs::read("roms/brix.rom").unwrap(), 0x200, &Quirks::default());
+      if env::var_os("CHIP8_BLESS").is_some() {
+            fs::write(TRANSLATION, &source).unwrap();
+      }
+      assert!(source == fs::read_to_string(TRANSLATION).unwrap(),
+              "{} is out of date; run the tests with CHIP8_BLESS=1", TRANSLATION);
+}
+
+#[test]
+fn blocks_run_in_lockstep_with_the_interpreter() {
+      let mut native = machine();
+      let mut interpreted = machine();
+
+      for frame in 0..600 {
+            for _ in 0..4 {
+                  brix::step(&mut native).unwrap();
+                  // Catch up one instruction at a time, to the end of the block
+                  while interpreted.instruction_count() < native.instruction_count() {
+                        interpreted.cycle().unwrap();
+                  }
+                  assert_eq!(interpreted.instruction_count(), native.instruction_count());
+                  assert_same(&native, &interpreted);
+            }
+            native.tick_timers();
+            interpreted.tick_timers();
+            // Move the paddle now and then
+            let key = frame / 50 % 2 == 0;
+            native.keypad[4] = key;
+            interpreted.keypad[4] = key;
+      }
+      assert!(native.instruction_count() > 2000);
+}
+
+#[test]
+fn watchdog_stops_native_code_on_the_same_instruction() {
+      let mut native = machine();
+      let mut interpreted = machine();
+      native.set_watchdog(Some(1001));
+      interpreted.set_watchdog(Some(1001));
+
+      while native.running() {
+            brix::step(&mut native).unwrap();
+      }
+      while interpreted.running() {
+            interpreted.cycle().unwrap();
+      }
+      assert_eq!(native.state, MachineState::WatchdogExpired { instructions: 1001 });
+      assert_same(&native, &interpreted);
+}
@@ -0,0 +1,43 @@
+/*
+* Rewinding a running machine: every state popped back out of the deltas
+* matches the snapshot taken at that frame.
+*/
+
+extern crate chip8;
+
+use chip8::{ChipBuilder, Snapshot};
+use chip8::rewind::Rewind;
+
+#[test]
+fn popping_replays_the_frames_backwards() {
+      let mut chip = ChipBuilder::new().build().unwrap();
+      chip.load_rom_bytes(include_bytes!("../roms/brix.rom")).unwrap();
+      let mut rewind = Rewind::new(16);
+      assert!(rewind.is_empty());
+
+      let mut frames: Vec<Snapshot> = Vec::new();
+      for _ in 0..24 {
+            for _ in 0..10 {
+                  chip.cycle().unwrap();
+            }
+            chip.tick_timers();
+            frames.push(chip.snapshot());
+            rewind.push(&chip.snapshot());
+      }
+      // The oldest states fell out of the buffer
+      assert_eq!(rewind.len(), 16);
+      assert!(rewind.memory_used() < 2 * frames[0].to_bytes().len());
+
+      for expected in frames[8..23].iter().rev() {
+            let state = rewind.pop().unwrap();
+            assert_eq!(&state, expected);
+      }
+      // The oldest state stays, so holding rewind stops there
+      assert_eq!(rewind.len(), 1);
+      assert_eq!(rewind.pop(), None);
+
+      chip.restore(&frames[8]).unwrap();
+      assert_eq!(chip.snapshot(), frames[8]);
+      rewind.clear();
+      assert!(rewind.is_empty());
+}
@@ -0,0 +1,83 @@
+/*
+* Save states: the bytes read back into the same state, a restored machine
+* remembers why it stopped and how far it got, and anything that is not a
+* whole state of this version is rejected.
+*/
+
+extern crate chip8;
+
+use chip8::{ChipBuilder, ChipError, MachineState, Snapshot, SnapshotError};
+
+// LD V0, 05; LD I, 300; RET, with nothing to return to
+const PROGRAM: [u8; 6] = [0x60, 0x05, 0xA3, 0x00, 0x00, 0xEE];
+
+fn faulted() -> Snapshot {
+      let mut chip = ChipBuilder::vip().build().unwrap();
+      chip.load_rom_bytes(&PROGRAM).unwrap();
+      for _ in 0..2 {
+            chip.cycle().unwrap();
+      }
+      assert!(chip.cycle().is_err());
+      chip.snapshot()
+}
+
+#[test]
+fn bytes_read_back_into_the_same_state() {
+      let state = faulted();
+      assert_eq!(Snapshot::from_bytes(&state.to_bytes()), Ok(state.clone()));
+
+      let mut chip = ChipBuilder::vip().build().unwrap();
+      chip.restore(&state).unwrap();
+      assert_eq!(chip.state, MachineState::Faulted(ChipError::StackUnderflow { pc: 0x204, opcode: 0x00EE }));
+      assert_eq!(chip.instruction_count(), 2);
+      assert_eq!(chip.registers[0], 5);
+      assert_eq!(chip.snapshot(), state);
+}
+
+#[test]
+fn every_stopped_state_survives_a_restore() {
+      let mut chip = ChipBuilder::vip().build().unwrap();
+      chip.load_rom_bytes(&PROGRAM).unwrap();
+      let states = [MachineState::Halted { pc: 0x202 }, MachineState::Exited,
+                    MachineState::WatchdogExpired { instructions: 1 },
+                    MachineState::Faulted(ChipError::MemoryOutOfBounds { pc: 0x202, opcode: 0xF065, addr: 0x1000 }),
+                    MachineState::Faulted(ChipError::RomIo { path: String::from("roms/gone.rom"), cause: String::from("not found") }),
+                    MachineState::Faulted(ChipError::RomTooLarge { size: 0x1000, max: 0xE00 }),
+                    MachineState::Faulted(ChipError::InvalidConfig(String::from("no stack")))];
+      for state in states.iter() {
+            chip.state = state.clone();
+            let bytes = chip.snapshot().to_bytes();
+            chip.state = MachineState::Running;
+            chip.restore(&Snapshot::from_bytes(&bytes).unwrap()).unwrap();
+            assert_eq!(&chip.state, state);
+      }
+}
+
+#[test]
+fn other_data_is_not_a_save_state() {
+      let mut bytes = faulted().to_bytes();
+      bytes[0] = b'X';
+      assert_eq!(Snapshot::from_bytes(&bytes), Err(SnapshotError::BadMagic));
+      assert_eq!(Snapshot::from_bytes(b"CH8"), Err(SnapshotError::BadMagic));
+      assert_eq!(Snapshot::from_bytes(&[]), Err(SnapshotError::BadMagic));
+}
+
+#[test]
+fn other_versions_are_rejected() {
+      let mut bytes = faulted().to_bytes();
+      for &version in [1, 3, 0xFFFF].iter() {
+            bytes[4] = version as u8;
+            bytes[5] = (version >> 8) as u8;
+            assert_eq!(Snapshot::from_bytes(&bytes), Err(SnapshotError::UnsupportedVersion(version)));
+      }
+}
+
+#[test]
+fn truncated_states_are_rejected_at_every_length() {
+      let bytes = faulted().to_bytes();
+      for len in 6..bytes.len() {
+            assert_eq!(Snapshot::from_bytes(&bytes[..len]), Err(SnapshotError::Truncated), "{} bytes", len);
+      }
+      // Cut inside the version
+      assert_eq!(Snapshot::from_bytes(&bytes[..5]), Err(SnapshotError::Truncated));
+}
@@ -0,0 +1,57 @@
+/*
+* VIP timing: what a short program costs in machine cycles, instruction by
+* instruction, including a BNNN that crosses a page and a draw stalled on
+* the display wait quirk.
+*/
+
+extern crate chip8;
+
+use chip8::{Chip, ChipBuilder, Quirks};
+use chip8::timing::Timing;
+
+// (200) LD V0, F0; LD V2, 20; JP V0, 210; ... (230) JP 230; ... (300) DRW V2, V2, 1; JP 302
+fn program() -> Vec<u8> {
+      let mut rom = vec![0; 0x104];
+      rom[..6].copy_from_slice(&[0x60, 0xF0, 0x62, 0x20, 0xB2, 0x10]);
+      rom[0x30..0x32].copy_from_slice(&[0x12, 0x30]);
+      rom[0x100..].copy_from_slice(&[0xD2, 0x21, 0x13, 0x02]);
+      rom
+}
+
+fn machine(quirks: Quirks) -> Chip {
+      let mut chip = ChipBuilder::vip().quirks(quirks).build().unwrap();
+      chip.timing = Timing::Vip;
+      chip.load_rom_bytes(&program()).unwrap();
+      chip
+}
+
+#[test]
+fn vip_costs_follow_the_interpreter() {
+      let mut chip = machine(Quirks::vip());
+      let mut costs = Vec::new();
+      for _ in 0..3 {
+            costs.push(chip.cycle().unwrap());
+      }
+      // 200 + F0 is in the next page
+      assert_eq!(chip.program_counter, 0x300);
+      // No vertical blank yet, so the draw only costs its fetch, and runs again
+      costs.push(chip.cycle().unwrap());
+      assert_eq!(chip.program_counter, 0x300);
+      chip.tick_timers();
+      costs.push(chip.cycle().unwrap());
+      costs.push(chip.cycle().unwrap());
+      assert!(!chip.running());
+
+      // Loads, the long branch, the stalled and the real draw, and the jump
+      assert_eq!(costs, [46, 46, 64, 40, 106, 52]);
+      assert_eq!(costs.iter().sum::<u32>(), 354);
+}
+
+#[test]
+fn jump_quirk_offsets_by_vx_for_the_page_check() {
+      let mut chip = machine(Quirks { jump_uses_vx: true, ..Quirks::vip() });
+      let costs: Vec<u32> = (0..3).map(|_| chip.cycle().unwrap()).collect();
+      // 210 + V2 stays in the page, although 210 + V0 would not
+      assert_eq!(chip.program_counter, 0x230);
+      assert_eq!(costs, [46, 46, 62]);
+}